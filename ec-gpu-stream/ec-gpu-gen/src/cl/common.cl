@@ -15,6 +15,54 @@
   typedef unsigned char uchar;
 
   #define CUDA
+#elif defined(EC_GPU_HOST_C) // Plain C, the kernels are run on CPU threads
+  #include <math.h>
+  #include <stdbool.h>
+  #include <stdint.h>
+
+  typedef unsigned char uchar;
+  typedef unsigned short ushort;
+  typedef unsigned int uint;
+  typedef uint64_t ulong;
+
+  #define DEVICE static
+  #define GLOBAL
+  // The launcher finds the kernels in the preprocessed source by this attribute.
+  #define KERNEL __attribute__((used))
+  #define LOCAL
+  #define CONSTANT static const
+
+  // The position of the work-item that is run by the current thread. It's set by the launcher
+  // (see `ec_gpu_gen::host`) before the kernel is called.
+  typedef struct {
+    uint global_id;
+    uint group_id;
+    uint local_id;
+    uint local_size;
+    // Blocks until all work-items of the group reached the barrier.
+    void (*barrier)(void*);
+    void* barrier_data;
+  } ec_gpu_host_work_item;
+
+  static _Thread_local ec_gpu_host_work_item ec_gpu_host_item;
+
+  void ec_gpu_host_enter(const ec_gpu_host_work_item* item) {
+    ec_gpu_host_item = *item;
+  }
+
+  #define GET_GLOBAL_ID() ec_gpu_host_item.global_id
+  #define GET_GROUP_ID() ec_gpu_host_item.group_id
+  #define GET_LOCAL_ID() ec_gpu_host_item.local_id
+  #define GET_LOCAL_SIZE() ec_gpu_host_item.local_size
+  #define BARRIER_LOCAL() ec_gpu_host_item.barrier(ec_gpu_host_item.barrier_data)
+
+  // OpenCL built-ins that are used by the kernels.
+  #define min(a, b) ((a) < (b) ? (a) : (b))
+  #define max(a, b) ((a) > (b) ? (a) : (b))
+
+  static inline ulong mad_hi(ulong a, ulong b, ulong c) {
+    return (ulong)(((unsigned __int128)a * b) >> 64) + c;
+  }
 #else // OpenCL
   #define DEVICE
   #define GLOBAL __global
@@ -0,0 +1,86 @@
+// Decompression of points in the compressed format of zcash, which is also used by `blstrs`.
+//
+// The x-coordinate is stored in big-endian in POINT_COMPRESSED_BYTES bytes. The three most
+// significant bits of the first byte are flags: the compression flag (always set), the infinity
+// flag and the sort flag. The sort flag is set if y is the lexicographically largest of the two
+// square roots, i.e. if y > (p - 1) / 2.
+
+#define POINT_COMPRESSION_FLAG 0x80
+#define POINT_INFINITY_FLAG 0x40
+#define POINT_SORT_FLAG 0x20
+#define POINT_FLAGS (POINT_COMPRESSION_FLAG | POINT_INFINITY_FLAG | POINT_SORT_FLAG)
+
+// Reads the big-endian encoding without the flags, the result is not in Montgomery form.
+DEVICE FIELD POINT_read_compressed(GLOBAL uchar *bytes) {
+  FIELD res = FIELD_ZERO;
+  for(uint i = 0; i < POINT_COMPRESSED_BYTES; i++) {
+    FIELD_limb byte = bytes[POINT_COMPRESSED_BYTES - 1 - i];
+    if(i == POINT_COMPRESSED_BYTES - 1) byte &= ~POINT_FLAGS & 0xff;
+    const uint limb = 8 * i / FIELD_LIMB_BITS;
+    const uint shift = 8 * i % FIELD_LIMB_BITS;
+    res.val[limb] |= (byte << shift) & FIELD_LIMB_MASK;
+    // With unsaturated limbs a byte might be split across two limbs.
+    if(shift + 8 > FIELD_LIMB_BITS)
+      res.val[limb + 1] |= byte >> (FIELD_LIMB_BITS - shift);
+  }
+  return res;
+}
+
+// Returns whether `r * p` is the point at infinity, where `r` is the modulus of the scalar field.
+DEVICE bool POINT_in_subgroup(POINT_affine p) {
+  POINT_jacobian res = POINT_ZERO;
+  for(uint i = 0; i < EXPONENT_BITS; i++) {
+    res = POINT_double(res);
+    if(EXPONENT_get_bit(EXPONENT_P, i))
+      res = POINT_add_mixed(res, p);
+  }
+  const FIELD local_zero = FIELD_ZERO;
+  return FIELD_eq(res.z, local_zero);
+}
+
+// Decompresses `n` points. `valid[i]` is set to 1 if the encoding of the point is valid, to 0
+// otherwise. The point at infinity is `(0, 0)`, like in `blstrs`. Invalid points are `(0, 0)` as
+// well.
+KERNEL void POINT_decompress(GLOBAL uchar *compressed,
+                        GLOBAL POINT_affine *points,
+                        GLOBAL uint *valid,
+                        uint n,
+                        uint check_subgroup) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+
+  GLOBAL uchar *bytes = compressed + gid * POINT_COMPRESSED_BYTES;
+  const uchar flags = bytes[0] & POINT_FLAGS;
+  const FIELD local_zero = FIELD_ZERO;
+  const FIELD x_repr = POINT_read_compressed(bytes);
+  POINT_affine res;
+  res.x = local_zero;
+  res.y = local_zero;
+  points[gid] = res;
+  valid[gid] = 0;
+
+  if(!(flags & POINT_COMPRESSION_FLAG)) return;
+  if(flags & POINT_INFINITY_FLAG) {
+    // The sort flag and the x-coordinate must not be set for the point at infinity.
+    valid[gid] = !(flags & POINT_SORT_FLAG) && FIELD_eq(x_repr, local_zero);
+    return;
+  }
+  // The x-coordinate must be canonical.
+  if(FIELD_gte(x_repr, FIELD_P)) return;
+
+  // y^2 = x^3 + a * x + b
+  res.x = FIELD_mont(x_repr);
+  const FIELD curve_a = POINT_A;
+  const FIELD curve_b = POINT_B;
+  FIELD yy = FIELD_mul(FIELD_add(FIELD_sqr(res.x), curve_a), res.x);
+  yy = FIELD_add(yy, curve_b);
+  if(!FIELD_sqrt(yy, &res.y)) return;
+
+  const bool largest = !FIELD_gte(FIELD_P_MINUS_1_OVER_2, FIELD_unmont(res.y));
+  if(largest != ((flags & POINT_SORT_FLAG) != 0))
+    res.y = FIELD_sub(local_zero, res.y);
+
+  if(check_subgroup && !POINT_in_subgroup(res)) return;
+  points[gid] = res;
+  valid[gid] = 1;
+}
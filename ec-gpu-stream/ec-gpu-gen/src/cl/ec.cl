@@ -1,4 +1,7 @@
 // Elliptic curve operations (Short Weierstrass Jacobian form)
+// The curve is y^2 = x^3 + POINT_A * x + POINT_B. If POINT_A_IS_ZERO is defined, the faster
+// doubling formula for a = 0 is used. If XYZZ_BUCKETS is defined, the multiexp accumulates its
+// buckets in XYZZ coordinates instead of Jacobian ones.
 
 #define POINT_ZERO ((POINT_jacobian){FIELD_ZERO, FIELD_ONE, FIELD_ZERO})
 
@@ -13,6 +16,7 @@ typedef struct {
   FIELD z;
 } POINT_jacobian;
 
+#ifdef POINT_A_IS_ZERO
 // http://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#doubling-dbl-2009-l
 DEVICE POINT_jacobian POINT_double(POINT_jacobian inp) {
   const FIELD local_zero = FIELD_ZERO;
@@ -40,6 +44,38 @@ DEVICE POINT_jacobian POINT_double(POINT_jacobian inp) {
 
   return inp;
 }
+#else
+// http://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian.html#doubling-dbl-2007-bl
+DEVICE POINT_jacobian POINT_double(POINT_jacobian inp) {
+  const FIELD local_zero = FIELD_ZERO;
+  if(FIELD_eq(inp.z, local_zero)) {
+      return inp;
+  }
+
+  const FIELD curve_a = POINT_A;
+  const FIELD xx = FIELD_sqr(inp.x); // XX = X1^2
+  const FIELD yy = FIELD_sqr(inp.y); // YY = Y1^2
+  FIELD yyyy = FIELD_sqr(yy); // YYYY = YY^2
+  const FIELD zz = FIELD_sqr(inp.z); // ZZ = Z1^2
+
+  // S = 2*((X1+YY)^2-XX-YYYY)
+  FIELD s = FIELD_add(inp.x, yy);
+  s = FIELD_sqr(s); s = FIELD_sub(FIELD_sub(s, xx), yyyy); s = FIELD_double(s);
+
+  // M = 3*XX+a*ZZ^2
+  const FIELD m = FIELD_add(FIELD_add(FIELD_double(xx), xx), FIELD_mul(curve_a, FIELD_sqr(zz)));
+
+  // Z3 = (Y1+Z1)^2-YY-ZZ
+  inp.z = FIELD_add(inp.y, inp.z); inp.z = FIELD_sub(FIELD_sub(FIELD_sqr(inp.z), yy), zz);
+  inp.x = FIELD_sub(FIELD_sub(FIELD_sqr(m), s), s); // X3 = M^2-2*S
+
+  // Y3 = M*(S-X3)-8*YYYY
+  yyyy = FIELD_double(yyyy); yyyy = FIELD_double(yyyy); yyyy = FIELD_double(yyyy);
+  inp.y = FIELD_sub(FIELD_mul(FIELD_sub(s, inp.x), m), yyyy);
+
+  return inp;
+}
+#endif
 
 // http://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#addition-madd-2007-bl
 DEVICE POINT_jacobian POINT_add_mixed(POINT_jacobian a, POINT_affine b) {
@@ -118,3 +154,138 @@ DEVICE POINT_jacobian POINT_add(POINT_jacobian a, POINT_jacobian b) {
     return a;
   }
 }
+
+#ifdef XYZZ_BUCKETS
+#define POINT_XYZZ_ZERO ((POINT_xyzz){FIELD_ONE, FIELD_ONE, FIELD_ZERO, FIELD_ZERO})
+
+typedef struct {
+  FIELD x;
+  FIELD y;
+  FIELD zz;
+  FIELD zzz;
+} POINT_xyzz; // Represents: x = X/ZZ, y = Y/ZZZ, ZZ^3 = ZZZ^2
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-shortw-xyzz.html#doubling-mdbl-2008-s-1
+DEVICE POINT_xyzz POINT_xyzz_double_affine(POINT_affine inp) {
+  const FIELD u = FIELD_double(inp.y); // U = 2*Y1
+  const FIELD v = FIELD_sqr(u); // V = U^2
+  const FIELD w = FIELD_mul(u, v); // W = U*V
+  const FIELD s = FIELD_mul(inp.x, v); // S = X1*V
+
+  // M = 3*X1^2+a
+  const FIELD xx = FIELD_sqr(inp.x);
+  FIELD m = FIELD_add(FIELD_double(xx), xx);
+#ifndef POINT_A_IS_ZERO
+  const FIELD curve_a = POINT_A;
+  m = FIELD_add(m, curve_a);
+#endif
+
+  POINT_xyzz ret;
+  ret.x = FIELD_sub(FIELD_sub(FIELD_sqr(m), s), s); // X3 = M^2-2*S
+  ret.y = FIELD_sub(FIELD_mul(m, FIELD_sub(s, ret.x)), FIELD_mul(w, inp.y)); // Y3 = M*(S-X3)-W*Y1
+  ret.zz = v; // ZZ3 = V
+  ret.zzz = w; // ZZZ3 = W
+  return ret;
+}
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-shortw-xyzz.html#doubling-dbl-2008-s-1
+DEVICE POINT_xyzz POINT_xyzz_double(POINT_xyzz inp) {
+  const FIELD local_zero = FIELD_ZERO;
+  if(FIELD_eq(inp.zz, local_zero)) {
+      return inp;
+  }
+
+  const FIELD u = FIELD_double(inp.y); // U = 2*Y1
+  const FIELD v = FIELD_sqr(u); // V = U^2
+  const FIELD w = FIELD_mul(u, v); // W = U*V
+  const FIELD s = FIELD_mul(inp.x, v); // S = X1*V
+
+  // M = 3*X1^2+a*ZZ1^2
+  const FIELD xx = FIELD_sqr(inp.x);
+  FIELD m = FIELD_add(FIELD_double(xx), xx);
+#ifndef POINT_A_IS_ZERO
+  const FIELD curve_a = POINT_A;
+  m = FIELD_add(m, FIELD_mul(curve_a, FIELD_sqr(inp.zz)));
+#endif
+
+  inp.zz = FIELD_mul(v, inp.zz); // ZZ3 = V*ZZ1
+  inp.zzz = FIELD_mul(w, inp.zzz); // ZZZ3 = W*ZZZ1
+  const FIELD wy = FIELD_mul(w, inp.y);
+  inp.x = FIELD_sub(FIELD_sub(FIELD_sqr(m), s), s); // X3 = M^2-2*S
+  inp.y = FIELD_sub(FIELD_mul(m, FIELD_sub(s, inp.x)), wy); // Y3 = M*(S-X3)-W*Y1
+  return inp;
+}
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-shortw-xyzz.html#addition-madd-2008-s
+DEVICE POINT_xyzz POINT_xyzz_add_mixed(POINT_xyzz a, POINT_affine b) {
+  const FIELD local_zero = FIELD_ZERO;
+  if(FIELD_eq(a.zz, local_zero)) {
+    const FIELD local_one = FIELD_ONE;
+    a.x = b.x;
+    a.y = b.y;
+    a.zz = local_one;
+    a.zzz = local_one;
+    return a;
+  }
+
+  const FIELD u2 = FIELD_mul(b.x, a.zz); // U2 = X2*ZZ1
+  const FIELD s2 = FIELD_mul(b.y, a.zzz); // S2 = Y2*ZZZ1
+  const FIELD p = FIELD_sub(u2, a.x); // P = U2-X1
+  const FIELD r = FIELD_sub(s2, a.y); // R = S2-Y1
+
+  if(FIELD_eq(p, local_zero) && FIELD_eq(r, local_zero)) {
+      return POINT_xyzz_double_affine(b);
+  }
+
+  const FIELD pp = FIELD_sqr(p); // PP = P^2
+  const FIELD ppp = FIELD_mul(p, pp); // PPP = P*PP
+  const FIELD q = FIELD_mul(a.x, pp); // Q = X1*PP
+
+  POINT_xyzz ret;
+  ret.x = FIELD_sub(FIELD_sub(FIELD_sub(FIELD_sqr(r), ppp), q), q); // X3 = R^2-PPP-2*Q
+  ret.y = FIELD_sub(FIELD_mul(r, FIELD_sub(q, ret.x)), FIELD_mul(a.y, ppp)); // Y3 = R*(Q-X3)-Y1*PPP
+  ret.zz = FIELD_mul(a.zz, pp); // ZZ3 = ZZ1*PP
+  ret.zzz = FIELD_mul(a.zzz, ppp); // ZZZ3 = ZZZ1*PPP
+  return ret;
+}
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-shortw-xyzz.html#addition-add-2008-s
+DEVICE POINT_xyzz POINT_xyzz_add(POINT_xyzz a, POINT_xyzz b) {
+  const FIELD local_zero = FIELD_ZERO;
+  if(FIELD_eq(a.zz, local_zero)) return b;
+  if(FIELD_eq(b.zz, local_zero)) return a;
+
+  const FIELD u1 = FIELD_mul(a.x, b.zz); // U1 = X1*ZZ2
+  const FIELD u2 = FIELD_mul(b.x, a.zz); // U2 = X2*ZZ1
+  const FIELD s1 = FIELD_mul(a.y, b.zzz); // S1 = Y1*ZZZ2
+  const FIELD s2 = FIELD_mul(b.y, a.zzz); // S2 = Y2*ZZZ1
+  const FIELD p = FIELD_sub(u2, u1); // P = U2-U1
+  const FIELD r = FIELD_sub(s2, s1); // R = S2-S1
+
+  if(FIELD_eq(p, local_zero) && FIELD_eq(r, local_zero)) {
+      return POINT_xyzz_double(a);
+  }
+
+  const FIELD pp = FIELD_sqr(p); // PP = P^2
+  const FIELD ppp = FIELD_mul(p, pp); // PPP = P*PP
+  const FIELD q = FIELD_mul(u1, pp); // Q = U1*PP
+
+  a.x = FIELD_sub(FIELD_sub(FIELD_sub(FIELD_sqr(r), ppp), q), q); // X3 = R^2-PPP-2*Q
+  a.y = FIELD_sub(FIELD_mul(r, FIELD_sub(q, a.x)), FIELD_mul(s1, ppp)); // Y3 = R*(Q-X3)-S1*PPP
+  a.zz = FIELD_mul(FIELD_mul(a.zz, b.zz), pp); // ZZ3 = ZZ1*ZZ2*PP
+  a.zzz = FIELD_mul(FIELD_mul(a.zzz, b.zzz), ppp); // ZZZ3 = ZZZ1*ZZZ2*PPP
+  return a;
+}
+
+// The representation of the points that is used by the multiexp kernel.
+typedef POINT_xyzz POINT_projective;
+#define POINT_PROJECTIVE_ZERO POINT_XYZZ_ZERO
+#define POINT_projective_add_mixed POINT_xyzz_add_mixed
+#define POINT_projective_add POINT_xyzz_add
+#else
+// The representation of the points that is used by the multiexp kernel.
+typedef POINT_jacobian POINT_projective;
+#define POINT_PROJECTIVE_ZERO POINT_ZERO
+#define POINT_projective_add_mixed POINT_add_mixed
+#define POINT_projective_add POINT_add
+#endif
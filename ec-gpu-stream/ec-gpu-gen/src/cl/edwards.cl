@@ -0,0 +1,93 @@
+// Elliptic curve operations (Twisted Edwards extended coordinates)
+// The curve is POINT_A * x^2 + y^2 = 1 + POINT_D * x^2 * y^2. The formulas are complete if
+// POINT_A is a square and POINT_D is not, hence there are no special cases.
+
+#define POINT_ZERO ((POINT_extended){FIELD_ZERO, FIELD_ONE, FIELD_ZERO, FIELD_ONE})
+
+typedef struct {
+  FIELD x;
+  FIELD y;
+} POINT_affine;
+
+typedef struct {
+  FIELD x;
+  FIELD y;
+  FIELD t;
+  FIELD z;
+} POINT_extended; // Represents: x = X/Z, y = Y/Z, x * y = T/Z
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-twisted-extended.html#doubling-dbl-2008-hwcd
+DEVICE POINT_extended POINT_double(POINT_extended inp) {
+  const FIELD curve_a = POINT_A;
+  const FIELD a = FIELD_sqr(inp.x); // A = X1^2
+  const FIELD b = FIELD_sqr(inp.y); // B = Y1^2
+  const FIELD c = FIELD_double(FIELD_sqr(inp.z)); // C = 2*Z1^2
+  const FIELD d = FIELD_mul(curve_a, a); // D = a*A
+
+  // E = (X1+Y1)^2-A-B
+  FIELD e = FIELD_add(inp.x, inp.y);
+  e = FIELD_sqr(e); e = FIELD_sub(FIELD_sub(e, a), b);
+
+  const FIELD g = FIELD_add(d, b); // G = D+B
+  const FIELD f = FIELD_sub(g, c); // F = G-C
+  const FIELD h = FIELD_sub(d, b); // H = D-B
+
+  inp.x = FIELD_mul(e, f); // X3 = E*F
+  inp.y = FIELD_mul(g, h); // Y3 = G*H
+  inp.t = FIELD_mul(e, h); // T3 = E*H
+  inp.z = FIELD_mul(f, g); // Z3 = F*G
+  return inp;
+}
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-twisted-extended.html#addition-madd-2008-hwcd
+DEVICE POINT_extended POINT_add_mixed(POINT_extended a, POINT_affine b) {
+  const FIELD curve_a = POINT_A;
+  const FIELD curve_d = POINT_D;
+  const FIELD aa = FIELD_mul(a.x, b.x); // A = X1*X2
+  const FIELD bb = FIELD_mul(a.y, b.y); // B = Y1*Y2
+  const FIELD c = FIELD_mul(FIELD_mul(a.t, curve_d), FIELD_mul(b.x, b.y)); // C = T1*d*T2
+
+  // E = (X1+Y1)*(X2+Y2)-A-B
+  FIELD e = FIELD_mul(FIELD_add(a.x, a.y), FIELD_add(b.x, b.y));
+  e = FIELD_sub(FIELD_sub(e, aa), bb);
+
+  const FIELD f = FIELD_sub(a.z, c); // F = Z1-C
+  const FIELD g = FIELD_add(a.z, c); // G = Z1+C
+  const FIELD h = FIELD_sub(bb, FIELD_mul(curve_a, aa)); // H = B-a*A
+
+  a.x = FIELD_mul(e, f); // X3 = E*F
+  a.y = FIELD_mul(g, h); // Y3 = G*H
+  a.t = FIELD_mul(e, h); // T3 = E*H
+  a.z = FIELD_mul(f, g); // Z3 = F*G
+  return a;
+}
+
+// http://www.hyperelliptic.org/EFD/g1p/auto-twisted-extended.html#addition-add-2008-hwcd
+DEVICE POINT_extended POINT_add(POINT_extended a, POINT_extended b) {
+  const FIELD curve_a = POINT_A;
+  const FIELD curve_d = POINT_D;
+  const FIELD aa = FIELD_mul(a.x, b.x); // A = X1*X2
+  const FIELD bb = FIELD_mul(a.y, b.y); // B = Y1*Y2
+  const FIELD c = FIELD_mul(FIELD_mul(a.t, curve_d), b.t); // C = T1*d*T2
+  const FIELD d = FIELD_mul(a.z, b.z); // D = Z1*Z2
+
+  // E = (X1+Y1)*(X2+Y2)-A-B
+  FIELD e = FIELD_mul(FIELD_add(a.x, a.y), FIELD_add(b.x, b.y));
+  e = FIELD_sub(FIELD_sub(e, aa), bb);
+
+  const FIELD f = FIELD_sub(d, c); // F = D-C
+  const FIELD g = FIELD_add(d, c); // G = D+C
+  const FIELD h = FIELD_sub(bb, FIELD_mul(curve_a, aa)); // H = B-a*A
+
+  a.x = FIELD_mul(e, f); // X3 = E*F
+  a.y = FIELD_mul(g, h); // Y3 = G*H
+  a.t = FIELD_mul(e, h); // T3 = E*H
+  a.z = FIELD_mul(f, g); // Z3 = F*G
+  return a;
+}
+
+// The representation of the points that is used by the multiexp kernel.
+typedef POINT_extended POINT_projective;
+#define POINT_PROJECTIVE_ZERO POINT_ZERO
+#define POINT_projective_add_mixed POINT_add_mixed
+#define POINT_projective_add POINT_add
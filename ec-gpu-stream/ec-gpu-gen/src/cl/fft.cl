@@ -6,7 +6,7 @@ KERNEL void FIELD_radix_fft(GLOBAL FIELD* x, // Source buffer
                       GLOBAL FIELD* pq, // Precalculated twiddle factors
                       GLOBAL FIELD* omegas, // [omega, omega^2, omega^4, ...]
                       LOCAL FIELD* u_arg, // Local buffer to store intermediary values
-                      uint n, // Number of elements
+                      uint n, // Number of elements of a single FFT
                       uint lgp, // Log2 of `p` (Read more in the link above)
                       uint deg, // 1=>radix2, 2=>radix4, 3=>radix8, ...
                       uint max_deg) // Maximum degree supported, according to `pq` and `omegas`
@@ -22,13 +22,15 @@ KERNEL void FIELD_radix_fft(GLOBAL FIELD* x, // Source buffer
 
   uint lid = GET_LOCAL_ID();
   uint lsize = GET_LOCAL_SIZE();
-  uint index = GET_GROUP_ID();
   uint t = n >> deg;
+  // Several FFTs of the same size may be stored one after another, each one uses `t` groups.
+  uint batch = GET_GROUP_ID() / t;
+  uint index = GET_GROUP_ID() - batch * t;
   uint p = 1 << lgp;
   uint k = index & (p - 1);
 
-  x += index;
-  y += ((index - k) << deg) + k;
+  x += batch * n + index;
+  y += batch * n + ((index - k) << deg) + k;
 
   uint count = 1 << deg; // 2^deg
   uint counth = count >> 1; // Half of count
@@ -72,5 +74,52 @@ KERNEL void FIELD_mul_by_field(GLOBAL FIELD* elements,
                         uint n,
                         FIELD field) {
   const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
   elements[gid] = FIELD_mul(elements[gid], field);
 }
+
+/// Multiplies the element at index `i` by `g^i`, where `i` is the index within its FFT
+KERNEL void FIELD_distribute_powers(GLOBAL FIELD* elements,
+                        uint n, // Number of elements of all FFTs
+                        uint fft_size, // Number of elements of a single FFT
+                        GLOBAL FIELD* powers) { // [g, g^2, g^4, ...]
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  const uint i = gid % fft_size;
+  elements[gid] = FIELD_mul(elements[gid], FIELD_pow_lookup(powers, i));
+}
+
+#ifdef FIELD_RADIX3_FFT
+/// A single radix-3 round of a Stockham FFT of size `n`, which follows the rounds of `FIELD_radix_fft`.
+/// All radix-2 rounds must be done before, as those need the size of the previous rounds to be a
+/// power of two.
+KERNEL void FIELD_radix3_fft(GLOBAL FIELD* x, // Source buffer
+                      GLOBAL FIELD* y, // Destination buffer
+                      GLOBAL FIELD* omegas, // [omega, omega^2, omega^4, ...]
+                      uint n, // Number of elements of a single FFT
+                      uint p, // Product of the radices of the previous rounds
+                      uint count) // Number of threads, a third of the elements of all FFTs
+{
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= count) return;
+
+  const uint third = n / 3;
+  const uint batch = gid / third;
+  const uint i = gid - batch * third;
+  const uint k = i % p;
+  x += batch * n + i;
+  y += batch * n + (i - k) * 3 + k;
+
+  // `omega^(n / 3)` is a primitive cube root of unity.
+  const FIELD w = FIELD_pow_lookup(omegas, third);
+  const FIELD w2 = FIELD_sqr(w);
+  const FIELD twiddle = FIELD_pow_lookup(omegas, third / p * k);
+  const FIELD u0 = x[0];
+  const FIELD u1 = FIELD_mul(x[third], twiddle);
+  const FIELD u2 = FIELD_mul(x[2 * third], FIELD_sqr(twiddle));
+
+  y[0] = FIELD_add(FIELD_add(u0, u1), u2);
+  y[p] = FIELD_add(FIELD_add(u0, FIELD_mul(w, u1)), FIELD_mul(w2, u2));
+  y[2 * p] = FIELD_add(FIELD_add(u0, FIELD_mul(w2, u1)), FIELD_mul(w, u2));
+}
+#endif
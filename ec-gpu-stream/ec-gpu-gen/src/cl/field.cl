@@ -2,12 +2,45 @@
 // Arbitrary size prime-field arithmetic library (add, sub, mul, pow)
 
 #define FIELD_BITS (FIELD_LIMBS * FIELD_LIMB_BITS)
-#if FIELD_LIMB_BITS == 32
-  #define FIELD_mac_with_carry mac_with_carry_32
-  #define FIELD_add_with_carry add_with_carry_32
-#elif FIELD_LIMB_BITS == 64
-  #define FIELD_mac_with_carry mac_with_carry_64
-  #define FIELD_add_with_carry add_with_carry_64
+#ifdef FIELD_UNSATURATED
+  // Only the lower FIELD_LIMB_BITS bits of a limb are used, the carries are split off with shifts
+  // and masks instead of add-with-carry instructions.
+  #define FIELD_LIMB_MASK (((FIELD_limb)1 << FIELD_LIMB_BITS) - 1)
+  #define FIELD_mac_with_carry FIELD_mac_with_carry_unsaturated
+  #define FIELD_add_with_carry FIELD_add_with_carry_unsaturated
+
+  // Returns the lower bits of `a * b + c + d`, the carry is stored in `d`.
+  #if FIELD_LIMB_BITS > 32
+    // The limbs are 64-bit integers, the result is combined from the lower and upper 64 bits.
+    DEVICE FIELD_limb FIELD_mac_with_carry_unsaturated(FIELD_limb a, FIELD_limb b, FIELD_limb c, FIELD_limb *d) {
+      ulong hi = *d;
+      ulong lo = mac_with_carry_64(a, b, c, &hi);
+      *d = (hi << (64 - FIELD_LIMB_BITS)) | (lo >> FIELD_LIMB_BITS);
+      return lo & FIELD_LIMB_MASK;
+    }
+  #else
+    DEVICE FIELD_limb FIELD_mac_with_carry_unsaturated(FIELD_limb a, FIELD_limb b, FIELD_limb c, FIELD_limb *d) {
+      ulong res = (ulong)a * b + c + *d;
+      *d = res >> FIELD_LIMB_BITS;
+      return res & FIELD_LIMB_MASK;
+    }
+  #endif
+
+  // Returns the lower bits of `a + b`, the carry is stored in `b`.
+  DEVICE FIELD_limb FIELD_add_with_carry_unsaturated(FIELD_limb a, FIELD_limb *b) {
+    FIELD_limb res = a + *b;
+    *b = res >> FIELD_LIMB_BITS;
+    return res & FIELD_LIMB_MASK;
+  }
+#else
+  #define FIELD_LIMB_MASK ((FIELD_limb)~(FIELD_limb)0)
+  #if FIELD_LIMB_BITS == 32
+    #define FIELD_mac_with_carry mac_with_carry_32
+    #define FIELD_add_with_carry add_with_carry_32
+  #elif FIELD_LIMB_BITS == 64
+    #define FIELD_mac_with_carry mac_with_carry_64
+    #define FIELD_add_with_carry add_with_carry_64
+  #endif
 #endif
 
 // Greater than or equal
@@ -30,7 +63,27 @@ DEVICE bool FIELD_eq(FIELD a, FIELD b) {
 }
 
 // Normal addition
-#if defined(OPENCL_NVIDIA) || defined(CUDA)
+#ifdef FIELD_UNSATURATED
+  DEVICE FIELD FIELD_add_(FIELD a, FIELD b) {
+    FIELD_limb carry = 0;
+    for(uchar i = 0; i < FIELD_LIMBS; i++) {
+      a.val[i] += b.val[i] + carry;
+      carry = a.val[i] >> FIELD_LIMB_BITS;
+      a.val[i] &= FIELD_LIMB_MASK;
+    }
+    return a;
+  }
+  // On a borrow the limb wraps around, which sets its most significant bit.
+  DEVICE FIELD FIELD_sub_(FIELD a, FIELD b) {
+    FIELD_limb borrow = 0;
+    for(uchar i = 0; i < FIELD_LIMBS; i++) {
+      a.val[i] -= b.val[i] + borrow;
+      borrow = a.val[i] >> (sizeof(FIELD_limb) * 8 - 1);
+      a.val[i] &= FIELD_LIMB_MASK;
+    }
+    return a;
+  }
+#elif defined(OPENCL_NVIDIA) || defined(CUDA)
   #define FIELD_add_ FIELD_add_nvidia
   #define FIELD_sub_ FIELD_sub_nvidia
 #else
@@ -281,7 +334,7 @@ DEVICE FIELD FIELD_mul_default(FIELD a, FIELD b) {
     t[FIELD_LIMBS + 1] = carry;
 
     carry = 0;
-    FIELD_limb m = FIELD_INV * t[0];
+    FIELD_limb m = (FIELD_INV * t[0]) & FIELD_LIMB_MASK;
     FIELD_mac_with_carry(m, FIELD_P.val[0], t[0], &carry);
     for(uchar j = 1; j < FIELD_LIMBS; j++)
       t[j - 1] = FIELD_mac_with_carry(m, FIELD_P.val[j], t[j], &carry);
@@ -298,7 +351,7 @@ DEVICE FIELD FIELD_mul_default(FIELD a, FIELD b) {
   return result;
 }
 
-#ifdef CUDA
+#if defined(CUDA) && !defined(FIELD_UNSATURATED)
 DEVICE FIELD FIELD_mul(FIELD a, FIELD b) {
   return FIELD_mul_nvidia(a, b);
 }
@@ -318,8 +371,8 @@ DEVICE FIELD FIELD_sqr(FIELD a) {
 // Faster version of FIELD_add(a, a)
 DEVICE FIELD FIELD_double(FIELD a) {
   for(uchar i = FIELD_LIMBS - 1; i >= 1; i--)
-    a.val[i] = (a.val[i] << 1) | (a.val[i - 1] >> (FIELD_LIMB_BITS - 1));
-  a.val[0] <<= 1;
+    a.val[i] = ((a.val[i] << 1) | (a.val[i - 1] >> (FIELD_LIMB_BITS - 1))) & FIELD_LIMB_MASK;
+  a.val[0] = (a.val[0] << 1) & FIELD_LIMB_MASK;
   if(FIELD_gte(a, FIELD_P)) a = FIELD_sub_(a, FIELD_P);
   return a;
 }
@@ -375,3 +428,69 @@ DEVICE uint FIELD_get_bits(FIELD l, uint skip, uint window) {
   }
   return ret;
 }
+
+// Exponentiation with an exponent that is as large as the field elements. The exponent is a plain
+// integer, not in Montgomery form.
+DEVICE FIELD FIELD_pow_big(FIELD base, FIELD exponent) {
+  FIELD res = FIELD_ONE;
+  for(uint i = 0; i < FIELD_BITS; i++) {
+    res = FIELD_sqr(res);
+    if(FIELD_get_bit(exponent, i))
+      res = FIELD_mul(res, base);
+  }
+  return res;
+}
+
+// Multiplicative inverse with Fermat's little theorem, `a^(p - 2)`. Zero is mapped to zero.
+DEVICE FIELD FIELD_inverse(FIELD a) {
+  return FIELD_pow_big(a, FIELD_P_MINUS_2);
+}
+
+// Legendre symbol, `a^((p - 1) / 2)`. It is 1 for non-zero squares, -1 for non-squares and 0 for
+// zero.
+DEVICE int FIELD_legendre(FIELD a) {
+  FIELD res = FIELD_pow_big(a, FIELD_P_MINUS_1_OVER_2);
+  if(FIELD_eq(res, FIELD_ZERO))
+    return 0;
+  if(FIELD_eq(res, FIELD_ONE))
+    return 1;
+  return -1;
+}
+
+#ifdef FIELD_HAS_SQRT
+// Square root with the Tonelli-Shanks algorithm, where `p - 1 = 2^S * t` with an odd `t`. Returns
+// whether `a` is a square, the root is stored in `res` if it is.
+DEVICE bool FIELD_sqrt(FIELD a, FIELD *res) {
+  if(FIELD_eq(a, FIELD_ZERO)) {
+    *res = FIELD_ZERO;
+    return true;
+  }
+  const FIELD one = FIELD_ONE;
+  FIELD w = FIELD_pow_big(a, FIELD_T_MINUS_1_OVER_2);
+  // `x = a^((t + 1) / 2)` and `b = a^t`, so that `x^2 = a * b`.
+  FIELD x = FIELD_mul(a, w);
+  FIELD b = FIELD_mul(x, w);
+  FIELD z = FIELD_ROOT_OF_UNITY;
+  uint v = FIELD_S;
+  while(!FIELD_eq(b, one)) {
+    // Find the least `k` with `b^(2^k) = 1`.
+    uint k = 0;
+    FIELD b2k = b;
+    while(!FIELD_eq(b2k, one)) {
+      b2k = FIELD_sqr(b2k);
+      k++;
+      if(k == v)
+        return false;
+    }
+    FIELD w2 = z;
+    for(uint i = 0; i < v - k - 1; i++)
+      w2 = FIELD_sqr(w2);
+    z = FIELD_sqr(w2);
+    b = FIELD_mul(b, z);
+    x = FIELD_mul(x, w2);
+    v = k;
+  }
+  *res = x;
+  return true;
+}
+#endif
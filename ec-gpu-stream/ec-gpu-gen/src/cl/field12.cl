@@ -0,0 +1,67 @@
+// Fp12 Extension Field where w^2 = v, which is the generator of the Fp6 sub-field
+
+#define FIELD12_LIMB_BITS FIELD6_LIMB_BITS
+#define FIELD12_ZERO ((FIELD12){FIELD6_ZERO, FIELD6_ZERO})
+#define FIELD12_ONE ((FIELD12){FIELD6_ONE, FIELD6_ZERO})
+
+typedef struct {
+  FIELD6 c0;
+  FIELD6 c1;
+} FIELD12; // Represents: c0 + w * c1
+
+DEVICE bool FIELD12_eq(FIELD12 a, FIELD12 b) {
+  return FIELD6_eq(a.c0, b.c0) && FIELD6_eq(a.c1, b.c1);
+}
+DEVICE FIELD12 FIELD12_sub(FIELD12 a, FIELD12 b) {
+  a.c0 = FIELD6_sub(a.c0, b.c0);
+  a.c1 = FIELD6_sub(a.c1, b.c1);
+  return a;
+}
+DEVICE FIELD12 FIELD12_add(FIELD12 a, FIELD12 b) {
+  a.c0 = FIELD6_add(a.c0, b.c0);
+  a.c1 = FIELD6_add(a.c1, b.c1);
+  return a;
+}
+DEVICE FIELD12 FIELD12_double(FIELD12 a) {
+  a.c0 = FIELD6_double(a.c0);
+  a.c1 = FIELD6_double(a.c1);
+  return a;
+}
+
+// c_0 - w * c_1, it's the inverse of elements of the cyclotomic subgroup.
+DEVICE FIELD12 FIELD12_conjugate(FIELD12 a) {
+  a.c1 = FIELD6_neg(a.c1);
+  return a;
+}
+
+/*
+ * (a_0 + w * a_1)(b_0 + w * b_1) = a_0 * b_0 + v * a_1 * b_1 + w * (a_0 * b_1 + a_1 * b_0)
+ * Therefore:
+ * c_0 = a_0 * b_0 + v * a_1 * b_1
+ * c_1 = (a_0 + a_1) * (b_0 + b_1) - a_0 * b_0 - a_1 * b_1
+ */
+DEVICE FIELD12 FIELD12_mul(FIELD12 a, FIELD12 b) {
+  const FIELD6 aa = FIELD6_mul(a.c0, b.c0);
+  const FIELD6 bb = FIELD6_mul(a.c1, b.c1);
+  const FIELD6 o = FIELD6_add(b.c0, b.c1);
+  a.c1 = FIELD6_add(a.c1, a.c0);
+  a.c1 = FIELD6_mul(a.c1, o);
+  a.c1 = FIELD6_sub(a.c1, aa);
+  a.c1 = FIELD6_sub(a.c1, bb);
+  a.c0 = FIELD6_add(aa, FIELD6_mul_by_v(bb));
+  return a;
+}
+
+/*
+ * (a_0 + w * a_1)^2 = a_0^2 + v * a_1^2 + w * 2 * a_0 * a_1
+ * Therefore:
+ * c_0 = (a_0 + a_1) * (a_0 + v * a_1) - a_0 * a_1 - v * a_0 * a_1
+ * c_1 = 2 * a_0 * a_1
+ */
+DEVICE FIELD12 FIELD12_sqr(FIELD12 a) {
+  const FIELD6 ab = FIELD6_mul(a.c0, a.c1);
+  const FIELD6 t = FIELD6_mul(FIELD6_add(a.c0, a.c1), FIELD6_add(a.c0, FIELD6_mul_by_v(a.c1)));
+  a.c0 = FIELD6_sub(FIELD6_sub(t, ab), FIELD6_mul_by_v(ab));
+  a.c1 = FIELD6_double(ab);
+  return a;
+}
@@ -0,0 +1,97 @@
+// Fp6 Extension Field where v^3 = FIELD6_NON_RESIDUE, which is an element of Fp2
+
+#define FIELD6_LIMB_BITS FIELD2_LIMB_BITS
+#define FIELD6_ZERO ((FIELD6){FIELD2_ZERO, FIELD2_ZERO, FIELD2_ZERO})
+#define FIELD6_ONE ((FIELD6){FIELD2_ONE, FIELD2_ZERO, FIELD2_ZERO})
+
+typedef struct {
+  FIELD2 c0;
+  FIELD2 c1;
+  FIELD2 c2;
+} FIELD6; // Represents: c0 + v * c1 + v^2 * c2
+
+DEVICE bool FIELD6_eq(FIELD6 a, FIELD6 b) {
+  return FIELD2_eq(a.c0, b.c0) && FIELD2_eq(a.c1, b.c1) && FIELD2_eq(a.c2, b.c2);
+}
+DEVICE FIELD6 FIELD6_sub(FIELD6 a, FIELD6 b) {
+  a.c0 = FIELD2_sub(a.c0, b.c0);
+  a.c1 = FIELD2_sub(a.c1, b.c1);
+  a.c2 = FIELD2_sub(a.c2, b.c2);
+  return a;
+}
+DEVICE FIELD6 FIELD6_add(FIELD6 a, FIELD6 b) {
+  a.c0 = FIELD2_add(a.c0, b.c0);
+  a.c1 = FIELD2_add(a.c1, b.c1);
+  a.c2 = FIELD2_add(a.c2, b.c2);
+  return a;
+}
+DEVICE FIELD6 FIELD6_double(FIELD6 a) {
+  a.c0 = FIELD2_double(a.c0);
+  a.c1 = FIELD2_double(a.c1);
+  a.c2 = FIELD2_double(a.c2);
+  return a;
+}
+DEVICE FIELD6 FIELD6_neg(FIELD6 a) {
+  return FIELD6_sub(FIELD6_ZERO, a);
+}
+
+// Multiplies an element of Fp2 with the non-residue.
+DEVICE FIELD2 FIELD6_mul_by_non_residue(FIELD2 a) {
+  return FIELD2_mul(a, FIELD6_NON_RESIDUE);
+}
+
+/*
+ * (a_0 + v * a_1 + v^2 * a_2) * v = v^3 * a_2 + v * a_0 + v^2 * a_1
+ * It's the non-residue of a quadratic extension on top of this field.
+ */
+DEVICE FIELD6 FIELD6_mul_by_v(FIELD6 a) {
+  const FIELD2 c2 = a.c2;
+  a.c2 = a.c1;
+  a.c1 = a.c0;
+  a.c0 = FIELD6_mul_by_non_residue(c2);
+  return a;
+}
+
+/*
+ * Karatsuba multiplication, with aa = a_0 * b_0, bb = a_1 * b_1 and cc = a_2 * b_2:
+ * c_0 = aa + v^3 * ((a_1 + a_2) * (b_1 + b_2) - bb - cc)
+ * c_1 = (a_0 + a_1) * (b_0 + b_1) - aa - bb + v^3 * cc
+ * c_2 = (a_0 + a_2) * (b_0 + b_2) - aa - cc + bb
+ */
+DEVICE FIELD6 FIELD6_mul(FIELD6 a, FIELD6 b) {
+  const FIELD2 aa = FIELD2_mul(a.c0, b.c0);
+  const FIELD2 bb = FIELD2_mul(a.c1, b.c1);
+  const FIELD2 cc = FIELD2_mul(a.c2, b.c2);
+
+  FIELD2 t0 = FIELD2_mul(FIELD2_add(a.c1, a.c2), FIELD2_add(b.c1, b.c2));
+  t0 = FIELD2_sub(FIELD2_sub(t0, bb), cc);
+  FIELD2 t1 = FIELD2_mul(FIELD2_add(a.c0, a.c1), FIELD2_add(b.c0, b.c1));
+  t1 = FIELD2_sub(FIELD2_sub(t1, aa), bb);
+  FIELD2 t2 = FIELD2_mul(FIELD2_add(a.c0, a.c2), FIELD2_add(b.c0, b.c2));
+  t2 = FIELD2_add(FIELD2_sub(FIELD2_sub(t2, aa), cc), bb);
+
+  a.c0 = FIELD2_add(aa, FIELD6_mul_by_non_residue(t0));
+  a.c1 = FIELD2_add(t1, FIELD6_mul_by_non_residue(cc));
+  a.c2 = t2;
+  return a;
+}
+
+/*
+ * Squaring by Chung and Hasan (CH-SQR2), with
+ * s_0 = a_0^2, s_1 = 2 * a_0 * a_1, s_2 = (a_0 - a_1 + a_2)^2, s_3 = 2 * a_1 * a_2, s_4 = a_2^2:
+ * c_0 = s_0 + v^3 * s_3
+ * c_1 = s_1 + v^3 * s_4
+ * c_2 = s_1 + s_2 + s_3 - s_0 - s_4
+ */
+DEVICE FIELD6 FIELD6_sqr(FIELD6 a) {
+  const FIELD2 s0 = FIELD2_sqr(a.c0);
+  const FIELD2 s1 = FIELD2_double(FIELD2_mul(a.c0, a.c1));
+  const FIELD2 s2 = FIELD2_sqr(FIELD2_add(FIELD2_sub(a.c0, a.c1), a.c2));
+  const FIELD2 s3 = FIELD2_double(FIELD2_mul(a.c1, a.c2));
+  const FIELD2 s4 = FIELD2_sqr(a.c2);
+
+  a.c0 = FIELD2_add(s0, FIELD6_mul_by_non_residue(s3));
+  a.c1 = FIELD2_add(s1, FIELD6_mul_by_non_residue(s4));
+  a.c2 = FIELD2_sub(FIELD2_sub(FIELD2_add(FIELD2_add(s1, s2), s3), s0), s4);
+  return a;
+}
@@ -6,12 +6,16 @@
  * window. The GPU kernel not only assigns a thread to each window but also
  * divides the bases into several groups which highly increases the number of
  * threads running in parallel for calculating a multiexp instance.
+ *
+ * It works on any curve whose source defines `POINT_affine` for the bases, `POINT_projective`
+ * for the buckets and results, `POINT_PROJECTIVE_ZERO` and the `POINT_projective_add_mixed()`
+ * and `POINT_projective_add()` functions.
  */
 
 KERNEL void POINT_multiexp(
     GLOBAL POINT_affine *bases,
-    GLOBAL POINT_jacobian *buckets,
-    GLOBAL POINT_jacobian *results,
+    GLOBAL POINT_projective *buckets,
+    GLOBAL POINT_projective *results,
     GLOBAL EXPONENT *exps,
     uint n,
     uint num_groups,
@@ -28,31 +32,34 @@ KERNEL void POINT_multiexp(
   // Each thread has its own set of buckets in global memory.
   buckets += bucket_len * gid;
 
-  const POINT_jacobian local_zero = POINT_ZERO;
+  const POINT_projective local_zero = POINT_PROJECTIVE_ZERO;
   for(uint i = 0; i < bucket_len; i++) buckets[i] = local_zero;
 
   const uint len = (uint)ceil(n / (float)num_groups); // Num of elements in each group
 
   // This thread runs the multiexp algorithm on elements from `nstart` to `nened`
-  // on the window [`bits`, `bits` + `w`)
+  // on the window [`bits`, `bits` + `w`). The least significant window comes first, like on the
+  // CPU, so that the windows can be compared.
   const uint nstart = len * (gid / num_windows);
   const uint nend = min(nstart + len, n);
   const uint bits = (gid % num_windows) * window_size;
   const ushort w = min((ushort)window_size, (ushort)(EXPONENT_BITS - bits));
+  // `EXPONENT_get_bits()` counts the bits from the most significant one.
+  const uint skip = EXPONENT_BITS - bits - w;
 
-  POINT_jacobian res = POINT_ZERO;
+  POINT_projective res = POINT_PROJECTIVE_ZERO;
   for(uint i = nstart; i < nend; i++) {
-    uint ind = EXPONENT_get_bits(exps[i], bits, w);
+    uint ind = EXPONENT_get_bits(exps[i], skip, w);
 
     #if defined(OPENCL_NVIDIA) || defined(CUDA)
       // O_o, weird optimization, having a single special case makes it
       // tremendously faster!
       // 511 is chosen because it's half of the maximum bucket len, but
       // any other number works... Bigger indices seems to be better...
-      if(ind == 511) buckets[510] = POINT_add_mixed(buckets[510], bases[i]);
-      else if(ind--) buckets[ind] = POINT_add_mixed(buckets[ind], bases[i]);
+      if(ind == 511) buckets[510] = POINT_projective_add_mixed(buckets[510], bases[i]);
+      else if(ind--) buckets[ind] = POINT_projective_add_mixed(buckets[ind], bases[i]);
     #else
-      if(ind--) buckets[ind] = POINT_add_mixed(buckets[ind], bases[i]);
+      if(ind--) buckets[ind] = POINT_projective_add_mixed(buckets[ind], bases[i]);
     #endif
   }
 
@@ -60,10 +67,10 @@ KERNEL void POINT_multiexp(
   // e.g. 3a + 2b + 1c = a +
   //                    (a) + b +
   //                    ((a) + b) + c
-  POINT_jacobian acc = POINT_ZERO;
+  POINT_projective acc = POINT_PROJECTIVE_ZERO;
   for(int j = bucket_len - 1; j >= 0; j--) {
-    acc = POINT_add(acc, buckets[j]);
-    res = POINT_add(res, acc);
+    acc = POINT_projective_add(acc, buckets[j]);
+    res = POINT_projective_add(res, acc);
   }
 
   results[gid] = res;
@@ -0,0 +1,39 @@
+// Conversion of points from Jacobian into affine coordinates.
+
+// Converts the points from Jacobian into affine coordinates. Every thread converts `segment`
+// consecutive points with Montgomery's trick, so that only a single inversion is needed per
+// segment. `scratch` holds the prefix products of the Z coordinates, it must have the size of
+// `points`. The point at infinity is mapped to `(0, 0)`, like in `blstrs`.
+KERNEL void POINT_batch_normalize(GLOBAL POINT_jacobian *points,
+                        GLOBAL POINT_affine *result,
+                        GLOBAL FIELD *scratch,
+                        uint n,
+                        uint segment) {
+  const uint start = GET_GLOBAL_ID() * segment;
+  if(start >= n) return;
+  const uint end = min(start + segment, n);
+  const FIELD local_zero = FIELD_ZERO;
+
+  FIELD acc = FIELD_ONE;
+  for(uint i = start; i < end; i++) {
+    scratch[i] = acc;
+    if(!FIELD_eq(points[i].z, local_zero)) acc = FIELD_mul(acc, points[i].z);
+  }
+
+  FIELD inv = FIELD_inverse(acc);
+  for(uint i = end; i > start; i--) {
+    const POINT_jacobian point = points[i - 1];
+    POINT_affine res;
+    if(FIELD_eq(point.z, local_zero)) {
+      res.x = local_zero;
+      res.y = local_zero;
+    } else {
+      const FIELD z_inv = FIELD_mul(inv, scratch[i - 1]);
+      inv = FIELD_mul(inv, point.z);
+      const FIELD zz_inv = FIELD_sqr(z_inv);
+      res.x = FIELD_mul(point.x, zz_inv);
+      res.y = FIELD_mul(FIELD_mul(point.y, zz_inv), z_inv);
+    }
+    result[i - 1] = res;
+  }
+}
@@ -29,3 +29,18 @@ KERNEL void test_sqr(FIELD a, GLOBAL FIELD *result) {
 KERNEL void test_double(FIELD a, GLOBAL FIELD *result) {
   *result = FIELD_double(a);
 }
+
+KERNEL void test_inverse(FIELD a, GLOBAL FIELD *result) {
+  *result = FIELD_inverse(a);
+}
+
+// The Legendre symbol is returned as field element, i.e. `-1` is `p - 1`.
+KERNEL void test_legendre(FIELD a, GLOBAL FIELD *result) {
+  int legendre = FIELD_legendre(a);
+  if(legendre == 0)
+    *result = FIELD_ZERO;
+  else if(legendre == 1)
+    *result = FIELD_ONE;
+  else
+    *result = FIELD_sub(FIELD_ZERO, FIELD_ONE);
+}
@@ -0,0 +1,75 @@
+// Element-wise operations on vectors of field elements.
+
+/// Adds the elements of `b` to the elements of `a` at the same index
+KERNEL void FIELD_vector_add(GLOBAL FIELD* a,
+                        GLOBAL FIELD* b,
+                        uint n) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  a[gid] = FIELD_add(a[gid], b[gid]);
+}
+
+/// Subtracts the elements of `b` from the elements of `a` at the same index
+KERNEL void FIELD_vector_sub(GLOBAL FIELD* a,
+                        GLOBAL FIELD* b,
+                        uint n) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  a[gid] = FIELD_sub(a[gid], b[gid]);
+}
+
+/// Multiplies the elements of `a` by the elements of `b` at the same index
+KERNEL void FIELD_vector_mul(GLOBAL FIELD* a,
+                        GLOBAL FIELD* b,
+                        uint n) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  a[gid] = FIELD_mul(a[gid], b[gid]);
+}
+
+/// Multiplies all elements of `a` by `c`
+KERNEL void FIELD_vector_scale(GLOBAL FIELD* a,
+                        uint n,
+                        FIELD c) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  a[gid] = FIELD_mul(a[gid], c);
+}
+
+/// Inverts all elements of `a`, zeros stay zero.
+///
+/// Every thread inverts `chunk` consecutive elements with Montgomery's trick, which needs a single
+/// inversion and three multiplications per element. `scratch` holds the prefix products, it must
+/// have the size of `a`.
+KERNEL void FIELD_vector_batch_inverse(GLOBAL FIELD* a,
+                        GLOBAL FIELD* scratch,
+                        uint n,
+                        uint chunk) {
+  const uint start = GET_GLOBAL_ID() * chunk;
+  if(start >= n) return;
+  const uint end = min(start + chunk, n);
+
+  FIELD acc = FIELD_ONE;
+  for(uint i = start; i < end; i++) {
+    scratch[i] = acc;
+    if(!FIELD_eq(a[i], FIELD_ZERO)) acc = FIELD_mul(acc, a[i]);
+  }
+
+  FIELD inv = FIELD_inverse(acc);
+  for(uint i = end; i > start; i--) {
+    const FIELD x = a[i - 1];
+    if(!FIELD_eq(x, FIELD_ZERO)) {
+      a[i - 1] = FIELD_mul(inv, scratch[i - 1]);
+      inv = FIELD_mul(inv, x);
+    }
+  }
+}
+
+/// Writes the powers `[x^0, x^1, ..., x^(n-1)]` into `out`
+KERNEL void FIELD_vector_powers(GLOBAL FIELD* out,
+                        GLOBAL FIELD* squares, // [x, x^2, x^4, ...]
+                        uint n) {
+  const uint gid = GET_GLOBAL_ID();
+  if(gid >= n) return;
+  out[gid] = FIELD_pow_lookup(squares, gid);
+}
@@ -0,0 +1,61 @@
+use crate::scalar::WindowScalar;
+
+/// A point in affine coordinates.
+///
+/// It has the same memory layout as the points that are used as bases of the GPU multiexp, no
+/// matter which curve model the kernel uses.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointAffine<F> {
+    /// The x-coordinate.
+    pub x: F,
+    /// The y-coordinate.
+    pub y: F,
+}
+
+/// The group operations of a curve model, in the coordinates of its GPU multiexp buckets.
+///
+/// The scalar multiplication and the multiexp are built on top of them.
+pub trait GroupOps {
+    /// A point in the coordinates of the buckets.
+    type Point: Copy;
+    /// A point in the representation of the bases.
+    type Affine;
+
+    /// Returns the neutral element.
+    fn identity(&self) -> Self::Point;
+
+    /// Doubles a point.
+    fn double(&self, point: &Self::Point) -> Self::Point;
+
+    /// Adds two points.
+    fn add(&self, p: &Self::Point, q: &Self::Point) -> Self::Point;
+
+    /// Adds a base to a point.
+    fn add_mixed(&self, p: &Self::Point, q: &Self::Affine) -> Self::Point;
+
+    /// Multiplies a base with a scalar, with double-and-add from the most significant bit on.
+    fn mul<E: WindowScalar>(&self, point: &Self::Affine, scalar: &E) -> Self::Point {
+        (0..E::num_bits()).rev().fold(self.identity(), |acc, bit| {
+            let acc = self.double(&acc);
+            if scalar.get_bits(bit, 1) == 1 {
+                self.add_mixed(&acc, point)
+            } else {
+                acc
+            }
+        })
+    }
+
+    /// Calculates `sum(bases[i] * exponents[i])`, one scalar multiplication after another.
+    ///
+    /// It has none of the optimizations of the GPU multiexp, which makes it a simple reference.
+    fn multiexp<E: WindowScalar>(&self, bases: &[Self::Affine], exponents: &[E]) -> Self::Point {
+        assert_eq!(bases.len(), exponents.len());
+        bases
+            .iter()
+            .zip(exponents)
+            .fold(self.identity(), |acc, (base, exponent)| {
+                self.add(&acc, &self.mul(base, exponent))
+            })
+    }
+}
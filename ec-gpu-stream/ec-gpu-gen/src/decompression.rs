@@ -0,0 +1,122 @@
+use std::marker::PhantomData;
+
+use ec_gpu::GpuCurve;
+use rust_gpu_tools::{program_closures, Program};
+
+use crate::error::{EcError, EcResult};
+use crate::layout::GpuAffine;
+use crate::source::compressed_point_size;
+
+/// The local work size of the decompression kernel.
+const LOCAL_WORK_SIZE: usize = 64;
+
+/// Point decompression kernel for a single GPU.
+///
+/// The program must contain the kernel of [`crate::SourceBuilder::add_point_decompression`] for
+/// the curve `C`.
+pub struct PointDecompressionKernel<'a, C>
+where
+    C: GpuCurve,
+{
+    program: Program,
+    /// An optional function which will be called at places where it is possible to abort the
+    /// decompression. If it returns true, the calculation will be aborted with an
+    /// [`EcError::Aborted`].
+    maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, C> PointDecompressionKernel<'a, C>
+where
+    C: GpuCurve,
+{
+    /// Create a new point decompression kernel instance for a device.
+    ///
+    /// The `maybe_abort` function is called when it is possible to abort the computation, without
+    /// leaving the GPU in a weird state. If that function returns `true`, execution is aborted.
+    pub fn create(
+        program: Program,
+        maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    ) -> EcResult<Self> {
+        Ok(Self {
+            program,
+            maybe_abort,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Decompresses the concatenated compressed encodings of points.
+    ///
+    /// An entry is `None` if the encoding is invalid or if the point is not on the curve. If
+    /// `check_subgroup` is set, points that are not in the prime order subgroup are invalid as
+    /// well. The points are written into the memory of `G` directly, see [`GpuAffine`], hence its
+    /// coordinates must be in the base field of the curve `C`.
+    pub fn decompress<G: GpuAffine<Base = C::BaseField>>(
+        &self,
+        compressed: &[u8],
+        check_subgroup: bool,
+    ) -> EcResult<Vec<Option<G>>> {
+        let compressed_size = compressed_point_size::<C>();
+        if compressed.len() % compressed_size != 0 {
+            return Err(EcError::Simple(
+                "The compressed points must be a multiple of the size of a single one.",
+            ));
+        }
+        let point_size = std::mem::size_of::<G>();
+        if point_size != 2 * compressed_size {
+            return Err(EcError::Simple(
+                "The points must have the size of two base field elements.",
+            ));
+        }
+        let n = compressed.len() / compressed_size;
+        if n > u32::MAX as usize {
+            return Err(EcError::Simple(
+                "Too many points for the decompression kernel.",
+            ));
+        }
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        if let Some(maybe_abort) = &self.maybe_abort {
+            if maybe_abort() {
+                return Err(EcError::Aborted);
+            }
+        }
+
+        let closures = program_closures!(|program, _arg| -> EcResult<(Vec<u8>, Vec<u32>)> {
+            let compressed_buffer = program.create_buffer_from_slice(compressed)?;
+            // It is safe as the GPU will initialize those buffers.
+            let points_buffer = unsafe { program.create_buffer::<u8>(n * point_size)? };
+            let valid_buffer = unsafe { program.create_buffer::<u32>(n)? };
+
+            let kernel_name = format!("{}_decompress", C::name());
+            let num_groups = (n + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+            let kernel = program.create_kernel(&kernel_name, num_groups, LOCAL_WORK_SIZE)?;
+            kernel
+                .arg(&compressed_buffer)
+                .arg(&points_buffer)
+                .arg(&valid_buffer)
+                .arg(&(n as u32))
+                .arg(&(check_subgroup as u32))
+                .run()?;
+
+            let mut points = vec![0u8; n * point_size];
+            program.read_into_buffer(&points_buffer, &mut points)?;
+            let mut valid = vec![0u32; n];
+            program.read_into_buffer(&valid_buffer, &mut valid)?;
+            Ok((points, valid))
+        });
+
+        let (points, valid) = self.program.run(closures, ())?;
+        Ok(points
+            .chunks(point_size)
+            .zip(valid)
+            .map(|(chunk, valid)| {
+                // It is safe as the chunk has the size of a point and `G` has the layout of the
+                // affine points of the kernel, which is guaranteed by `GpuAffine`.
+                (valid == 1).then(|| unsafe { (chunk.as_ptr() as *const G).read_unaligned() })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use ff::PrimeField;
+use once_cell::sync::OnceCell;
+
+use crate::error::{EcError, EcResult};
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+use crate::fft::FftKernel;
+use crate::fft_cpu::{cached_twiddles, distribute_powers, in_place_fft_with_twiddles, scale};
+use crate::threadpool::Worker;
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+use ec_gpu::GpuName;
+
+/// A multiplicative subgroup of size `n = 2^log_n` of a prime field, which is used to evaluate
+/// and interpolate polynomials with FFTs.
+///
+/// All values that are needed for the (inverse) FFTs over the domain and over its coset are
+/// computed once, so that they can be reused for any number of transforms. The twiddle factors
+/// are only computed by the first FFT or IFFT on the CPU that needs them, on its [`Worker`].
+#[derive(Clone, Debug)]
+pub struct EvaluationDomain<F: PrimeField> {
+    log_n: u32,
+    /// The `n`-th primitive root of unity.
+    omega: F,
+    omega_inv: F,
+    /// The generator of the coset, it's the multiplicative generator of the field.
+    generator: F,
+    generator_inv: F,
+    /// `1/n`
+    size_inv: F,
+    /// `[omega^0, omega^1, ..., omega^(n/2 - 1)]`
+    twiddles: OnceCell<Arc<Vec<F>>>,
+    /// `[omega^0, omega^-1, ..., omega^-(n/2 - 1)]`
+    twiddles_inv: OnceCell<Arc<Vec<F>>>,
+}
+
+impl<F: PrimeField> EvaluationDomain<F> {
+    /// Creates a domain of size `2^log_n`.
+    ///
+    /// Fails if the field doesn't have a subgroup of that size.
+    pub fn new(log_n: u32) -> EcResult<Self> {
+        if log_n > F::S {
+            return Err(EcError::Simple("The field has no subgroup of that size."));
+        }
+
+        // Compute omega, the 2^log_n primitive root of unity
+        let mut omega = F::root_of_unity();
+        for _ in log_n..F::S {
+            omega = omega.square();
+        }
+        let omega_inv = omega.invert().unwrap();
+        let generator = F::multiplicative_generator();
+        let generator_inv = generator.invert().unwrap();
+        let size_inv = F::from(1u64 << log_n).invert().unwrap();
+
+        Ok(Self {
+            log_n,
+            omega,
+            omega_inv,
+            generator,
+            generator_inv,
+            size_inv,
+            twiddles: OnceCell::new(),
+            twiddles_inv: OnceCell::new(),
+        })
+    }
+
+    /// Creates the smallest domain that has at least `size` elements.
+    pub fn for_size(size: usize) -> EcResult<Self> {
+        let log_n = size.next_power_of_two().trailing_zeros();
+        Self::new(log_n)
+    }
+
+    /// Returns the number of elements of the domain.
+    pub fn size(&self) -> usize {
+        1 << self.log_n
+    }
+
+    /// Returns log2 of the number of elements of the domain.
+    pub fn log_size(&self) -> u32 {
+        self.log_n
+    }
+
+    /// Returns the `n`-th primitive root of unity, which generates the domain.
+    pub fn omega(&self) -> &F {
+        &self.omega
+    }
+
+    /// Returns the inverse of [`EvaluationDomain::omega`].
+    pub fn omega_inv(&self) -> &F {
+        &self.omega_inv
+    }
+
+    /// Returns the generator of the coset that is used by the coset FFTs.
+    pub fn generator(&self) -> &F {
+        &self.generator
+    }
+
+    /// Returns the inverse of [`EvaluationDomain::generator`].
+    pub fn generator_inv(&self) -> &F {
+        &self.generator_inv
+    }
+
+    /// Returns `1/n`.
+    pub fn size_inv(&self) -> &F {
+        &self.size_inv
+    }
+
+    /// Returns the twiddle factors `[omega^0, omega^1, ..., omega^(n/2 - 1)]`.
+    ///
+    /// They are computed on the given worker, unless an earlier call already did.
+    pub fn twiddles(&self, worker: &Worker) -> &[F] {
+        Self::get_twiddles(&self.twiddles, worker, &self.omega, self.log_n)
+    }
+
+    /// Evaluates the polynomial given by its coefficients `a` over the domain, on the CPU.
+    pub fn fft(&self, a: &mut [F], worker: &Worker) {
+        self.fft_with_twiddles(a, worker, self.twiddles(worker));
+    }
+
+    /// Interpolates the evaluations `a` over the domain into coefficients, on the CPU.
+    pub fn ifft(&self, a: &mut [F], worker: &Worker) {
+        let twiddles_inv =
+            Self::get_twiddles(&self.twiddles_inv, worker, &self.omega_inv, self.log_n);
+        self.fft_with_twiddles(a, worker, twiddles_inv);
+        scale(a, worker, &self.size_inv);
+    }
+
+    /// Evaluates the polynomial given by its coefficients `a` over the coset of the domain, on the
+    /// CPU.
+    pub fn coset_fft(&self, a: &mut [F], worker: &Worker) {
+        self.check_size(a);
+        distribute_powers(a, worker, &self.generator);
+        self.fft(a, worker);
+    }
+
+    /// Interpolates the evaluations `a` over the coset of the domain into coefficients, on the
+    /// CPU.
+    pub fn icoset_fft(&self, a: &mut [F], worker: &Worker) {
+        self.ifft(a, worker);
+        distribute_powers(a, worker, &self.generator_inv);
+    }
+
+    /// Evaluates the vanishing polynomial of the domain `Z(x) = x^n - 1` at `x`.
+    pub fn evaluate_vanishing(&self, x: &F) -> F {
+        x.pow_vartime(&[self.size() as u64]) - F::one()
+    }
+
+    /// Divides the evaluations `a` over the coset of the domain by the vanishing polynomial.
+    ///
+    /// The vanishing polynomial has the same value `g^n - 1` at every element of the coset, hence
+    /// it's a multiplication with its inverse.
+    pub fn divide_by_vanishing_on_coset(&self, a: &mut [F], worker: &Worker) {
+        self.check_size(a);
+        let vanishing_inv = self
+            .evaluate_vanishing(&self.generator)
+            .invert()
+            .expect("the coset doesn't intersect the domain");
+        scale(a, worker, &vanishing_inv);
+    }
+
+    /// Returns the twiddle factors of `omega` from `cell`, they are computed on `worker` if the
+    /// cell is still empty.
+    fn get_twiddles<'a>(
+        cell: &'a OnceCell<Arc<Vec<F>>>,
+        worker: &Worker,
+        omega: &F,
+        log_n: u32,
+    ) -> &'a [F] {
+        cell.get_or_init(|| worker.install(|| cached_twiddles(omega, log_n)))
+    }
+
+    fn fft_with_twiddles(&self, a: &mut [F], worker: &Worker, twiddles: &[F]) {
+        self.check_size(a);
+        in_place_fft_with_twiddles(a, worker, twiddles, self.log_n);
+    }
+
+    fn check_size(&self, a: &[F]) {
+        assert_eq!(
+            a.len(),
+            self.size(),
+            "The input must have the size of the domain."
+        );
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+impl<F: PrimeField + GpuName> EvaluationDomain<F> {
+    /// Evaluates the polynomial given by its coefficients `a` over the domain, on the GPU.
+    pub fn fft_gpu(&self, a: &mut [F], kern: &mut FftKernel<F>) -> EcResult<()> {
+        self.check_size(a);
+        kern.radix_fft(a, &self.omega, self.log_n)
+    }
+
+    /// Interpolates the evaluations `a` over the domain into coefficients, on the GPU.
+    pub fn ifft_gpu(&self, a: &mut [F], kern: &mut FftKernel<F>) -> EcResult<()> {
+        self.check_size(a);
+        kern.ifft(a, &self.omega, self.log_n)
+    }
+
+    /// Evaluates the polynomial given by its coefficients `a` over the coset of the domain, on the
+    /// GPU.
+    pub fn coset_fft_gpu(&self, a: &mut [F], kern: &mut FftKernel<F>) -> EcResult<()> {
+        self.check_size(a);
+        kern.coset_fft(a, &self.omega, self.log_n, &self.generator)
+    }
+
+    /// Interpolates the evaluations `a` over the coset of the domain into coefficients, on the
+    /// GPU.
+    pub fn icoset_fft_gpu(&self, a: &mut [F], kern: &mut FftKernel<F>) -> EcResult<()> {
+        self.check_size(a);
+        kern.icoset_fft(a, &self.omega, self.log_n, &self.generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    use crate::fft_cpu::serial_fft;
+
+    #[test]
+    fn test_domain() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+
+        for log_n in 0..10 {
+            let domain = EvaluationDomain::<Fr>::new(log_n).unwrap();
+            let n = domain.size();
+            assert!(bool::from(
+                domain.evaluate_vanishing(domain.omega()).is_zero()
+            ));
+            assert_eq!(*domain.omega() * domain.omega_inv(), Fr::one());
+            assert_eq!(Fr::from(n as u64) * domain.size_inv(), Fr::one());
+            assert!(domain.twiddles.get().is_none() && domain.twiddles_inv.get().is_none());
+
+            let coeffs = (0..n).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+
+            let mut expected = coeffs.clone();
+            serial_fft(&mut expected, domain.omega(), log_n);
+            let mut v = coeffs.clone();
+            domain.fft(&mut v, &worker);
+            assert_eq!(v, expected);
+            assert_eq!(domain.twiddles(&worker).len(), n / 2);
+            assert!(domain.twiddles_inv.get().is_none());
+            domain.ifft(&mut v, &worker);
+            assert_eq!(v, coeffs);
+
+            domain.coset_fft(&mut v, &worker);
+            domain.icoset_fft(&mut v, &worker);
+            assert_eq!(v, coeffs);
+        }
+
+        assert!(EvaluationDomain::<Fr>::new(Fr::S + 1).is_err());
+        assert_eq!(EvaluationDomain::<Fr>::for_size(1000).unwrap().size(), 1024);
+        assert_eq!(EvaluationDomain::<Fr>::for_size(1024).unwrap().size(), 1024);
+    }
+
+    #[test]
+    fn test_divide_by_vanishing_on_coset() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let domain = EvaluationDomain::<Fr>::new(4).unwrap();
+
+        // A polynomial that is a multiple of `Z(x) = x^n - 1`, it's `h(x) * Z(x)`.
+        let n = domain.size();
+        let h = (0..n / 2)
+            .map(|_| Fr::random(&mut *rng))
+            .collect::<Vec<_>>();
+        let mut a = vec![Fr::zero(); 2 * n];
+        for (i, coeff) in h.iter().enumerate() {
+            a[i] -= coeff;
+            a[i + n] += coeff;
+        }
+
+        // Evaluate on the coset of a domain that is big enough for the product.
+        let big_domain = EvaluationDomain::<Fr>::new(5).unwrap();
+        big_domain.coset_fft(&mut a, &worker);
+        // `Z(x)` isn't constant on the coset of the bigger domain, divide by it point-wise.
+        let mut x = *big_domain.generator();
+        for eval in a.iter_mut() {
+            *eval *= domain.evaluate_vanishing(&x).invert().unwrap();
+            x *= big_domain.omega();
+        }
+        big_domain.icoset_fft(&mut a, &worker);
+        assert_eq!(&a[..n / 2], &h[..]);
+        assert!(a[n / 2..].iter().all(|coeff| coeff.is_zero_vartime()));
+
+        // On the coset of the domain itself, `Z(x)` is constant.
+        let mut b = (0..n).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+        let expected = b.clone();
+        domain.divide_by_vanishing_on_coset(&mut b, &worker);
+        let vanishing = domain.evaluate_vanishing(domain.generator());
+        for (quotient, eval) in b.iter().zip(expected.iter()) {
+            assert_eq!(*quotient * vanishing, *eval);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use ff::Field;
+
+use crate::curve_cpu::{GroupOps, PointAffine};
+
+/// A twisted Edwards curve `a * x^2 + y^2 = 1 + d * x^2 * y^2` over the field `F`.
+///
+/// It uses the same formulas as the GPU kernels of
+/// [`crate::SourceBuilder::add_edwards_multiexp`], which are complete if `a` is a square and `d`
+/// is not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EdwardsCurve<F> {
+    /// The coefficient `a` of the curve equation.
+    pub a: F,
+    /// The coefficient `d` of the curve equation.
+    pub d: F,
+}
+
+/// A point in affine coordinates, the neutral element is `(0, 1)`.
+pub type EdwardsAffine<F> = PointAffine<F>;
+
+/// A point in extended coordinates, where `x = X / Z`, `y = Y / Z` and `x * y = T / Z`.
+///
+/// It has the same memory layout as the results of the GPU multiexp.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct EdwardsExtended<F> {
+    /// The `X` coordinate.
+    pub x: F,
+    /// The `Y` coordinate.
+    pub y: F,
+    /// The `T` coordinate.
+    pub t: F,
+    /// The `Z` coordinate.
+    pub z: F,
+}
+
+impl<F: Field> EdwardsExtended<F> {
+    /// Returns the neutral element.
+    pub fn identity() -> Self {
+        Self {
+            x: F::zero(),
+            y: F::one(),
+            t: F::zero(),
+            z: F::one(),
+        }
+    }
+
+    /// Converts the point into affine coordinates.
+    pub fn to_affine(&self) -> EdwardsAffine<F> {
+        let z_inv =
+            Option::<F>::from(self.z.invert()).expect("Z is never zero for complete formulas");
+        EdwardsAffine {
+            x: self.x * z_inv,
+            y: self.y * z_inv,
+        }
+    }
+}
+
+impl<F: Field> From<EdwardsAffine<F>> for EdwardsExtended<F> {
+    fn from(point: EdwardsAffine<F>) -> Self {
+        Self {
+            x: point.x,
+            y: point.y,
+            t: point.x * point.y,
+            z: F::one(),
+        }
+    }
+}
+
+/// Points are equal if they represent the same affine point.
+impl<F: Field> PartialEq for EdwardsExtended<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x * other.z == other.x * self.z && self.y * other.z == other.y * self.z
+    }
+}
+
+impl<F: Field> Eq for EdwardsExtended<F> {}
+
+impl<F: Field> EdwardsCurve<F> {
+    /// Returns whether the point satisfies the curve equation.
+    pub fn is_on_curve(&self, point: &EdwardsAffine<F>) -> bool {
+        let xx = point.x.square();
+        let yy = point.y.square();
+        self.a * xx + yy == F::one() + self.d * xx * yy
+    }
+
+    /// Returns a point with the given y-coordinate, if there is one.
+    ///
+    /// If there are two such points, the one whose x-coordinate is returned by [`Field::sqrt`] is
+    /// chosen.
+    pub fn point_with_y(&self, y: F) -> Option<EdwardsAffine<F>> {
+        // x^2 = (1 - y^2) / (a - d * y^2)
+        let yy = y.square();
+        let denominator = Option::<F>::from((self.a - self.d * yy).invert())?;
+        let x = Option::<F>::from(((F::one() - yy) * denominator).sqrt())?;
+        Some(EdwardsAffine { x, y })
+    }
+}
+
+/// The bucket arithmetic of [`crate::SourceBuilder::add_edwards_multiexp`], it needs no special
+/// cases for the neutral element or for doubling.
+impl<F: Field> GroupOps for EdwardsCurve<F> {
+    type Point = EdwardsExtended<F>;
+    type Affine = EdwardsAffine<F>;
+
+    fn identity(&self) -> EdwardsExtended<F> {
+        EdwardsExtended::identity()
+    }
+
+    /// Doubles a point, with the formula "dbl-2008-hwcd".
+    fn double(&self, point: &EdwardsExtended<F>) -> EdwardsExtended<F> {
+        let a = point.x.square();
+        let b = point.y.square();
+        let c = point.z.square().double();
+        let d = self.a * a;
+        let e = (point.x + point.y).square() - a - b;
+        let g = d + b;
+        let f = g - c;
+        let h = d - b;
+        EdwardsExtended {
+            x: e * f,
+            y: g * h,
+            t: e * h,
+            z: f * g,
+        }
+    }
+
+    /// Adds two points, with the formula "add-2008-hwcd".
+    fn add(&self, p: &EdwardsExtended<F>, q: &EdwardsExtended<F>) -> EdwardsExtended<F> {
+        let a = p.x * q.x;
+        let b = p.y * q.y;
+        let c = p.t * self.d * q.t;
+        let d = p.z * q.z;
+        let e = (p.x + p.y) * (q.x + q.y) - a - b;
+        let f = d - c;
+        let g = d + c;
+        let h = b - self.a * a;
+        EdwardsExtended {
+            x: e * f,
+            y: g * h,
+            t: e * h,
+            z: f * g,
+        }
+    }
+
+    /// Adds an affine point, with the formula "madd-2008-hwcd".
+    fn add_mixed(&self, p: &EdwardsExtended<F>, q: &EdwardsAffine<F>) -> EdwardsExtended<F> {
+        self.add(p, &EdwardsExtended::from(*q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+    use rand::RngCore;
+
+    /// The Jubjub curve `-x^2 + y^2 = 1 - (10240/10241) * x^2 * y^2`.
+    fn jubjub() -> EdwardsCurve<Fr> {
+        EdwardsCurve {
+            a: -Fr::one(),
+            d: -(Fr::from(10240) * Fr::from(10241).invert().unwrap()),
+        }
+    }
+
+    fn random_point<R: RngCore>(curve: &EdwardsCurve<Fr>, rng: &mut R) -> EdwardsAffine<Fr> {
+        loop {
+            if let Some(point) = curve.point_with_y(Fr::random(&mut *rng)) {
+                return point;
+            }
+        }
+    }
+
+    #[test]
+    fn test_edwards_arithmetic() {
+        let mut rng = rand::thread_rng();
+        let curve = jubjub();
+        let identity = EdwardsExtended::identity();
+
+        for _ in 0..20 {
+            let p = random_point(&curve, &mut rng);
+            let q = random_point(&curve, &mut rng);
+            assert!(curve.is_on_curve(&p));
+            let p_extended = EdwardsExtended::from(p);
+            let q_extended = EdwardsExtended::from(q);
+
+            let sum = curve.add(&p_extended, &q_extended);
+            assert!(curve.is_on_curve(&sum.to_affine()));
+            assert_eq!(sum, curve.add(&q_extended, &p_extended));
+            assert_eq!(sum, curve.add_mixed(&p_extended, &q));
+            assert_eq!(curve.add(&p_extended, &identity), p_extended);
+            assert_eq!(
+                curve.double(&p_extended),
+                curve.add(&p_extended, &p_extended)
+            );
+
+            // The inverse of `(x, y)` is `(-x, y)`.
+            let minus_p = EdwardsAffine { x: -p.x, y: p.y };
+            assert_eq!(curve.add_mixed(&p_extended, &minus_p), identity);
+        }
+    }
+
+    #[test]
+    fn test_edwards_multiexp() {
+        let mut rng = rand::thread_rng();
+        let curve = jubjub();
+        let p = random_point(&curve, &mut rng);
+        let q = random_point(&curve, &mut rng);
+
+        let p_extended = EdwardsExtended::from(p);
+        let p_times_3 = curve.add(&curve.double(&p_extended), &p_extended);
+        assert_eq!(curve.mul(&p, &3u64), p_times_3);
+        assert_eq!(curve.mul(&p, &0u64), EdwardsExtended::identity());
+
+        let expected = curve.add(&p_times_3, &curve.double(&EdwardsExtended::from(q)));
+        assert_eq!(curve.multiexp(&[p, q], &[3u64, 2u64]), expected);
+    }
+}
@@ -21,6 +21,15 @@ pub enum EcError {
     // GpuTools(#[from] CudaError),
     GpuTools(#[from] GPUError),
 
+    /// Error in case the kernels cannot be compiled or loaded for running them on the CPU.
+    #[cfg(feature = "host-c")]
+    #[error("Host C error: {0}")]
+    HostC(String),
+
+    /// Error in case a thread pool cannot be created.
+    #[error("Cannot create thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+
     /// IO error.
     #[error("Encountered an I/O error: {0}")]
     Io(#[from] io::Error),
@@ -4,15 +4,263 @@ use std::sync::{Arc, RwLock};
 use ec_gpu::GpuName;
 use ff::Field;
 use log::{error, info};
-use rust_gpu_tools::{program_closures, LocalBuffer, Program};
+use rayon::prelude::*;
+#[cfg(feature = "cuda")]
+use rust_gpu_tools::cuda;
+#[cfg(feature = "opencl")]
+use rust_gpu_tools::opencl;
+use rust_gpu_tools::{program_closures, Device, LocalBuffer, Program};
 
 use crate::error::{EcError, EcResult};
-use crate::threadpool::THREAD_POOL;
+use crate::fft_cpu::{four_step_split, transpose};
+use crate::threadpool::{Worker, THREAD_POOL};
 
 const LOG2_MAX_ELEMENTS: usize = 32; // At most 2^32 elements is supported.
 const MAX_LOG2_RADIX: u32 = 8; // Radix256
 const MAX_LOG2_LOCAL_WORK_SIZE: u32 = 7; // 128
 
+/// The local work size of the kernels that operate on every element independently.
+const ELEMENTWISE_LOCAL_WORK_SIZE: usize = 64;
+
+/// The number of consecutive elements a single thread inverts with Montgomery's trick.
+const BATCH_INVERSE_CHUNK_SIZE: usize = 64;
+
+/// The maximum number of twiddle factor tables that are kept on a device.
+const MAX_CACHED_TWIDDLES: usize = 8;
+
+/// Let 20% of GPU memory be free when the rows of a four-step FFT are uploaded, this is an
+/// arbitrary value.
+const MEMORY_PADDING: f64 = 0.2f64;
+
+/// Wrapper to pass a field element by value to a kernel.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct FieldArgument<F>(F);
+
+#[cfg(feature = "cuda")]
+impl<F: Field> cuda::KernelArgument for FieldArgument<F> {
+    fn as_c_void(&self) -> *mut std::ffi::c_void {
+        &self.0 as *const _ as _
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl<F: Field> opencl::KernelArgument for FieldArgument<F> {
+    fn push(&self, kernel: &mut opencl::Kernel) {
+        kernel.builder.set_arg(&self.0);
+    }
+}
+
+/// Element-wise steps that are performed on the GPU before or after the FFT rounds.
+#[derive(Clone, Copy, Default)]
+struct FftSteps<F> {
+    /// Multiply the element at index `i` by `g^i` before the FFT.
+    distribute_before: Option<F>,
+    /// Multiply the element at index `i` by `g^i` after the FFT.
+    distribute_after: Option<F>,
+    /// Multiply all elements by this value after the FFT.
+    scale_after: Option<F>,
+}
+
+/// Precalculated values of an FFT for a certain `omega` and size, which are kept on the device.
+struct DeviceTwiddles<F, B> {
+    omega: F,
+    n: usize,
+    /// `[omega^(0/(2^(deg-1))), omega^(1/(2^(deg-1))), ..., omega^((2^(deg-1)-1)/(2^(deg-1)))]`
+    pq: B,
+    /// `[omega, omega^2, omega^4, omega^8, ..., omega^(2^31)]`
+    omegas: B,
+}
+
+/// The twiddle factor tables of a device, for each of the backends.
+///
+/// Only the slot of the backend the program runs on is ever filled.
+#[derive(Default)]
+struct TwiddleCache<F> {
+    #[cfg(feature = "cuda")]
+    cuda: Vec<DeviceTwiddles<F, cuda::Buffer<F>>>,
+    #[cfg(feature = "opencl")]
+    opencl: Vec<DeviceTwiddles<F, opencl::Buffer<F>>>,
+}
+
+/// A vector of field elements that stays in the memory of a GPU.
+///
+/// It's created by [`SingleFftKernel::upload`] and must only be used with the kernel that created
+/// it. The FFTs and element-wise operations on it don't transfer any data between the host and
+/// the GPU, only [`SingleFftKernel::download`] does. This way several steps can be chained and
+/// only the final result is read back.
+pub struct FftBuffer<F> {
+    len: usize,
+    #[cfg(feature = "cuda")]
+    cuda: Option<cuda::Buffer<F>>,
+    #[cfg(feature = "opencl")]
+    opencl: Option<opencl::Buffer<F>>,
+}
+
+impl<F> FftBuffer<F> {
+    /// Returns a buffer that isn't on any device yet.
+    fn empty(len: usize) -> Self {
+        Self {
+            len,
+            #[cfg(feature = "cuda")]
+            cuda: None,
+            #[cfg(feature = "opencl")]
+            opencl: None,
+        }
+    }
+
+    /// Returns the number of elements of the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The arguments of the FFT program closures, the buffer and the twiddle factors of the device.
+type FftArgs<'i, F> = (&'i mut FftBuffer<F>, &'i mut TwiddleCache<F>);
+
+/// The error if an [`FftBuffer`] is used with a program of another backend.
+const WRONG_BACKEND: &str = "The buffer was created by a kernel of another GPU backend.";
+
+/// Selects the twiddle factor tables and buffers of the backend of a program.
+///
+/// This way the body of the program closures is the same for all backends.
+trait BackendSlot<F> {
+    type Buffer;
+
+    fn twiddle_slot<'c>(
+        &self,
+        cache: &'c mut TwiddleCache<F>,
+    ) -> &'c mut Vec<DeviceTwiddles<F, Self::Buffer>>;
+
+    fn wrap_buffer(&self, buffer: Self::Buffer, len: usize) -> FftBuffer<F>;
+
+    fn device_buffer<'c>(&self, buffer: &'c FftBuffer<F>) -> EcResult<&'c Self::Buffer>;
+
+    fn device_buffer_mut<'c>(&self, buffer: &'c mut FftBuffer<F>)
+        -> EcResult<&'c mut Self::Buffer>;
+}
+
+#[cfg(feature = "cuda")]
+impl<F> BackendSlot<F> for cuda::Program {
+    type Buffer = cuda::Buffer<F>;
+
+    fn twiddle_slot<'c>(
+        &self,
+        cache: &'c mut TwiddleCache<F>,
+    ) -> &'c mut Vec<DeviceTwiddles<F, Self::Buffer>> {
+        &mut cache.cuda
+    }
+
+    fn wrap_buffer(&self, buffer: Self::Buffer, len: usize) -> FftBuffer<F> {
+        FftBuffer {
+            cuda: Some(buffer),
+            ..FftBuffer::empty(len)
+        }
+    }
+
+    fn device_buffer<'c>(&self, buffer: &'c FftBuffer<F>) -> EcResult<&'c Self::Buffer> {
+        buffer.cuda.as_ref().ok_or(EcError::Simple(WRONG_BACKEND))
+    }
+
+    fn device_buffer_mut<'c>(
+        &self,
+        buffer: &'c mut FftBuffer<F>,
+    ) -> EcResult<&'c mut Self::Buffer> {
+        buffer.cuda.as_mut().ok_or(EcError::Simple(WRONG_BACKEND))
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl<F> BackendSlot<F> for opencl::Program {
+    type Buffer = opencl::Buffer<F>;
+
+    fn twiddle_slot<'c>(
+        &self,
+        cache: &'c mut TwiddleCache<F>,
+    ) -> &'c mut Vec<DeviceTwiddles<F, Self::Buffer>> {
+        &mut cache.opencl
+    }
+
+    fn wrap_buffer(&self, buffer: Self::Buffer, len: usize) -> FftBuffer<F> {
+        FftBuffer {
+            opencl: Some(buffer),
+            ..FftBuffer::empty(len)
+        }
+    }
+
+    fn device_buffer<'c>(&self, buffer: &'c FftBuffer<F>) -> EcResult<&'c Self::Buffer> {
+        buffer.opencl.as_ref().ok_or(EcError::Simple(WRONG_BACKEND))
+    }
+
+    fn device_buffer_mut<'c>(
+        &self,
+        buffer: &'c mut FftBuffer<F>,
+    ) -> EcResult<&'c mut Self::Buffer> {
+        buffer.opencl.as_mut().ok_or(EcError::Simple(WRONG_BACKEND))
+    }
+}
+
+/// Returns the `pq` values of [`DeviceTwiddles`], which are valid for radix degrees up to
+/// `max_deg`.
+fn pq<F: Field>(omega: &F, n: usize, max_deg: u32) -> Vec<F> {
+    // There is always at least one value, so that the buffer isn't empty.
+    let mut pq = vec![F::zero(); cmp::max(1 << max_deg >> 1, 1)];
+    let twiddle = omega.pow_vartime([(n >> max_deg) as u64]);
+    pq[0] = F::one();
+    if max_deg > 1 {
+        pq[1] = twiddle;
+        for i in 2..(1 << max_deg >> 1) {
+            pq[i] = pq[i - 1];
+            pq[i].mul_assign(&twiddle);
+        }
+    }
+    pq
+}
+
+/// Returns `[x, x^2, x^4, x^8, ..., x^(2^31)]`, which is used for lookups on the GPU.
+fn squares<F: Field>(x: &F) -> Vec<F> {
+    let mut squares = vec![*x; LOG2_MAX_ELEMENTS];
+    for i in 1..LOG2_MAX_ELEMENTS {
+        squares[i] = squares[i - 1].square();
+    }
+    squares
+}
+
+fn invert<F: Field>(x: &F) -> EcResult<F> {
+    Option::from(x.invert()).ok_or(EcError::Simple("Cannot invert zero."))
+}
+
+/// Returns `1/n` for `n = 2^log_n`.
+fn inverse_size<F: Field>(log_n: u32) -> F {
+    let n = (0..log_n).fold(F::one(), |n, _| n.double());
+    n.invert().expect("2^log_n is not zero")
+}
+
+/// Returns the number of work groups of the kernels that operate on every element independently.
+fn elementwise_work_groups(len: usize) -> EcResult<usize> {
+    if len > u32::MAX as usize {
+        return Err(EcError::Simple("Too many elements for the FFT kernel."));
+    }
+    Ok((len + ELEMENTWISE_LOCAL_WORK_SIZE - 1) / ELEMENTWISE_LOCAL_WORK_SIZE)
+}
+
+/// Calculates the maximum number of rows of `row_size` elements that can be transformed at once
+/// with `memory` bytes of GPU memory.
+fn calc_rows_per_block<F>(memory: u64, row_size: usize) -> usize {
+    // Leave `MEMORY_PADDING` percent of the memory free.
+    let max_memory = ((memory as f64) * (1f64 - MEMORY_PADDING)) as usize;
+    // The FFT rounds alternate between the uploaded buffer and another one of the same size.
+    let row_memory = 2 * row_size * std::mem::size_of::<F>();
+    // The kernel indexes the elements with 32-bit integers.
+    let max_rows = u32::MAX as usize / row_size;
+    cmp::max(cmp::min(max_memory / row_memory, max_rows), 1)
+}
+
 /// FFT kernel for a single GPU.
 pub struct SingleFftKernel<'a, F>
 where
@@ -23,7 +271,11 @@ where
     /// calculations. If it returns true, the calculation will be aborted with an
     /// [`EcError::Aborted`].
     maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
-    _phantom: std::marker::PhantomData<F>,
+    /// The memory of the device in bytes.
+    memory: u64,
+    /// The twiddle factors of the most recently used `omega` and size combinations, so that
+    /// they are only uploaded once.
+    twiddles: TwiddleCache<F>,
 }
 
 impl<'a, F: Field + GpuName> SingleFftKernel<'a, F> {
@@ -35,10 +287,16 @@ impl<'a, F: Field + GpuName> SingleFftKernel<'a, F> {
         program: Program,
         maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
     ) -> EcResult<Self> {
+        let memory = Device::all()
+            .into_iter()
+            .find(|device| device.name() == program.device_name())
+            .map(|device| device.memory())
+            .ok_or(EcError::Simple("Cannot find the device of the program."))?;
         Ok(SingleFftKernel {
             program,
             maybe_abort,
-            _phantom: Default::default(),
+            memory,
+            twiddles: Default::default(),
         })
     }
 
@@ -46,38 +304,374 @@ impl<'a, F: Field + GpuName> SingleFftKernel<'a, F> {
     /// * `omega` - Special value `omega` is used for FFT over finite-fields
     /// * `log_n` - Specifies log2 of number of elements
     pub fn radix_fft(&mut self, input: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
-        let closures = program_closures!(|program, input: &mut [F]| -> EcResult<()> {
-            let n = 1 << log_n;
-            // All usages are safe as the buffers are initialized from either the host or the GPU
-            // before they are read.
-            let mut src_buffer = unsafe { program.create_buffer::<F>(n)? };
-            let mut dst_buffer = unsafe { program.create_buffer::<F>(n)? };
-            // The precalculated values pq` and `omegas` are valid for radix degrees up to `max_deg`
+        self.on_device(input, |kern, buffer| {
+            kern.radix_fft_buffer(buffer, omega, log_n)
+        })
+    }
+
+    /// Performs inverse FFT on `input`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    pub fn ifft(&mut self, input: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        self.on_device(input, |kern, buffer| kern.ifft_buffer(buffer, omega, log_n))
+    }
+
+    /// Performs FFT on `input`, evaluating over the coset defined by the generator `g`
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The generator of the coset, the element at index `i` is multiplied by `g^i`
+    pub fn coset_fft(&mut self, input: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        self.on_device(input, |kern, buffer| {
+            kern.coset_fft_buffer(buffer, omega, log_n, g)
+        })
+    }
+
+    /// Performs the inverse of [`SingleFftKernel::coset_fft`] on `input`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The same generator of the coset that is used for the forward FFT
+    pub fn icoset_fft(&mut self, input: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        self.on_device(input, |kern, buffer| {
+            kern.icoset_fft_buffer(buffer, omega, log_n, g)
+        })
+    }
+
+    /// Performs FFT on each of the vectors of size `2^log_n`, which are stored one after another
+    /// in `input`
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements of a single vector
+    ///
+    /// All vectors are uploaded at once and transformed with a single set of kernel launches.
+    pub fn radix_fft_batch(&mut self, input: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        self.on_device(input, |kern, buffer| {
+            kern.fft_buffer(buffer, omega, log_n, 0, FftSteps::default())
+        })
+    }
+
+    /// Performs FFT of size `n = 2^log2_n * 3^log3_n` on `input`
+    /// * `omega` - A primitive `n`-th root of unity
+    /// * `log2_n` - Specifies log2 of the power of two part of the number of elements
+    /// * `log3_n` - Specifies log3 of the power of three part of the number of elements
+    ///
+    /// The kernel must be built with [`crate::SourceBuilder::add_mixed_radix_fft`] for any
+    /// `log3_n` other than zero.
+    pub fn mixed_radix_fft(
+        &mut self,
+        input: &mut [F],
+        omega: &F,
+        log2_n: u32,
+        log3_n: u32,
+    ) -> EcResult<()> {
+        self.on_device(input, |kern, buffer| {
+            kern.fft_buffer(buffer, omega, log2_n, log3_n, FftSteps::default())
+        })
+    }
+
+    /// Copies `input` into a new buffer on the GPU.
+    pub fn upload(&mut self, input: &[F]) -> EcResult<FftBuffer<F>> {
+        let closures = program_closures!(|program, input: &[F]| -> EcResult<FftBuffer<F>> {
+            let buffer = program.create_buffer_from_slice(input)?;
+            Ok(program.wrap_buffer(buffer, input.len()))
+        });
+
+        self.program.run(closures, input)
+    }
+
+    /// Copies the elements of `buffer` from the GPU into `output`.
+    pub fn download(&mut self, buffer: &FftBuffer<F>, output: &mut [F]) -> EcResult<()> {
+        assert_eq!(
+            buffer.len(),
+            output.len(),
+            "The output must have the size of the buffer."
+        );
+
+        let closures =
+            program_closures!(|program, args: (&FftBuffer<F>, &mut [F])| -> EcResult<()> {
+                let (buffer, output) = args;
+                program.read_into_buffer(program.device_buffer(buffer)?, output)?;
+                Ok(())
+            });
+
+        self.program.run(closures, (buffer, output))
+    }
+
+    /// Performs FFT on `buffer`, without transferring it between the host and the GPU
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    pub fn radix_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+    ) -> EcResult<()> {
+        self.fft_buffer(buffer, omega, log_n, 0, FftSteps::default())
+    }
+
+    /// Performs inverse FFT on `buffer`, without transferring it between the host and the GPU
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    pub fn ifft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+    ) -> EcResult<()> {
+        let steps = FftSteps {
+            scale_after: Some(inverse_size(log_n)),
+            ..Default::default()
+        };
+        self.fft_buffer(buffer, &invert(omega)?, log_n, 0, steps)
+    }
+
+    /// Performs FFT on `buffer`, evaluating over the coset defined by the generator `g`, without
+    /// transferring it between the host and the GPU
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The generator of the coset, the element at index `i` is multiplied by `g^i`
+    pub fn coset_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        let steps = FftSteps {
+            distribute_before: Some(*g),
+            ..Default::default()
+        };
+        self.fft_buffer(buffer, omega, log_n, 0, steps)
+    }
+
+    /// Performs the inverse of [`SingleFftKernel::coset_fft_buffer`] on `buffer`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The same generator of the coset that is used for the forward FFT
+    pub fn icoset_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        let steps = FftSteps {
+            distribute_after: Some(invert(g)?),
+            scale_after: Some(inverse_size(log_n)),
+            ..Default::default()
+        };
+        self.fft_buffer(buffer, &invert(omega)?, log_n, 0, steps)
+    }
+
+    /// Adds the elements of `b` to the elements of `a` at the same index, on the GPU.
+    pub fn add_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.pointwise("vector_add", a, b)
+    }
+
+    /// Multiplies the elements of `a` by the elements of `b` at the same index, on the GPU.
+    pub fn mul_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.pointwise("vector_mul", a, b)
+    }
+
+    /// Subtracts the elements of `b` from the elements of `a` at the same index, on the GPU.
+    pub fn sub_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.pointwise("vector_sub", a, b)
+    }
+
+    /// Multiplies all elements of `buffer` by `c`, on the GPU.
+    pub fn scale_buffer(&mut self, buffer: &mut FftBuffer<F>, c: &F) -> EcResult<()> {
+        let closures = program_closures!(|program, buffer: &mut FftBuffer<F>| -> EcResult<()> {
+            let len = buffer.len();
+            let kernel_name = format!("{}_vector_scale", F::name());
+            let kernel = program.create_kernel(
+                &kernel_name,
+                elementwise_work_groups(len)?,
+                ELEMENTWISE_LOCAL_WORK_SIZE,
+            )?;
+            kernel
+                .arg(program.device_buffer(buffer)?)
+                .arg(&(len as u32))
+                .arg(&FieldArgument(*c))
+                .run()?;
+            Ok(())
+        });
+
+        self.program.run(closures, buffer)
+    }
+
+    /// Inverts all elements of `buffer` on the GPU, zeros stay zero.
+    ///
+    /// The kernel must be built with [`crate::SourceBuilder::add_vector_ops`] (which is part of
+    /// [`crate::SourceBuilder::add_fft`]), like all element-wise operations on buffers.
+    pub fn batch_inverse_buffer(&mut self, buffer: &mut FftBuffer<F>) -> EcResult<()> {
+        let closures = program_closures!(|program, buffer: &mut FftBuffer<F>| -> EcResult<()> {
+            let len = buffer.len();
+            let num_threads = (len + BATCH_INVERSE_CHUNK_SIZE - 1) / BATCH_INVERSE_CHUNK_SIZE;
+            // Safe as every prefix product is written before it is read.
+            let scratch = unsafe { program.create_buffer::<F>(cmp::max(len, 1))? };
+            let kernel_name = format!("{}_vector_batch_inverse", F::name());
+            let kernel = program.create_kernel(
+                &kernel_name,
+                elementwise_work_groups(num_threads)?,
+                ELEMENTWISE_LOCAL_WORK_SIZE,
+            )?;
+            kernel
+                .arg(program.device_buffer(buffer)?)
+                .arg(&scratch)
+                .arg(&(len as u32))
+                .arg(&(BATCH_INVERSE_CHUNK_SIZE as u32))
+                .run()?;
+            Ok(())
+        });
+
+        self.program.run(closures, buffer)
+    }
+
+    /// Returns a new buffer on the GPU with the powers `[x^0, x^1, ..., x^(n-1)]`.
+    pub fn powers_buffer(&mut self, x: &F, n: usize) -> EcResult<FftBuffer<F>> {
+        let closures = program_closures!(|program, _arg| -> EcResult<FftBuffer<F>> {
+            // Safe as the kernel writes all elements.
+            let powers = unsafe { program.create_buffer::<F>(cmp::max(n, 1))? };
+            let squares = program.create_buffer_from_slice(&squares(x))?;
+            let kernel_name = format!("{}_vector_powers", F::name());
+            let kernel = program.create_kernel(
+                &kernel_name,
+                elementwise_work_groups(n)?,
+                ELEMENTWISE_LOCAL_WORK_SIZE,
+            )?;
+            kernel.arg(&powers).arg(&squares).arg(&(n as u32)).run()?;
+            Ok(program.wrap_buffer(powers, n))
+        });
+
+        self.program.run(closures, ())
+    }
+
+    /// Runs the element-wise kernel `{F}_{op}` with the arguments `a`, `b` and their length.
+    fn pointwise(&mut self, op: &str, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        assert_eq!(a.len(), b.len(), "The buffers must have the same size.");
+
+        let closures = program_closures!(|program,
+                                          args: (&mut FftBuffer<F>, &FftBuffer<F>)|
+         -> EcResult<()> {
+            let (a, b) = args;
+            let len = a.len();
+            let kernel_name = format!("{}_{}", F::name(), op);
+            let kernel = program.create_kernel(
+                &kernel_name,
+                elementwise_work_groups(len)?,
+                ELEMENTWISE_LOCAL_WORK_SIZE,
+            )?;
+            kernel
+                .arg(program.device_buffer(a)?)
+                .arg(program.device_buffer(b)?)
+                .arg(&(len as u32))
+                .run()?;
+            Ok(())
+        });
+
+        self.program.run(closures, (a, b))
+    }
+
+    /// Uploads `input`, runs `op` on it and downloads the result back into `input`.
+    fn on_device<O>(&mut self, input: &mut [F], op: O) -> EcResult<()>
+    where
+        O: FnOnce(&mut Self, &mut FftBuffer<F>) -> EcResult<()>,
+    {
+        let mut buffer = self.upload(input)?;
+        op(self, &mut buffer)?;
+        self.download(&buffer, input)
+    }
+
+    /// Performs the FFT rounds on `buffer`, together with the given element-wise steps.
+    ///
+    /// The `buffer` may contain several FFTs of size `2^log_n * 3^log3_n`, stored one after
+    /// another. The radix-2 rounds are done first, then the radix-3 rounds.
+    fn fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+        log3_n: u32,
+        steps: FftSteps<F>,
+    ) -> EcResult<()> {
+        let n = (1 << log_n) * 3usize.pow(log3_n);
+        assert_eq!(
+            buffer.len() % n,
+            0,
+            "The buffer must consist of FFTs of size 2^log_n * 3^log3_n."
+        );
+
+        let closures = program_closures!(|program, args: FftArgs<F>| -> EcResult<()> {
+            let (buffer, cache) = args;
+            // The buffer may contain several FFTs of the same size.
+            let total = buffer.len();
+            let num_ffts = total / n;
+            let elementwise_work_size = elementwise_work_groups(total)?;
+
+            // Multiplies the element at index `i` by `g^i`.
+            let distribute_powers = |buffer, g: &F| -> EcResult<()> {
+                let powers_buffer = program.create_buffer_from_slice(&squares(g))?;
+                let kernel_name = format!("{}_distribute_powers", F::name());
+                let kernel = program.create_kernel(
+                    &kernel_name,
+                    elementwise_work_size,
+                    ELEMENTWISE_LOCAL_WORK_SIZE,
+                )?;
+                kernel
+                    .arg(buffer)
+                    .arg(&(total as u32))
+                    .arg(&(n as u32))
+                    .arg(&powers_buffer)
+                    .run()?;
+                Ok(())
+            };
+
+            // Multiplies all elements by `field`.
+            let mul_by_field = |buffer, field: &F| -> EcResult<()> {
+                let kernel_name = format!("{}_mul_by_field", F::name());
+                let kernel = program.create_kernel(
+                    &kernel_name,
+                    elementwise_work_size,
+                    ELEMENTWISE_LOCAL_WORK_SIZE,
+                )?;
+                kernel
+                    .arg(buffer)
+                    .arg(&(total as u32))
+                    .arg(&FieldArgument(*field))
+                    .run()?;
+                Ok(())
+            };
+
+            // The rounds read from `src_buffer` and write into `dst_buffer`, afterwards their
+            // contents are swapped, so that the result always ends up in the buffer of the caller.
+            let src_buffer = program.device_buffer_mut(buffer)?;
+            // Safe as every round writes all elements before they are read.
+            let mut dst_buffer = unsafe { program.create_buffer::<F>(total)? };
+            // The precalculated values `pq` and `omegas` are valid for radix degrees up to `max_deg`
             let max_deg = cmp::min(MAX_LOG2_RADIX, log_n);
 
-            // Precalculate:
-            // [omega^(0/(2^(deg-1))), omega^(1/(2^(deg-1))), ..., omega^((2^(deg-1)-1)/(2^(deg-1)))]
-            let mut pq = vec![F::zero(); 1 << max_deg >> 1];
-            let twiddle = omega.pow_vartime([(n >> max_deg) as u64]);
-            pq[0] = F::one();
-            if max_deg > 1 {
-                pq[1] = twiddle;
-                for i in 2..(1 << max_deg >> 1) {
-                    pq[i] = pq[i - 1];
-                    pq[i].mul_assign(&twiddle);
+            // Reuse the precalculated values if they are already on the device.
+            let slot = program.twiddle_slot(cache);
+            let position = slot
+                .iter()
+                .position(|twiddles| twiddles.omega == *omega && twiddles.n == n);
+            let twiddles = match position {
+                Some(position) => &slot[position],
+                None => {
+                    if slot.len() == MAX_CACHED_TWIDDLES {
+                        slot.remove(0);
+                    }
+                    slot.push(DeviceTwiddles {
+                        omega: *omega,
+                        n,
+                        pq: program.create_buffer_from_slice(&pq(omega, n, max_deg))?,
+                        omegas: program.create_buffer_from_slice(&squares(omega))?,
+                    });
+                    slot.last().expect("an element was just pushed")
                 }
-            }
-            let pq_buffer = program.create_buffer_from_slice(&pq)?;
+            };
 
-            // Precalculate [omega, omega^2, omega^4, omega^8, ..., omega^(2^31)]
-            let mut omegas = vec![F::zero(); 32];
-            omegas[0] = *omega;
-            for i in 1..LOG2_MAX_ELEMENTS {
-                omegas[i] = omegas[i - 1].pow_vartime([2u64]);
+            if let Some(g) = &steps.distribute_before {
+                distribute_powers(&*src_buffer, g)?;
             }
-            let omegas_buffer = program.create_buffer_from_slice(&omegas)?;
-
-            program.write_from_buffer(&mut src_buffer, &*input)?;
             // Specifies log2 of `p`, (http://www.bealto.com/gpu-fft_group-1.html)
             let mut log_p = 0u32;
             // Each iteration performs a FFT round
@@ -91,20 +685,20 @@ impl<'a, F: Field + GpuName> SingleFftKernel<'a, F> {
                 // 1=>radix2, 2=>radix4, 3=>radix8, ...
                 let deg = cmp::min(max_deg, log_n - log_p);
 
-                let n = 1u32 << log_n;
+                let n = n as u32;
                 let local_work_size = 1 << cmp::min(deg - 1, MAX_LOG2_LOCAL_WORK_SIZE);
-                let global_work_size = n >> deg;
+                let global_work_size = (n >> deg) as usize * num_ffts;
                 let kernel_name = format!("{}_radix_fft", F::name());
                 let kernel = program.create_kernel(
                     &kernel_name,
-                    global_work_size as usize,
+                    global_work_size,
                     local_work_size as usize,
                 )?;
                 kernel
-                    .arg(&src_buffer)
+                    .arg(&*src_buffer)
                     .arg(&dst_buffer)
-                    .arg(&pq_buffer)
-                    .arg(&omegas_buffer)
+                    .arg(&twiddles.pq)
+                    .arg(&twiddles.omegas)
                     .arg(&LocalBuffer::<F>::new(1 << deg))
                     .arg(&n)
                     .arg(&log_p)
@@ -113,15 +707,49 @@ impl<'a, F: Field + GpuName> SingleFftKernel<'a, F> {
                     .run()?;
 
                 log_p += deg;
-                std::mem::swap(&mut src_buffer, &mut dst_buffer);
+                std::mem::swap(src_buffer, &mut dst_buffer);
+            }
+
+            // The radix-3 rounds, `p` is the product of the radices of the previous rounds.
+            let mut p = 1u32 << log_n;
+            for _ in 0..log3_n {
+                if let Some(maybe_abort) = &self.maybe_abort {
+                    if maybe_abort() {
+                        return Err(EcError::Aborted);
+                    }
+                }
+
+                let count = total / 3;
+                let kernel_name = format!("{}_radix3_fft", F::name());
+                let kernel = program.create_kernel(
+                    &kernel_name,
+                    elementwise_work_groups(count)?,
+                    ELEMENTWISE_LOCAL_WORK_SIZE,
+                )?;
+                kernel
+                    .arg(&*src_buffer)
+                    .arg(&dst_buffer)
+                    .arg(&twiddles.omegas)
+                    .arg(&(n as u32))
+                    .arg(&p)
+                    .arg(&(count as u32))
+                    .run()?;
+
+                p *= 3;
+                std::mem::swap(src_buffer, &mut dst_buffer);
             }
 
-            program.read_into_buffer(&src_buffer, input)?;
+            if let Some(g) = &steps.distribute_after {
+                distribute_powers(&*src_buffer, g)?;
+            }
+            if let Some(field) = &steps.scale_after {
+                mul_by_field(&*src_buffer, field)?;
+            }
 
             Ok(())
         });
 
-        self.program.run(closures, input)
+        self.program.run(closures, (buffer, &mut self.twiddles))
     }
 }
 
@@ -192,6 +820,146 @@ where
         self.kernels[0].radix_fft(input, omega, log_n)
     }
 
+    /// Performs inverse FFT on `input`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    ///
+    /// Uses the first available GPU.
+    pub fn ifft(&mut self, input: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        self.kernels[0].ifft(input, omega, log_n)
+    }
+
+    /// Performs FFT on `input`, evaluating over the coset defined by the generator `g`
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The generator of the coset
+    ///
+    /// Uses the first available GPU.
+    pub fn coset_fft(&mut self, input: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        self.kernels[0].coset_fft(input, omega, log_n, g)
+    }
+
+    /// Performs the inverse of [`FftKernel::coset_fft`] on `input`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The same generator of the coset that is used for the forward FFT
+    ///
+    /// Uses the first available GPU.
+    pub fn icoset_fft(&mut self, input: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        self.kernels[0].icoset_fft(input, omega, log_n, g)
+    }
+
+    /// Performs FFT of size `n = 2^log2_n * 3^log3_n` on `input`
+    /// * `omega` - A primitive `n`-th root of unity
+    /// * `log2_n` - Specifies log2 of the power of two part of the number of elements
+    /// * `log3_n` - Specifies log3 of the power of three part of the number of elements
+    ///
+    /// Uses the first available GPU.
+    pub fn mixed_radix_fft(
+        &mut self,
+        input: &mut [F],
+        omega: &F,
+        log2_n: u32,
+        log3_n: u32,
+    ) -> EcResult<()> {
+        self.kernels[0].mixed_radix_fft(input, omega, log2_n, log3_n)
+    }
+
+    /// Copies `input` into a new buffer on the GPU, see [`FftBuffer`].
+    ///
+    /// Uses the first available GPU, like all other operations on buffers.
+    pub fn upload(&mut self, input: &[F]) -> EcResult<FftBuffer<F>> {
+        self.kernels[0].upload(input)
+    }
+
+    /// Copies the elements of `buffer` from the GPU into `output`.
+    pub fn download(&mut self, buffer: &FftBuffer<F>, output: &mut [F]) -> EcResult<()> {
+        self.kernels[0].download(buffer, output)
+    }
+
+    /// Performs FFT on `buffer`, without transferring it between the host and the GPU
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    pub fn radix_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+    ) -> EcResult<()> {
+        self.kernels[0].radix_fft_buffer(buffer, omega, log_n)
+    }
+
+    /// Performs inverse FFT on `buffer`, without transferring it between the host and the GPU
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    pub fn ifft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+    ) -> EcResult<()> {
+        self.kernels[0].ifft_buffer(buffer, omega, log_n)
+    }
+
+    /// Performs FFT on `buffer`, evaluating over the coset defined by the generator `g`, without
+    /// transferring it between the host and the GPU
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The generator of the coset
+    pub fn coset_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        self.kernels[0].coset_fft_buffer(buffer, omega, log_n, g)
+    }
+
+    /// Performs the inverse of [`FftKernel::coset_fft_buffer`] on `buffer`
+    /// * `omega` - The same `omega` that is used for the forward FFT
+    /// * `log_n` - Specifies log2 of number of elements
+    /// * `g` - The same generator of the coset that is used for the forward FFT
+    pub fn icoset_fft_buffer(
+        &mut self,
+        buffer: &mut FftBuffer<F>,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        self.kernels[0].icoset_fft_buffer(buffer, omega, log_n, g)
+    }
+
+    /// Adds the elements of `b` to the elements of `a` at the same index, on the GPU.
+    pub fn add_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.kernels[0].add_assign_buffer(a, b)
+    }
+
+    /// Multiplies the elements of `a` by the elements of `b` at the same index, on the GPU.
+    pub fn mul_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.kernels[0].mul_assign_buffer(a, b)
+    }
+
+    /// Subtracts the elements of `b` from the elements of `a` at the same index, on the GPU.
+    pub fn sub_assign_buffer(&mut self, a: &mut FftBuffer<F>, b: &FftBuffer<F>) -> EcResult<()> {
+        self.kernels[0].sub_assign_buffer(a, b)
+    }
+
+    /// Multiplies all elements of `buffer` by `c`, on the GPU.
+    pub fn scale_buffer(&mut self, buffer: &mut FftBuffer<F>, c: &F) -> EcResult<()> {
+        self.kernels[0].scale_buffer(buffer, c)
+    }
+
+    /// Inverts all elements of `buffer` on the GPU, zeros stay zero.
+    pub fn batch_inverse_buffer(&mut self, buffer: &mut FftBuffer<F>) -> EcResult<()> {
+        self.kernels[0].batch_inverse_buffer(buffer)
+    }
+
+    /// Returns a new buffer on the GPU with the powers `[x^0, x^1, ..., x^(n-1)]`.
+    pub fn powers_buffer(&mut self, x: &F, n: usize) -> EcResult<FftBuffer<F>> {
+        self.kernels[0].powers_buffer(x, n)
+    }
+
     /// Performs FFT on `inputs`
     /// * `omega` - Special value `omega` is used for FFT over finite-fields
     /// * `log_n` - Specifies log2 of number of elements
@@ -217,7 +985,7 @@ where
                 .zip(self.kernels.iter_mut())
             {
                 let result = result.clone();
-                s.execute(move || {
+                s.spawn(move |_| {
                     for ((input, omega), log_n) in
                         inputs.iter_mut().zip(omegas.iter()).zip(log_ns.iter())
                     {
@@ -236,4 +1004,131 @@ where
 
         Arc::try_unwrap(result).unwrap().into_inner().unwrap()
     }
+
+    /// Performs FFT on each of the vectors of size `2^log_n`, which are stored one after another
+    /// in `input`
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements of a single vector
+    ///
+    /// The vectors are distributed over all available GPUs, each GPU transforms its share with a
+    /// single upload and a single set of kernel launches.
+    pub fn radix_fft_batch(&mut self, input: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        let n = 1 << log_n;
+        assert_eq!(
+            input.len() % n,
+            0,
+            "The input must consist of FFTs of size 2^log_n."
+        );
+        let num_ffts = input.len() / n;
+        let num_devices = self.kernels.len();
+        let ffts_per_device = cmp::max((num_ffts + num_devices - 1) / num_devices, 1);
+
+        let result = Arc::new(RwLock::new(Ok(())));
+
+        THREAD_POOL.scoped(|s| {
+            for (input, kern) in input
+                .chunks_mut(ffts_per_device * n)
+                .zip(self.kernels.iter_mut())
+            {
+                let result = result.clone();
+                s.spawn(move |_| {
+                    if let Err(err) = kern.radix_fft_batch(input, omega, log_n) {
+                        *result.write().unwrap() = Err(err);
+                    }
+                });
+            }
+        });
+
+        Arc::try_unwrap(result).unwrap().into_inner().unwrap()
+    }
+
+    /// Performs FFT on `input` with the four-step (Bailey) algorithm, see
+    /// [`crate::fft_cpu::four_step_fft`] for the decomposition.
+    /// * `worker` - The worker that does the transpositions and twiddle multiplications
+    /// * `omega` - Special value `omega` is used for FFT over finite-fields
+    /// * `log_n` - Specifies log2 of number of elements
+    ///
+    /// The input stays in host memory, the GPUs only get blocks of rows of size `~sqrt(n)` that
+    /// fit into their memory. Hence the FFT can be larger than the memory of a single GPU. The
+    /// rows are distributed over all available GPUs. The transpositions and the multiplications
+    /// with the twiddle factors are done on the CPU, which needs `n` elements of additional host
+    /// memory.
+    pub fn four_step_fft(
+        &mut self,
+        input: &mut [F],
+        worker: &Worker,
+        omega: &F,
+        log_n: u32,
+    ) -> EcResult<()> {
+        assert_eq!(input.len(), 1 << log_n);
+        let (log_n1, log_n2) = four_step_split(log_n);
+        let (n1, n2) = (1 << log_n1, 1 << log_n2);
+        let mut tmp = vec![F::zero(); input.len()];
+
+        // Step 1: the columns of `input` are the rows of `tmp`.
+        worker.install(|| transpose(input, &mut tmp, n2, n1));
+        self.fft_rows(&mut tmp, &omega.pow_vartime([n1 as u64]), log_n2)?;
+
+        // Step 2: the element `k2` of row `j1` is multiplied by `omega^(j1 * k2)`.
+        worker.install(|| {
+            tmp.par_chunks_mut(n2).enumerate().for_each(|(j1, row)| {
+                let w = omega.pow_vartime([j1 as u64]);
+                let mut u = F::one();
+                for value in row.iter_mut() {
+                    *value *= u;
+                    u *= w;
+                }
+            })
+        });
+
+        // Step 3
+        worker.install(|| transpose(&tmp, input, n1, n2));
+        self.fft_rows(input, &omega.pow_vartime([n2 as u64]), log_n1)?;
+
+        // Step 4
+        worker.install(|| transpose(input, &mut tmp, n2, n1));
+        input.copy_from_slice(&tmp);
+        Ok(())
+    }
+
+    /// Performs an FFT of size `2^log_row` on each row of `rows`, distributed over all GPUs.
+    ///
+    /// Every GPU uploads as many rows at once as fit into its memory and transforms them with a
+    /// single set of kernel launches, like [`SingleFftKernel::radix_fft_batch`].
+    fn fft_rows(&mut self, rows: &mut [F], omega: &F, log_row: u32) -> EcResult<()> {
+        // An FFT of size one is the identity.
+        if log_row == 0 {
+            return Ok(());
+        }
+        let row_size = 1 << log_row;
+        let num_rows = rows.len() / row_size;
+        let num_devices = self.kernels.len();
+        let rows_per_device = (num_rows + num_devices - 1) / num_devices;
+
+        let result = Arc::new(RwLock::new(Ok(())));
+
+        THREAD_POOL.scoped(|s| {
+            for (rows, kern) in rows
+                .chunks_mut(rows_per_device * row_size)
+                .zip(self.kernels.iter_mut())
+            {
+                let result = result.clone();
+                s.spawn(move |_| {
+                    let rows_per_block = calc_rows_per_block::<F>(kern.memory, row_size);
+                    for block in rows.chunks_mut(rows_per_block * row_size) {
+                        if result.read().unwrap().is_err() {
+                            break;
+                        }
+
+                        if let Err(err) = kern.radix_fft_batch(block, omega, log_row) {
+                            *result.write().unwrap() = Err(err);
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        Arc::try_unwrap(result).unwrap().into_inner().unwrap()
+    }
 }
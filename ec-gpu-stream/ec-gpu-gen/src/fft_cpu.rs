@@ -1,56 +1,196 @@
+use std::any::{Any, TypeId};
+use std::cmp;
+use std::sync::{Arc, Mutex};
+
 use ff::PrimeField;
+use once_cell::sync::Lazy;
+use rayon::prelude::{
+    IndexedParallelIterator, IntoParallelIterator, ParallelIterator, ParallelSliceMut,
+};
 
 use crate::threadpool::Worker;
 
+/// The number of columns that are transposed by a single task.
+const TRANSPOSE_BLOCK_SIZE: usize = 16;
+
+/// The number of low and high bits of an index, which define the blocks of the bit-reversal
+/// permutation.
+const BITREVERSE_BLOCK_BITS: u32 = 5;
+
+/// The number of elements of the first round of the in-place FFT that are processed by a single
+/// task.
+const FFT_CHUNK_SIZE: usize = 1 << 12;
+
+/// The number of twiddle factors that are calculated by a single task.
+const TWIDDLES_CHUNK_SIZE: usize = 1 << 12;
+
+/// The maximum number of twiddle factor tables that are cached, it's the same as on the GPU.
+const MAX_CACHED_TWIDDLES: usize = 8;
+
+/// The twiddle factors are cached per field, `omega` (as bytes) and `log_n`.
+type TwiddleKey = (TypeId, Vec<u8>, u32);
+
+static TWIDDLE_CACHE: Lazy<Mutex<TwiddleLru>> = Lazy::new(Default::default);
+
+/// A cache of twiddle factor tables, which evicts the least recently used one when it is full.
+#[derive(Default)]
+struct TwiddleLru {
+    /// The tables, the most recently used one is the last.
+    entries: Vec<(TwiddleKey, Arc<dyn Any + Send + Sync>)>,
+}
+
+impl TwiddleLru {
+    /// Returns the table of the given key and marks it as the most recently used one.
+    fn get(&mut self, key: &TwiddleKey) -> Option<Arc<dyn Any + Send + Sync>> {
+        let position = self.entries.iter().position(|(cached, _)| cached == key)?;
+        let entry = self.entries.remove(position);
+        let twiddles = entry.1.clone();
+        self.entries.push(entry);
+        Some(twiddles)
+    }
+
+    /// Inserts a table, unless there already is one for that key, and returns the cached one.
+    fn insert(
+        &mut self,
+        key: TwiddleKey,
+        twiddles: Arc<dyn Any + Send + Sync>,
+    ) -> Arc<dyn Any + Send + Sync> {
+        // Another thread might have calculated the same table in the meantime.
+        if let Some(cached) = self.get(&key) {
+            return cached;
+        }
+        if self.entries.len() == MAX_CACHED_TWIDDLES {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, twiddles.clone()));
+        twiddles
+    }
+}
+
 /// Calculate the Fast Fourier Transform on the CPU (single-threaded).
 ///
 /// The input `a` is mutated and contains the result when this function returns. The length of the
-/// input vector must be `2^log_n`.
-#[allow(clippy::many_single_char_names)]
+/// input vector must be `2^log_n`. The twiddle factors are calculated on the current thread, use
+/// [`serial_fft_with_twiddles`] in order to reuse them.
 pub fn serial_fft<F: PrimeField>(a: &mut [F], omega: &F, log_n: u32) {
-    fn bitreverse(mut n: u32, l: u32) -> u32 {
-        let mut r = 0;
-        for _ in 0..l {
-            r = (r << 1) | (n & 1);
-            n >>= 1;
-        }
-        r
-    }
+    let mut twiddles = vec![F::zero(); (1 << log_n) >> 1];
+    powers_into(&mut twiddles, omega, F::one());
+    serial_fft_with_twiddles(a, &twiddles, log_n);
+}
 
-    let n = a.len() as u32;
+/// Calculate the Fast Fourier Transform on the CPU (single-threaded), with precomputed twiddle
+/// factors.
+///
+/// The `twiddles` are the ones returned by [`twiddles`]. They may also be the ones of a bigger FFT
+/// whose size is a power of two multiple of `2^log_n`, then only every `stride`th one is used.
+pub fn serial_fft_with_twiddles<F: PrimeField>(a: &mut [F], twiddles: &[F], log_n: u32) {
+    let n = a.len();
     assert_eq!(n, 1 << log_n);
-
-    for k in 0..n {
-        let rk = bitreverse(k, log_n);
-        if k < rk {
-            a.swap(rk as usize, k as usize);
-        }
+    if n == 1 {
+        return;
     }
+    let stride = twiddles.len() / (n / 2);
+    assert_eq!(twiddles.len(), stride * (n / 2), "Twiddles of wrong size.");
+
+    bitreverse_permutation(a, log_n);
 
     let mut m = 1;
     for _ in 0..log_n {
-        let w_m = omega.pow_vartime(&[u64::from(n / (2 * m))]);
+        // The twiddle factor of index `j` is `omega^(j * n / (2 * m))`.
+        let step = n / (2 * m) * stride;
 
-        let mut k = 0;
-        while k < n {
-            let mut w = F::one();
+        for k in (0..n).step_by(2 * m) {
             for j in 0..m {
-                let mut t = a[(k + j + m) as usize];
-                t *= w;
-                let mut tmp = a[(k + j) as usize];
+                let mut t = a[k + j + m];
+                t *= twiddles[j * step];
+                let mut tmp = a[k + j];
                 tmp -= t;
-                a[(k + j + m) as usize] = tmp;
-                a[(k + j) as usize] += t;
-                w *= w_m;
+                a[k + j + m] = tmp;
+                a[k + j] += t;
             }
-
-            k += 2 * m;
         }
 
         m *= 2;
     }
 }
 
+/// Returns the twiddle factors `[omega^0, omega^1, ..., omega^(n/2 - 1)]` of an FFT of size
+/// `n = 2^log_n`.
+///
+/// They are calculated in parallel on the current thread pool.
+pub fn twiddles<F: PrimeField>(omega: &F, log_n: u32) -> Vec<F> {
+    let mut twiddles = vec![F::zero(); (1 << log_n) >> 1];
+    twiddles
+        .par_chunks_mut(TWIDDLES_CHUNK_SIZE)
+        .enumerate()
+        .for_each(|(i, chunk)| {
+            let start = omega.pow_vartime(&[(i * TWIDDLES_CHUNK_SIZE) as u64]);
+            powers_into(chunk, omega, start);
+        });
+    twiddles
+}
+
+/// Fills `powers` with `start * omega^i` for every index `i`.
+fn powers_into<F: PrimeField>(powers: &mut [F], omega: &F, start: F) {
+    let mut w = start;
+    for power in powers {
+        *power = w;
+        w *= omega;
+    }
+}
+
+/// Returns the twiddle factors of [`twiddles`], they are only calculated once for every field,
+/// `omega` and `log_n`, as long as they are cached.
+///
+/// The cache keeps the eight most recently used tables, like the GPU FFT does. Use
+/// [`clear_twiddle_cache`] to free the memory earlier.
+pub fn cached_twiddles<F: PrimeField>(omega: &F, log_n: u32) -> Arc<Vec<F>> {
+    cached_twiddles_in(&TWIDDLE_CACHE, omega, log_n)
+}
+
+/// Returns the twiddle factors of [`twiddles`] from the given cache.
+fn cached_twiddles_in<F: PrimeField>(
+    cache: &Mutex<TwiddleLru>,
+    omega: &F,
+    log_n: u32,
+) -> Arc<Vec<F>> {
+    let key = (TypeId::of::<F>(), omega.to_repr().as_ref().to_vec(), log_n);
+    if let Some(twiddles) = cache.lock().unwrap().get(&key) {
+        return twiddles.downcast().expect("the key contains the type");
+    }
+
+    // The lock isn't held while the twiddle factors are calculated, as it's done in parallel and
+    // the threads might need the cache for other work.
+    let twiddles = Arc::new(twiddles(omega, log_n));
+    let cached = cache.lock().unwrap().insert(key, twiddles);
+    cached.downcast().expect("the key contains the type")
+}
+
+/// Removes all twiddle factors from the cache that is used by [`cached_twiddles`].
+pub fn clear_twiddle_cache() {
+    TWIDDLE_CACHE.lock().unwrap().entries.clear();
+}
+
+/// Reverses the `l` least significant bits of `n`.
+fn bitreverse(mut n: u32, l: u32) -> u32 {
+    let mut r = 0;
+    for _ in 0..l {
+        r = (r << 1) | (n & 1);
+        n >>= 1;
+    }
+    r
+}
+
+/// Permutes the elements, so that each element is at the bit-reversed position of its index.
+fn bitreverse_permutation<F>(a: &mut [F], log_n: u32) {
+    for k in 0..a.len() as u32 {
+        let rk = bitreverse(k, log_n);
+        if k < rk {
+            a.swap(rk as usize, k as usize);
+        }
+    }
+}
+
 /// Calculate the Fast Fourier Transform on the CPU (multithreaded).
 ///
 /// The result is is written to the input `a`.
@@ -63,18 +203,55 @@ pub fn parallel_fft<F: PrimeField>(
     log_n: u32,
     log_threads: u32,
 ) {
+    let new_omega = omega.pow_vartime(&[1u64 << log_threads]);
+    let twiddles = worker.install(|| cached_twiddles(&new_omega, log_n - log_threads));
+    parallel_fft_inner(a, worker, omega, log_n, log_threads, |tmp, log_new_n| {
+        serial_fft_with_twiddles::<F>(tmp, &twiddles, log_new_n)
+    });
+}
+
+/// Calculate the Fast Fourier Transform on the CPU (multithreaded), with precomputed twiddle
+/// factors.
+///
+/// The `twiddles` are the ones of `omega` returned by [`twiddles`], see also
+/// [`serial_fft_with_twiddles`].
+pub fn parallel_fft_with_twiddles<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    twiddles: &[F],
+    log_n: u32,
+    log_threads: u32,
+) {
+    parallel_fft_inner(a, worker, omega, log_n, log_threads, |tmp, log_new_n| {
+        serial_fft_with_twiddles::<F>(tmp, twiddles, log_new_n)
+    });
+}
+
+/// Splits the FFT into `2^log_threads` sub-FFTs, which are calculated with `sub_fft`.
+fn parallel_fft_inner<F, S>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_n: u32,
+    log_threads: u32,
+    sub_fft: S,
+) where
+    F: PrimeField,
+    S: Fn(&mut [F], u32) + Sync,
+{
     assert!(log_n >= log_threads);
 
     let num_threads = 1 << log_threads;
     let log_new_n = log_n - log_threads;
     let mut tmp = vec![vec![F::zero(); 1 << log_new_n]; num_threads];
-    let new_omega = omega.pow_vartime(&[num_threads as u64]);
 
     worker.scope(0, |scope, _| {
         let a = &*a;
+        let sub_fft = &sub_fft;
 
         for (j, tmp) in tmp.iter_mut().enumerate() {
-            scope.execute(move || {
+            scope.spawn(move |_| {
                 // Shuffle into a sub-FFT
                 let omega_j = omega.pow_vartime(&[j as u64]);
                 let omega_step = omega.pow_vartime(&[(j as u64) << log_new_n]);
@@ -92,7 +269,7 @@ pub fn parallel_fft<F: PrimeField>(
                 }
 
                 // Perform sub-FFT
-                serial_fft::<F>(tmp, &new_omega, log_new_n);
+                sub_fft(tmp, log_new_n);
             });
         }
     });
@@ -102,7 +279,7 @@ pub fn parallel_fft<F: PrimeField>(
         let tmp = &tmp;
 
         for (idx, a) in a.chunks_mut(chunk).enumerate() {
-            scope.execute(move || {
+            scope.spawn(move |_| {
                 let mut idx = idx * chunk;
                 let mask = (1 << log_threads) - 1;
                 for a in a {
@@ -114,6 +291,343 @@ pub fn parallel_fft<F: PrimeField>(
     });
 }
 
+/// A pointer to the elements of a slice that can be shared between threads.
+///
+/// It's used by tasks that are known to access disjoint elements.
+struct SharedSlice<F>(*mut F);
+
+unsafe impl<F: Send> Send for SharedSlice<F> {}
+unsafe impl<F: Send> Sync for SharedSlice<F> {}
+
+impl<F> SharedSlice<F> {
+    /// Swaps the elements at index `i` and `j`.
+    ///
+    /// # Safety
+    ///
+    /// Both indices must be within the slice and no other thread may access those elements at
+    /// the same time.
+    unsafe fn swap(&self, i: usize, j: usize) {
+        std::ptr::swap(self.0.add(i), self.0.add(j));
+    }
+}
+
+/// Permutes the elements like [`bitreverse_permutation`], in parallel on the current thread pool.
+///
+/// An index is split into its `high`, `middle` and `low` bits, where `high` and `low` have
+/// `BITREVERSE_BLOCK_BITS` bits each. The elements of the same middle bits form a block, which is
+/// swapped with the block of the reversed middle bits. A single task swaps a pair of blocks, it
+/// only touches a few cache lines.
+fn parallel_bitreverse_permutation<F: Send>(a: &mut [F], log_n: u32) {
+    let block_bits = BITREVERSE_BLOCK_BITS;
+    if log_n < 2 * block_bits {
+        bitreverse_permutation(a, log_n);
+        return;
+    }
+
+    let log_middle = log_n - 2 * block_bits;
+    let shared = SharedSlice(a.as_mut_ptr());
+    (0..1u32 << log_middle).into_par_iter().for_each(|middle| {
+        let reversed_middle = bitreverse(middle, log_middle);
+        if reversed_middle < middle {
+            return;
+        }
+        for high in 0..1 << block_bits {
+            for low in 0..1 << block_bits {
+                let i = high << (log_n - block_bits) | middle << block_bits | low;
+                let j = bitreverse(low, block_bits) << (log_n - block_bits)
+                    | reversed_middle << block_bits
+                    | bitreverse(high, block_bits);
+                // Within a single block each pair must only be swapped once.
+                if middle < reversed_middle || i < j {
+                    // SAFETY: The indices are within the slice as they have `log_n` bits. Only
+                    // this task accesses elements with the middle bits `middle` or
+                    // `reversed_middle`.
+                    unsafe { shared.swap(i as usize, j as usize) };
+                }
+            }
+        }
+    });
+}
+
+/// Splits a block of size `4 * m` into its quarters.
+fn split_quarters<F>(block: &mut [F], m: usize) -> (&mut [F], &mut [F], &mut [F], &mut [F]) {
+    let (q01, q23) = block.split_at_mut(2 * m);
+    let (q0, q1) = q01.split_at_mut(m);
+    let (q2, q3) = q23.split_at_mut(m);
+    (q0, q1, q2, q3)
+}
+
+/// Performs two radix-2 rounds at once, on the quarters `q0`, `q1`, `q2` and `q3` of a block of
+/// size `4 * m`, starting at index `j0` within the quarters.
+///
+/// `step` is the distance of the twiddle factors of the second round.
+#[allow(clippy::too_many_arguments)]
+fn radix4_butterflies<F: PrimeField>(
+    q0: &mut [F],
+    q1: &mut [F],
+    q2: &mut [F],
+    q3: &mut [F],
+    j0: usize,
+    m: usize,
+    twiddles: &[F],
+    step: usize,
+) {
+    let quarters = q0.iter_mut().zip(q1).zip(q2).zip(q3);
+    for (j, (((x0, x1), x2), x3)) in quarters.enumerate() {
+        let j = j0 + j;
+        // The round of the blocks of size `2 * m`.
+        let w1 = twiddles[j * 2 * step];
+        let t1 = *x1 * w1;
+        let t3 = *x3 * w1;
+        let (b0, b1) = (*x0 + t1, *x0 - t1);
+        let (b2, b3) = (*x2 + t3, *x2 - t3);
+        // The round of the blocks of size `4 * m`.
+        let u = b2 * twiddles[j * step];
+        let v = b3 * twiddles[(j + m) * step];
+        *x0 = b0 + u;
+        *x2 = b0 - u;
+        *x1 = b1 + v;
+        *x3 = b1 - v;
+    }
+}
+
+/// Calculate the Fast Fourier Transform on the CPU (multithreaded), in place.
+///
+/// Contrary to [`parallel_fft`] it works for every size and only needs constant additional
+/// memory, apart from the cached twiddle factors, see [`cached_twiddles`].
+pub fn in_place_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let twiddles = worker.install(|| cached_twiddles(omega, log_n));
+    in_place_fft_with_twiddles(a, worker, &twiddles, log_n);
+}
+
+/// Calculate the Fast Fourier Transform on the CPU (multithreaded), in place, with precomputed
+/// twiddle factors.
+///
+/// The `twiddles` are the ones returned by [`twiddles`]. After a bit-reversal permutation, two
+/// rounds are done at once (radix-4) with a single pass over the data. If there are fewer blocks
+/// than threads, the butterflies within the blocks are split among the threads.
+pub fn in_place_fft_with_twiddles<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    twiddles: &[F],
+    log_n: u32,
+) {
+    let n = a.len();
+    assert_eq!(n, 1 << log_n);
+    if n == 1 {
+        return;
+    }
+    assert_eq!(twiddles.len(), n / 2, "Twiddles of wrong size.");
+
+    worker.install(|| {
+        let num_threads = rayon::current_num_threads();
+        parallel_bitreverse_permutation(a, log_n);
+
+        // With an odd number of rounds, the first one is a radix-2 round, all its twiddle factors
+        // are one.
+        let mut log_m = 0;
+        if log_n % 2 == 1 {
+            a.par_chunks_mut(FFT_CHUNK_SIZE).for_each(|chunk| {
+                for pair in chunk.chunks_mut(2) {
+                    let t = pair[1];
+                    pair[1] = pair[0] - t;
+                    pair[0] += t;
+                }
+            });
+            log_m = 1;
+        }
+
+        while log_m < log_n {
+            let m = 1 << log_m;
+            // The twiddle factor of index `j` of the second round is `omega^(j * n / (4 * m))`.
+            let step = n / (4 * m);
+
+            if n / (4 * m) >= num_threads {
+                a.par_chunks_mut(4 * m).for_each(|block| {
+                    let (q0, q1, q2, q3) = split_quarters(block, m);
+                    radix4_butterflies(q0, q1, q2, q3, 0, m, twiddles, step);
+                });
+            } else {
+                let chunk_size = cmp::max(m / num_threads, 1);
+                for block in a.chunks_mut(4 * m) {
+                    let (q0, q1, q2, q3) = split_quarters(block, m);
+                    q0.par_chunks_mut(chunk_size)
+                        .zip(q1.par_chunks_mut(chunk_size))
+                        .zip(q2.par_chunks_mut(chunk_size))
+                        .zip(q3.par_chunks_mut(chunk_size))
+                        .enumerate()
+                        .for_each(|(i, (((q0, q1), q2), q3))| {
+                            radix4_butterflies(q0, q1, q2, q3, i * chunk_size, m, twiddles, step);
+                        });
+                }
+            }
+
+            log_m += 2;
+        }
+    });
+}
+
+/// Calculate the Fast Fourier Transform on the CPU.
+///
+/// It uses [`in_place_fft`] on the threads of the `worker`.
+pub fn fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    in_place_fft(a, worker, omega, log_n);
+}
+
+/// Calculate the Fast Fourier Transform on the CPU of each of the vectors of size `2^log_n`, which
+/// are stored one after another in `a`.
+///
+/// If there are at least as many vectors as threads, each vector is transformed by a single thread,
+/// else they are transformed one after another with [`in_place_fft`].
+pub fn fft_batch<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let n = 1 << log_n;
+    assert_eq!(
+        a.len() % n,
+        0,
+        "The input must consist of FFTs of size 2^log_n."
+    );
+    let twiddles = worker.install(|| cached_twiddles(omega, log_n));
+    if a.len() / n >= worker.num_threads() {
+        worker.install(|| {
+            a.par_chunks_mut(n)
+                .for_each(|a| serial_fft_with_twiddles(a, &twiddles, log_n))
+        });
+    } else {
+        for a in a.chunks_mut(n) {
+            in_place_fft_with_twiddles(a, worker, &twiddles, log_n);
+        }
+    }
+}
+
+/// Calculate the inverse Fast Fourier Transform on the CPU.
+///
+/// `omega` is the same value as used for the forward FFT. The result is scaled by `1/n`, so that
+/// it's the inverse of [`fft`].
+pub fn ifft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    let omega_inv = omega.invert().expect("omega is not zero");
+    fft(a, worker, &omega_inv, log_n);
+    let n_inv = F::from(1u64 << log_n)
+        .invert()
+        .expect("the size of the FFT is not a multiple of the modulus");
+    scale(a, worker, &n_inv);
+}
+
+/// Calculate the Fast Fourier Transform on the CPU over the coset defined by the generator `g`.
+///
+/// The element at index `i` is multiplied by `g^i` before the FFT.
+pub fn coset_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32, g: &F) {
+    distribute_powers(a, worker, g);
+    fft(a, worker, omega, log_n);
+}
+
+/// Calculate the inverse of [`coset_fft`] on the CPU.
+///
+/// `omega` and `g` are the same values as used for the forward FFT.
+pub fn icoset_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32, g: &F) {
+    ifft(a, worker, omega, log_n);
+    let g_inv = g.invert().expect("the generator is not zero");
+    distribute_powers(a, worker, &g_inv);
+}
+
+/// Multiplies the element at index `i` by `g^i` (multithreaded).
+pub fn distribute_powers<F: PrimeField>(a: &mut [F], worker: &Worker, g: &F) {
+    worker.scope(a.len(), |scope, chunk| {
+        for (i, a) in a.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut u = g.pow_vartime(&[(i * chunk) as u64]);
+                for a in a.iter_mut() {
+                    *a *= u;
+                    u *= g;
+                }
+            });
+        }
+    });
+}
+
+/// Multiplies all elements by `c` (multithreaded).
+pub fn scale<F: PrimeField>(a: &mut [F], worker: &Worker, c: &F) {
+    worker.scope(a.len(), |scope, chunk| {
+        for a in a.chunks_mut(chunk) {
+            scope.spawn(move |_| {
+                for a in a.iter_mut() {
+                    *a *= c;
+                }
+            });
+        }
+    });
+}
+
+/// Returns log2 of the number of columns and rows, into which an FFT of size `2^log_n` is split
+/// by the four-step FFT.
+pub(crate) fn four_step_split(log_n: u32) -> (u32, u32) {
+    let log_cols = log_n / 2;
+    (log_cols, log_n - log_cols)
+}
+
+/// Transposes the `rows x cols` matrix `src`, stored in row-major order, into `dst`.
+///
+/// It runs in parallel on the current thread pool. The columns are processed in blocks, so that
+/// consecutive elements are read from each row.
+pub(crate) fn transpose<T: Copy + Send + Sync>(src: &[T], dst: &mut [T], rows: usize, cols: usize) {
+    assert_eq!(src.len(), rows * cols);
+    assert_eq!(dst.len(), rows * cols);
+
+    dst.par_chunks_mut(rows * TRANSPOSE_BLOCK_SIZE)
+        .enumerate()
+        .for_each(|(block, dst)| {
+            let col = block * TRANSPOSE_BLOCK_SIZE;
+            for (row, src) in src.chunks(cols).enumerate() {
+                for (i, value) in src[col..].iter().take(TRANSPOSE_BLOCK_SIZE).enumerate() {
+                    dst[i * rows + row] = *value;
+                }
+            }
+        });
+}
+
+/// Calculate the Fast Fourier Transform on the CPU with the four-step (Bailey) algorithm.
+///
+/// The input of size `n = n1 * n2` is seen as a matrix with `n2` rows and `n1` columns. The steps
+/// are:
+///  1. An FFT of size `n2` over each column.
+///  2. A multiplication of the element `k2` of column `j1` with `omega^(j1 * k2)`.
+///  3. An FFT of size `n1` over each row.
+///  4. A transposition of the result.
+///
+/// The columns are transposed into rows before the FFTs, so that every FFT runs on contiguous
+/// memory. It needs `n` elements of additional memory.
+pub fn four_step_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log_n: u32) {
+    assert_eq!(a.len(), 1 << log_n);
+    let (log_n1, log_n2) = four_step_split(log_n);
+    let (n1, n2) = (1 << log_n1, 1 << log_n2);
+
+    worker.install(|| {
+        let mut tmp = vec![F::zero(); a.len()];
+
+        // Steps 1 and 2: the columns of `a` are the rows of `tmp`.
+        transpose(a, &mut tmp, n2, n1);
+        let twiddles = cached_twiddles(&omega.pow_vartime(&[n1 as u64]), log_n2);
+        tmp.par_chunks_mut(n2).enumerate().for_each(|(j1, column)| {
+            serial_fft_with_twiddles(column, &twiddles, log_n2);
+            let w = omega.pow_vartime(&[j1 as u64]);
+            let mut u = F::one();
+            for value in column.iter_mut() {
+                *value *= u;
+                u *= w;
+            }
+        });
+
+        // Step 3
+        transpose(&tmp, a, n1, n2);
+        let twiddles = cached_twiddles(&omega.pow_vartime(&[n2 as u64]), log_n1);
+        a.par_chunks_mut(n1)
+            .for_each(|row| serial_fft_with_twiddles(row, &twiddles, log_n1));
+
+        // Step 4
+        transpose(a, &mut tmp, n2, n1);
+        a.copy_from_slice(&tmp);
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,18 +635,10 @@ mod tests {
     use std::cmp::min;
 
     use blstrs::Scalar as Fr;
-    use ff::PrimeField;
-    use rand_core::RngCore;
+    use ff::{Field, PrimeField};
 
-    fn omega<F: PrimeField>(num_coeffs: usize) -> F {
-        // Compute omega, the 2^exp primitive root of unity
-        let exp = (num_coeffs as f32).log2().floor() as u32;
-        let mut omega = F::root_of_unity();
-        for _ in exp..F::S {
-            omega = omega.square();
-        }
-        omega
-    }
+    use crate::domain::EvaluationDomain;
+    use rand_core::RngCore;
 
     #[test]
     fn parallel_fft_consistency() {
@@ -145,7 +651,7 @@ mod tests {
 
                     let mut v1_coeffs = (0..d).map(|_| F::random(&mut *rng)).collect::<Vec<_>>();
                     let mut v2_coeffs = v1_coeffs.clone();
-                    let v1_omega = omega::<F>(v1_coeffs.len());
+                    let v1_omega = *EvaluationDomain::<F>::new(log_d).unwrap().omega();
                     let v2_omega = v1_omega;
 
                     for log_threads in log_d..min(log_d + 1, 3) {
@@ -162,4 +668,150 @@ mod tests {
 
         test_consistency::<Fr, _>(rng);
     }
+
+    #[test]
+    fn in_place_fft_consistency() {
+        let rng = &mut rand::thread_rng();
+
+        // A single thread as well as more threads than blocks in the last rounds.
+        for num_threads in [1, 3, 8] {
+            let worker = Worker::with_config(num_threads, "in-place-fft-test").unwrap();
+            for log_d in 0..=16 {
+                let d = 1 << log_d;
+                let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+                let mut expected = (0..d).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+                let mut v = expected.clone();
+
+                serial_fft(&mut expected, &omega, log_d);
+                in_place_fft(&mut v, &worker, &omega, log_d);
+                assert_eq!(v, expected, "in-place FFT of size 2^{}", log_d);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_bitreverse() {
+        for log_n in [0, 9, 10, 11, 15] {
+            let mut expected = (0..1u32 << log_n).collect::<Vec<_>>();
+            let mut v = expected.clone();
+            bitreverse_permutation(&mut expected, log_n);
+            parallel_bitreverse_permutation(&mut v, log_n);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn fft_batch_consistency() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::with_config(4, "fft-batch-test").unwrap();
+        let log_d = 8;
+        let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+
+        // Fewer and more vectors than threads.
+        for num_ffts in [1, 3, 9] {
+            let mut v = (0..num_ffts << log_d)
+                .map(|_| Fr::random(&mut *rng))
+                .collect::<Vec<_>>();
+            let mut expected = v.clone();
+
+            fft_batch(&mut v, &worker, &omega, log_d);
+            for a in expected.chunks_mut(1 << log_d) {
+                serial_fft(a, &omega, log_d);
+            }
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn twiddle_cache() {
+        // A cache of its own, as other tests running in parallel might evict from the global one.
+        let cache = Mutex::new(TwiddleLru::default());
+        let omega = *EvaluationDomain::<Fr>::new(16).unwrap().omega();
+        let twiddles = cached_twiddles_in(&cache, &omega, 16);
+        assert_eq!(twiddles.len(), 1 << 15);
+        let mut w = Fr::one();
+        for twiddle in twiddles.iter() {
+            assert_eq!(*twiddle, w);
+            w *= omega;
+        }
+        assert!(Arc::ptr_eq(
+            &twiddles,
+            &cached_twiddles_in(&cache, &omega, 16)
+        ));
+        assert!(!Arc::ptr_eq(
+            &twiddles,
+            &cached_twiddles_in(&cache, &omega.square(), 15)
+        ));
+    }
+
+    #[test]
+    fn twiddle_cache_eviction() {
+        let cache = Mutex::new(TwiddleLru::default());
+        let omega = *EvaluationDomain::<Fr>::new(4).unwrap().omega();
+        let first = cached_twiddles_in(&cache, &omega, 4);
+        let second = cached_twiddles_in(&cache, &omega, 3);
+        for log_n in 5..(MAX_CACHED_TWIDDLES as u32 + 3) {
+            cached_twiddles_in(&cache, &omega, log_n);
+        }
+
+        // Using the first table again makes the second one the least recently used.
+        assert!(Arc::ptr_eq(&first, &cached_twiddles_in(&cache, &omega, 4)));
+        cached_twiddles_in(&cache, &omega, 1);
+        assert_eq!(cache.lock().unwrap().entries.len(), MAX_CACHED_TWIDDLES);
+        assert!(!Arc::ptr_eq(
+            &second,
+            &cached_twiddles_in(&cache, &omega, 3)
+        ));
+        assert!(Arc::ptr_eq(&first, &cached_twiddles_in(&cache, &omega, 4)));
+    }
+
+    #[test]
+    fn four_step_fft_consistency() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+
+        for log_d in 0..=14 {
+            let d = 1 << log_d;
+            let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+            let mut expected = (0..d).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let mut v = expected.clone();
+
+            serial_fft(&mut expected, &omega, log_d);
+            four_step_fft(&mut v, &worker, &omega, log_d);
+            assert_eq!(v, expected, "four-step FFT of size 2^{}", log_d);
+        }
+    }
+
+    #[test]
+    fn ifft_and_coset_fft() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+        let g = Fr::multiplicative_generator();
+
+        for log_d in 0..10 {
+            let d = 1 << log_d;
+            let coeffs = (0..d).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+
+            let mut v = coeffs.clone();
+            fft(&mut v, &worker, &omega, log_d);
+            ifft(&mut v, &worker, &omega, log_d);
+            assert_eq!(v, coeffs);
+
+            // The coset FFT evaluates the polynomial at `g * omega^i`.
+            let mut v = coeffs.clone();
+            coset_fft(&mut v, &worker, &omega, log_d, &g);
+            let mut x = g;
+            for eval in &v {
+                let expected = coeffs
+                    .iter()
+                    .rev()
+                    .fold(Fr::zero(), |acc, coeff| acc * x + *coeff);
+                assert_eq!(*eval, expected);
+                x *= omega;
+            }
+            icoset_fft(&mut v, &worker, &omega, log_d, &g);
+            assert_eq!(v, coeffs);
+        }
+    }
 }
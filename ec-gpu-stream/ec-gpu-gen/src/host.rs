@@ -0,0 +1,738 @@
+//! Runs the generated kernels on CPU threads.
+//!
+//! The kernel source is compiled as plain C (see the `EC_GPU_HOST_C` branch of `common.cl`) into a
+//! shared library, which is loaded at run time. This makes it possible to test the kernels
+//! without a GPU. It's meant for testing, the kernels are not optimized for CPUs.
+//!
+//! The calling conventions follow CUDA: every kernel gets a trampoline that takes an array of
+//! pointers to the argument values. A work-group is run by as many threads as it has work-items,
+//! so that barriers and local memory work as on a GPU.
+use std::cmp;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{c_void, CStr, CString};
+use std::fmt::Write;
+use std::fs;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::ptr;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::error::{EcError, EcResult};
+
+/// `KERNEL` expands to this attribute in the host C source, it marks the kernels in the
+/// preprocessed source.
+const KERNEL_MARKER: &str = "__attribute__((used))";
+/// The prefix of the trampolines that call the kernels with an array of arguments.
+const KERNEL_PREFIX: &str = "ec_gpu_host_kernel_";
+/// The prefix of the constants that store the number of arguments of a kernel.
+const NUM_ARGS_PREFIX: &str = "ec_gpu_host_num_args_";
+
+/// The function that sets the work-item of the current thread, see `common.cl`.
+type EnterFn = unsafe extern "C" fn(*const WorkItem);
+/// The trampoline of a kernel.
+type KernelFn = unsafe extern "C" fn(*const *const c_void);
+
+/// The position of a work-item within the grid, it must match `ec_gpu_host_work_item` of
+/// `common.cl`.
+#[repr(C)]
+struct WorkItem {
+    global_id: u32,
+    group_id: u32,
+    local_id: u32,
+    local_size: u32,
+    barrier: extern "C" fn(*mut c_void),
+    barrier_data: *mut c_void,
+}
+
+/// Waits on the [`GroupBarrier`] `data` points to, it's called by `BARRIER_LOCAL()`.
+extern "C" fn barrier_wait(data: *mut c_void) {
+    let barrier = unsafe { &*(data as *const GroupBarrier) };
+    barrier.wait();
+}
+
+/// Locks the mutex, also if another thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The state of a [`GroupBarrier`].
+#[derive(Default)]
+struct BarrierState {
+    /// The number of work-items that are waiting.
+    count: usize,
+    /// It's incremented every time all work-items arrived.
+    generation: usize,
+    poisoned: bool,
+}
+
+/// A barrier for the work-items of a work-group, which is poisoned if a work-item fails.
+///
+/// Once it's poisoned, all waiting work-items are released and all further waits return right
+/// away, so that no work-item is blocked forever by a peer that never arrives.
+struct GroupBarrier {
+    state: Mutex<BarrierState>,
+    condvar: Condvar,
+    size: usize,
+}
+
+impl GroupBarrier {
+    fn new(size: usize) -> Self {
+        Self {
+            state: Mutex::new(BarrierState::default()),
+            condvar: Condvar::new(),
+            size,
+        }
+    }
+
+    /// Blocks until all work-items of the group arrived. Returns `false` if the barrier is
+    /// poisoned.
+    fn wait(&self) -> bool {
+        let mut state = lock(&self.state);
+        if state.poisoned {
+            return false;
+        }
+        state.count += 1;
+        if state.count == self.size {
+            state.count = 0;
+            state.generation = state.generation.wrapping_add(1);
+            self.condvar.notify_all();
+            return true;
+        }
+        let generation = state.generation;
+        while state.generation == generation && !state.poisoned {
+            state = self
+                .condvar
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        !state.poisoned
+    }
+
+    /// Releases all waiting work-items and lets all further waits fail.
+    fn poison(&self) {
+        lock(&self.state).poisoned = true;
+        self.condvar.notify_all();
+    }
+}
+
+/// Returns the message of a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Returns the names of all kernels together with the types of their parameters.
+///
+/// The source must already be preprocessed, so that only the kernels are found that are actually
+/// compiled.
+fn kernel_signatures(preprocessed: &str) -> Vec<(String, Vec<String>)> {
+    let mut signatures = Vec::new();
+    let mut rest = preprocessed;
+    while let Some(start) = rest.find(KERNEL_MARKER) {
+        rest = rest[start + KERNEL_MARKER.len()..].trim_start();
+        rest = match rest.strip_prefix("void") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let (open, close) = match (rest.find('('), rest.find(')')) {
+            (Some(open), Some(close)) if open < close => (open, close),
+            _ => break,
+        };
+        let name = rest[..open].trim().to_string();
+        let types = rest[open + 1..close]
+            .split(',')
+            .map(str::trim)
+            .filter(|param| !param.is_empty() && *param != "void")
+            .map(|param| {
+                let name_start = param
+                    .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .map(|pos| pos + 1)
+                    .unwrap_or(0);
+                param[..name_start].trim().to_string()
+            })
+            .collect();
+        signatures.push((name, types));
+        rest = &rest[close..];
+    }
+    signatures
+}
+
+/// Returns the trampolines for the kernels of the given preprocessed source.
+///
+/// Every trampoline takes the arguments as an array of pointers, there is also a constant with the
+/// number of arguments.
+fn trampolines(preprocessed: &str) -> String {
+    let mut source = String::new();
+    for (name, types) in kernel_signatures(preprocessed) {
+        let args = types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| format!("*({}*)args[{}]", ty, index))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            source,
+            "const uint {}{} = {};",
+            NUM_ARGS_PREFIX,
+            name,
+            types.len()
+        )
+        .expect("writing to a string cannot fail");
+        writeln!(
+            source,
+            "void {}{}(void* const* args) {{ {}({}); }}",
+            KERNEL_PREFIX, name, name, args
+        )
+        .expect("writing to a string cannot fail");
+    }
+    source
+}
+
+/// Compiles the kernel source into a shared library within `out_dir` and returns its path.
+///
+/// The C compiler is taken from the `CC` environment variable and defaults to `cc`. The default
+/// options can be overridden with the `EC_GPU_HOST_C_CC_ARGS` environment variable, the options
+/// that are needed for building a shared library are always set. The library is only rebuilt if
+/// the source or the compiler options change.
+pub fn compile(kernel_source: &str, out_dir: &Path) -> EcResult<PathBuf> {
+    use sha2::{Digest, Sha256};
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let mut cc = match env::var("EC_GPU_HOST_C_CC_ARGS") {
+        Ok(args) => execute::command(format!("{} {}", compiler, args)),
+        Err(_) => {
+            let mut command = Command::new(&compiler);
+            command.arg("-O2");
+            command
+        }
+    };
+    cc.arg("-std=c11")
+        .arg("-fPIC")
+        .arg("-shared")
+        // The kernels assume that `char` is signed, like it is on GPUs.
+        .arg("-fsigned-char");
+
+    // Hash the source and the compile flags. Use that as the filename, so that the library is only
+    // rebuilt if any of them change.
+    let mut hasher = Sha256::new();
+    hasher.update(kernel_source.as_bytes());
+    hasher.update(&format!("{:?}", &cc));
+    let kernel_digest = hex::encode(hasher.finalize());
+
+    let source_path = out_dir.join(format!("{}.c", &kernel_digest));
+    let library_path = out_dir.join(format!("{}.so", &kernel_digest));
+
+    // Only compile if the output doesn't exist yet.
+    if !library_path.exists() {
+        fs::create_dir_all(out_dir)?;
+        let mut source = format!("#define EC_GPU_HOST_C\n{}\n", kernel_source);
+        fs::write(&source_path, &source)?;
+
+        // The kernels are found in the preprocessed source, so that kernels which are excluded
+        // by the preprocessor don't get a trampoline.
+        let preprocessed = Command::new(&compiler)
+            .arg("-E")
+            .arg("-P")
+            .arg(&source_path)
+            .output()?;
+        if !preprocessed.status.success() {
+            return Err(EcError::HostC(format!(
+                "preprocessing {} failed: {}",
+                source_path.display(),
+                String::from_utf8_lossy(&preprocessed.stderr)
+            )));
+        }
+        source.push_str(&trampolines(&String::from_utf8_lossy(&preprocessed.stdout)));
+        fs::write(&source_path, &source)?;
+
+        // Compile into a temporary file, so that a concurrent build never loads a partially
+        // written library.
+        let tmp_path = out_dir.join(format!("{}.{}.tmp", &kernel_digest, process::id()));
+        let output = cc
+            .arg("-o")
+            .arg(&tmp_path)
+            .arg(&source_path)
+            // The multiexp kernel uses `ceil()`.
+            .arg("-lm")
+            .output()?;
+        if !output.status.success() {
+            return Err(EcError::HostC(format!(
+                "compiling {} failed: {}",
+                source_path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        fs::rename(&tmp_path, &library_path)?;
+    }
+
+    Ok(library_path)
+}
+
+/// Returns the error message of the last failed `dlopen()`/`dlsym()` call.
+fn dl_error() -> String {
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[macro_export]
+/// Helper macro to load the program that runs the kernels on the CPU.
+///
+/// It loads the library that was built via [`crate::generate`] in your `build.rs` with the
+/// `host-c` feature enabled. It returns an [`EcResult`] with a [`Program`].
+macro_rules! host_program {
+    () => {
+        $crate::host::Program::from_library(env!("_EC_GPU_HOST_C_KERNEL_LIBRARY"))
+    };
+}
+
+/// A shared library that is loaded at run time.
+struct Library(*mut c_void);
+
+impl Library {
+    fn open(path: &Path) -> EcResult<Self> {
+        let path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| EcError::Simple("Library path contains a NULL byte."))?;
+        let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(EcError::HostC(dl_error()));
+        }
+        Ok(Self(handle))
+    }
+
+    /// Returns the address of the symbol with the given name.
+    fn symbol(&self, name: &str) -> EcResult<*mut c_void> {
+        let name_cstring =
+            CString::new(name).map_err(|_| EcError::Simple("Symbol contains a NULL byte."))?;
+        let symbol = unsafe { libc::dlsym(self.0, name_cstring.as_ptr() as *const c_char) };
+        if symbol.is_null() {
+            return Err(EcError::HostC(format!(
+                "cannot find {}: {}",
+                name,
+                dl_error()
+            )));
+        }
+        Ok(symbol)
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.0) };
+    }
+}
+
+/// A program that runs kernels on CPU threads.
+pub struct Program {
+    library: Library,
+    enter: EnterFn,
+    /// The thread pools the kernels run on, by their number of threads. They are only created
+    /// once and reused by all kernels of the program.
+    pools: Mutex<HashMap<usize, ThreadPool>>,
+}
+
+// The handle of a loaded library can be used from any thread.
+unsafe impl Send for Program {}
+unsafe impl Sync for Program {}
+
+impl Program {
+    /// Loads a program from a library that was built with [`compile`].
+    pub fn from_library<P: AsRef<Path>>(path: P) -> EcResult<Self> {
+        let library = Library::open(path.as_ref())?;
+        let enter = library.symbol("ec_gpu_host_enter")?;
+        Ok(Self {
+            library,
+            enter: unsafe { mem::transmute::<*mut c_void, EnterFn>(enter) },
+            pools: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Compiles the given kernel source and loads it.
+    ///
+    /// The library is stored in the temporary directory of the system.
+    pub fn from_source(kernel_source: &str) -> EcResult<Self> {
+        let out_dir = env::temp_dir().join("ec-gpu-host-c");
+        let library_path = compile(kernel_source, &out_dir)?;
+        Self::from_library(library_path)
+    }
+
+    /// Creates a kernel that runs `num_groups` work-groups of `local_size` work-items each.
+    pub fn create_kernel(
+        &self,
+        name: &str,
+        num_groups: usize,
+        local_size: usize,
+    ) -> EcResult<Kernel<'_>> {
+        let function = self.library.symbol(&format!("{}{}", KERNEL_PREFIX, name))?;
+        let num_args = self
+            .library
+            .symbol(&format!("{}{}", NUM_ARGS_PREFIX, name))?;
+        Ok(Kernel {
+            pools: &self.pools,
+            enter: self.enter,
+            function: unsafe { mem::transmute::<*mut c_void, KernelFn>(function) },
+            num_args: unsafe { *(num_args as *const u32) } as usize,
+            num_groups,
+            local_size,
+            args: Vec::new(),
+            _args: PhantomData,
+        })
+    }
+}
+
+/// A value that can be passed into a kernel.
+pub trait KernelArgument {
+    /// Returns a pointer to the value that is passed into the kernel.
+    fn as_c_void(&self) -> *const c_void;
+
+    /// Returns the number of bytes every work-group allocates, if the argument is a
+    /// [`LocalBuffer`].
+    fn local_bytes(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Values like integers and field elements are passed into the kernel by value.
+impl<T: Copy> KernelArgument for T {
+    fn as_c_void(&self) -> *const c_void {
+        self as *const T as *const c_void
+    }
+}
+
+/// A buffer in host memory, which is accessible by all work-items, like global memory on a GPU.
+pub struct Buffer<T> {
+    ptr: *mut T,
+    len: usize,
+}
+
+// The kernels only access the buffer while it's borrowed by a kernel.
+unsafe impl<T: Send> Send for Buffer<T> {}
+unsafe impl<T: Sync> Sync for Buffer<T> {}
+
+impl<T: Copy> Buffer<T> {
+    /// Creates a buffer with a copy of the given slice.
+    pub fn from_slice(slice: &[T]) -> Self {
+        let len = slice.len();
+        let ptr = Box::into_raw(slice.to_vec().into_boxed_slice()) as *mut T;
+        Self { ptr, len }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copies the contents of the buffer into `out`, which must have the same size.
+    pub fn read_into(&self, out: &mut [T]) {
+        assert_eq!(out.len(), self.len, "The sizes of the buffers must match.");
+        out.copy_from_slice(unsafe { std::slice::from_raw_parts(self.ptr, self.len) });
+    }
+}
+
+impl<T> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(self.ptr, self.len)) });
+    }
+}
+
+impl<T> KernelArgument for Buffer<T> {
+    fn as_c_void(&self) -> *const c_void {
+        // The kernel gets the pointer to the elements.
+        &self.ptr as *const *mut T as *const c_void
+    }
+}
+
+/// Memory that is shared by the work-items of a work-group, like local memory on a GPU.
+///
+/// Every work-group gets its own uninitialized memory for `len` elements.
+pub struct LocalBuffer<T> {
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> LocalBuffer<T> {
+    /// Creates local memory for `len` elements per work-group.
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> KernelArgument for LocalBuffer<T> {
+    fn as_c_void(&self) -> *const c_void {
+        ptr::null()
+    }
+
+    fn local_bytes(&self) -> Option<usize> {
+        Some(self.len * mem::size_of::<T>())
+    }
+}
+
+/// The arguments of the work-items of a single thread group.
+struct GroupArgs {
+    args: Vec<*const c_void>,
+    barrier: GroupBarrier,
+}
+
+// The pointers are only used for calling the kernel while the arguments are borrowed.
+unsafe impl Sync for GroupArgs {}
+
+/// A kernel with its launch configuration and arguments.
+pub struct Kernel<'a> {
+    pools: &'a Mutex<HashMap<usize, ThreadPool>>,
+    enter: EnterFn,
+    function: KernelFn,
+    num_args: usize,
+    num_groups: usize,
+    local_size: usize,
+    /// The pointers to the argument values and the sizes of the local buffers.
+    args: Vec<(*const c_void, Option<usize>)>,
+    _args: PhantomData<&'a ()>,
+}
+
+impl<'a> Kernel<'a> {
+    /// Adds the next argument of the kernel.
+    pub fn arg<T: KernelArgument>(mut self, arg: &'a T) -> Self {
+        self.args.push((arg.as_c_void(), arg.local_bytes()));
+        self
+    }
+
+    /// Runs the kernel and blocks until all work-items are finished.
+    ///
+    /// Every work-group is run by `local_size` threads. To keep the number of threads low, there
+    /// are only as many work-groups run in parallel as needed to keep all CPUs busy. The kernels
+    /// of a program run one after another, like on a GPU queue, on thread pools that are reused.
+    ///
+    /// If a work-item panics, the barriers of all work-groups are poisoned, so that the other
+    /// work-items stop instead of waiting for it, and an error is returned.
+    pub fn run(self) -> EcResult<()> {
+        if self.args.len() != self.num_args {
+            return Err(EcError::Simple(
+                "The number of arguments doesn't match the kernel.",
+            ));
+        }
+        if self
+            .num_groups
+            .checked_mul(self.local_size)
+            .map_or(true, |size| size > u32::MAX as usize)
+        {
+            return Err(EcError::Simple("The grid has too many work-items."));
+        }
+        if self.num_groups == 0 || self.local_size == 0 {
+            return Ok(());
+        }
+
+        let num_parallel_groups = cmp::min(
+            self.num_groups,
+            cmp::max(num_cpus::get() / self.local_size, 1),
+        );
+
+        // Every group that runs in parallel gets its own local memory. `u128` makes sure that it
+        // is properly aligned for any type.
+        let mut local_memory = (0..num_parallel_groups)
+            .map(|_| {
+                self.args
+                    .iter()
+                    .filter_map(|(_, local_bytes)| *local_bytes)
+                    .map(|bytes| vec![0u128; (bytes + 15) / 16])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let local_pointers = local_memory
+            .iter_mut()
+            .map(|buffers| {
+                buffers
+                    .iter_mut()
+                    .map(|buffer| buffer.as_mut_ptr() as *mut c_void)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let group_args = local_pointers
+            .iter()
+            .map(|pointers| {
+                let mut pointers = pointers.iter();
+                let args = self
+                    .args
+                    .iter()
+                    .map(|(arg, local_bytes)| match local_bytes {
+                        Some(_) => {
+                            let pointer = pointers.next().expect("a pointer per local buffer");
+                            pointer as *const *mut c_void as *const c_void
+                        }
+                        None => *arg,
+                    })
+                    .collect();
+                GroupArgs {
+                    args,
+                    barrier: GroupBarrier::new(self.local_size),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // The work-items block each other at the barriers, hence every work-item needs its own
+        // thread. The lock is held while the kernel runs, so that no other kernel takes them.
+        let num_threads = num_parallel_groups * self.local_size;
+        let mut pools = lock(self.pools);
+        if !pools.contains_key(&num_threads) {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .thread_name(|index| format!("ec-gpu-host-c-{}", index))
+                .build()?;
+            pools.insert(num_threads, pool);
+        }
+        let pool = &pools[&num_threads];
+
+        let (enter, function) = (self.enter, self.function);
+        let (num_groups, local_size) = (self.num_groups, self.local_size);
+        let all_group_args = &group_args;
+        // The message of the first work-item that panicked.
+        let panicked = Mutex::new(None);
+        pool.scope(|scope| {
+            let panicked = &panicked;
+            for (first_group, group_args) in group_args.iter().enumerate() {
+                for local_id in 0..local_size {
+                    scope.spawn(move |_| {
+                        for group_id in (first_group..num_groups).step_by(num_parallel_groups) {
+                            let item = WorkItem {
+                                global_id: (group_id * local_size + local_id) as u32,
+                                group_id: group_id as u32,
+                                local_id: local_id as u32,
+                                local_size: local_size as u32,
+                                barrier: barrier_wait,
+                                barrier_data: &group_args.barrier as *const GroupBarrier
+                                    as *mut c_void,
+                            };
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                                enter(&item);
+                                function(group_args.args.as_ptr());
+                            }));
+                            if let Err(payload) = result {
+                                lock(panicked).get_or_insert_with(|| {
+                                    format!(
+                                        "work-item {} of work-group {} panicked: {}",
+                                        local_id,
+                                        group_id,
+                                        panic_message(&*payload)
+                                    )
+                                });
+                                for group_args in all_group_args {
+                                    group_args.barrier.poison();
+                                }
+                                return;
+                            }
+                            // The next group must not start before all work-items are done with
+                            // the local memory.
+                            if !group_args.barrier.wait() {
+                                return;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+        drop(pools);
+
+        match panicked
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            Some(message) => Err(EcError::HostC(message)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_kernel_signatures() {
+        let preprocessed = "
+            static uint helper(uint a) { return a; }
+            __attribute__((used)) void first(Fr* x,
+                              Fr *u,
+                              const uint n) {}
+            __attribute__((used)) void second() {}
+        ";
+        assert_eq!(
+            kernel_signatures(preprocessed),
+            vec![
+                (
+                    "first".to_string(),
+                    vec![
+                        "Fr*".to_string(),
+                        "Fr *".to_string(),
+                        "const uint".to_string()
+                    ]
+                ),
+                ("second".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_barrier() {
+        const SIZE: usize = 4;
+        let barrier = Arc::new(GroupBarrier::new(SIZE));
+        let passed = Arc::new(AtomicUsize::new(0));
+
+        // All work-items pass the barrier several times.
+        let threads = (0..SIZE)
+            .map(|_| {
+                let (barrier, passed) = (barrier.clone(), passed.clone());
+                thread::spawn(move || {
+                    for _ in 0..3 {
+                        assert!(barrier.wait());
+                        passed.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(passed.load(Ordering::SeqCst), 3 * SIZE);
+
+        // A missing work-item doesn't block the others once the barrier is poisoned.
+        let threads = (0..SIZE - 1)
+            .map(|_| {
+                let barrier = barrier.clone();
+                thread::spawn(move || barrier.wait())
+            })
+            .collect::<Vec<_>>();
+        barrier.poison();
+        for thread in threads {
+            assert!(!thread.join().unwrap());
+        }
+        assert!(!barrier.wait());
+    }
+}
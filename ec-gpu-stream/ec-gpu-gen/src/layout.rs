@@ -0,0 +1,31 @@
+use ff::Field;
+use group::prime::PrimeCurveAffine;
+
+/// Affine points that have the memory layout of the points of the GPU kernels.
+///
+/// The kernels that return affine points write them into the memory of those types directly, the
+/// kernels that take projective points read the memory of [`PrimeCurveAffine::Curve`].
+///
+/// # Safety
+///
+/// The type must consist of the `x` and `y` coordinates as [`GpuAffine::Base`] elements in
+/// Montgomery form, like [`ec_gpu::GpuField`] describes them, without any padding. The point at infinity must
+/// be all zeros.
+///
+/// The projective points `Self::Curve` must consist of the `X`, `Y` and `Z` coordinates in
+/// Jacobian coordinates in the same representation, also without any padding. The point at
+/// infinity has `Z = 0`.
+pub unsafe trait GpuAffine: PrimeCurveAffine {
+    /// The field the coordinates are in.
+    type Base: Field;
+}
+
+// It is safe as the affine and projective points of `blstrs` are the coordinates of the
+// underlying `blst` points. The affine ones use zero coordinates for the point at infinity, the
+// projective ones are in Jacobian coordinates.
+unsafe impl GpuAffine for blstrs::G1Affine {
+    type Base = blstrs::Fp;
+}
+unsafe impl GpuAffine for blstrs::G2Affine {
+    type Base = blstrs::Fp2;
+}
@@ -44,6 +44,10 @@
 //!
 //! CUDA and OpenCL are supprted, each be enabled with the `cuda` and `opencl` [feature flags].
 //!
+//! With the `host-c` feature, the kernels are additionally compiled as plain C with the system C
+//! compiler. The `host` module runs them on CPU threads, which makes it possible to test them
+//! without a GPU.
+//!
 //! [fatbin]: https://en.wikipedia.org/wiki/Fat_binary#Heterogeneous_computing
 //! [feature flags]: https://doc.rust-lang.org/cargo/reference/manifest.html#the-features-section
 mod error;
@@ -51,22 +55,54 @@ mod error;
 mod program;
 mod source;
 
+/// Point decompression on the GPU.
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub mod decompression;
+/// Group operations shared by the curve models on the CPU.
+pub mod curve_cpu;
+/// Evaluation domains for polynomials.
+pub mod domain;
+/// Twisted Edwards curve arithmetic on the CPU.
+pub mod edwards_cpu;
 /// Fast Fourier Transform on the GPU.
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 pub mod fft;
 /// Fast Fourier Transform on the CPU.
 pub mod fft_cpu;
+/// Running the generated kernels on the CPU.
+#[cfg(feature = "host-c")]
+pub mod host;
+/// Memory layouts of points that are shared with the GPU kernels.
+pub mod layout;
+/// Mixed-radix FFTs over domains of size `2^k * 3^m`.
+pub mod mixed_radix;
 /// Multiexponentiation on the GPU.
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 pub mod multiexp;
 /// Multiexponentiation on the CPU.
 pub mod multiexp_cpu;
+/// Conversion of points into affine coordinates on the GPU.
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub mod normalize;
+/// Conversion of points into affine coordinates on the CPU.
+pub mod normalize_cpu;
+/// Polynomial arithmetic on top of the FFTs.
+pub mod poly;
+/// Scalars that can be used as exponents of the multiexp.
+pub mod scalar;
 /// Helpers for multithreaded code.
 pub mod threadpool;
+/// Element-wise operations on vectors of field elements on the CPU.
+pub mod vector_cpu;
+/// XYZZ coordinates for short Weierstrass curves on the CPU.
+pub mod xyzz_cpu;
 
 /// Re-export rust-gpu-tools as things like [`rust_gpu_tools::Device`] might be needed.
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 pub use rust_gpu_tools;
 
 pub use error::{EcError, EcResult};
-pub use source::{generate, SourceBuilder};
+pub use source::{
+    generate, BucketCoordinates, Limb, Limb32, Limb64, SourceBuilder, UnsaturatedLimb,
+    UnsaturatedLimb64,
+};
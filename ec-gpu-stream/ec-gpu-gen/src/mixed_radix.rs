@@ -0,0 +1,199 @@
+use std::iter;
+
+use ff::PrimeField;
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+
+use crate::error::{EcError, EcResult};
+use crate::threadpool::Worker;
+
+/// A prime field whose multiplicative group also has subgroups of order `3^m`.
+///
+/// Together with the subgroups of order `2^k` of [`PrimeField`], there are roots of unity for all
+/// FFT sizes `2^k * 3^m` with `k <= S` and `m <= THREE_ADICITY`.
+pub trait SmallPrimeRoots: PrimeField {
+    /// The largest `m` such that `3^m` divides `p - 1`.
+    const THREE_ADICITY: u32;
+
+    /// Returns a primitive `3^THREE_ADICITY`-th root of unity.
+    fn root_of_unity_3() -> Self;
+}
+
+/// Returns a primitive cube root of unity, which is `(-1 + sqrt(-3)) / 2`.
+///
+/// It only exists if `3` divides `p - 1`.
+fn cube_root_of_unity<F: PrimeField>() -> Option<F> {
+    let minus_three = -F::from(3);
+    let sqrt = Option::<F>::from(minus_three.sqrt())?;
+    let two_inv = Option::<F>::from(F::from(2).invert())?;
+    Some((sqrt - F::one()) * two_inv)
+}
+
+impl SmallPrimeRoots for blstrs::Scalar {
+    const THREE_ADICITY: u32 = 1;
+
+    fn root_of_unity_3() -> Self {
+        cube_root_of_unity().expect("3 divides r - 1")
+    }
+}
+
+/// Returns the size of an FFT with `2^log2_n * 3^log3_n` elements.
+pub fn size(log2_n: u32, log3_n: u32) -> usize {
+    (1 << log2_n) * 3usize.pow(log3_n)
+}
+
+/// Returns a primitive root of unity of order `2^log2_n * 3^log3_n`.
+///
+/// Fails if the field doesn't have a subgroup of that size.
+pub fn root_of_unity<F: SmallPrimeRoots>(log2_n: u32, log3_n: u32) -> EcResult<F> {
+    if log2_n > F::S || log3_n > F::THREE_ADICITY {
+        return Err(EcError::Simple("The field has no subgroup of that size."));
+    }
+
+    let mut omega_2 = F::root_of_unity();
+    for _ in log2_n..F::S {
+        omega_2 = omega_2.square();
+    }
+    let mut omega_3 = F::root_of_unity_3();
+    for _ in log3_n..F::THREE_ADICITY {
+        omega_3 = omega_3.square() * omega_3;
+    }
+    // The orders are coprime, hence the product has the product of the orders.
+    Ok(omega_2 * omega_3)
+}
+
+/// Calculate the Fast Fourier Transform of size `n = 2^log2_n * 3^log3_n` on the CPU
+/// (multithreaded).
+///
+/// `omega` must be a primitive `n`-th root of unity, see [`root_of_unity`]. It's a Stockham FFT,
+/// which needs `n` elements of additional memory, but no permutation of the elements. The
+/// radix-2 rounds come first, then the radix-3 rounds, like on the GPU.
+pub fn fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F, log2_n: u32, log3_n: u32) {
+    let n = size(log2_n, log3_n);
+    assert_eq!(
+        a.len(),
+        n,
+        "The input must have 2^log2_n * 3^log3_n elements."
+    );
+    if n == 1 {
+        return;
+    }
+
+    worker.install(|| {
+        // All powers of `omega`, every twiddle factor is one of them.
+        let mut powers = Vec::with_capacity(n);
+        powers.extend(iter::successors(Some(F::one()), |power| Some(*power * omega)).take(n));
+
+        let mut src = a.to_vec();
+        let mut dst = vec![F::zero(); n];
+        let radices = iter::repeat(2)
+            .take(log2_n as usize)
+            .chain(iter::repeat(3).take(log3_n as usize));
+
+        // The product of the radices of the previous rounds.
+        let mut p = 1;
+        for radix in radices {
+            stockham_round(&src, &mut dst, &powers, radix, p);
+            std::mem::swap(&mut src, &mut dst);
+            p *= radix;
+        }
+        a.copy_from_slice(&src);
+    });
+}
+
+/// Performs a single radix-`radix` round of a Stockham FFT, in parallel on the current thread
+/// pool.
+///
+/// `p` is the product of the radices of the previous rounds, `powers` are all powers of `omega`.
+/// The outputs of `radix * p` consecutive elements only depend on `p` elements of each of the
+/// `radix` parts of the input.
+fn stockham_round<F: PrimeField>(src: &[F], dst: &mut [F], powers: &[F], radix: usize, p: usize) {
+    let n = src.len();
+    let part = n / radix;
+    // `omega^(n / radix)` is a primitive `radix`-th root of unity.
+    let roots = (0..radix).map(|q| powers[q * part]).collect::<Vec<_>>();
+
+    dst.par_chunks_mut(radix * p)
+        .enumerate()
+        .for_each(|(s, dst)| {
+            let mut u = vec![F::zero(); radix];
+            for k in 0..p {
+                let i = s * p + k;
+                // The twiddle factor `omega^(n / (radix * p) * k)`, to the power of `t`.
+                let step = part / p * k;
+                for (t, u) in u.iter_mut().enumerate() {
+                    *u = src[i + t * part] * powers[step * t % n];
+                }
+                for q in 0..radix {
+                    dst[k + q * p] = u
+                        .iter()
+                        .enumerate()
+                        .fold(F::zero(), |sum, (t, u)| sum + *u * roots[t * q % radix]);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    use crate::fft_cpu::serial_fft;
+
+    /// Evaluates the polynomial at all powers of `omega` directly.
+    fn naive_dft(a: &[Fr], omega: &Fr) -> Vec<Fr> {
+        let mut x = Fr::one();
+        (0..a.len())
+            .map(|_| {
+                let eval = a
+                    .iter()
+                    .rev()
+                    .fold(Fr::zero(), |acc, coeff| acc * x + coeff);
+                x *= omega;
+                eval
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_root_of_unity() {
+        let omega_3 = Fr::root_of_unity_3();
+        assert_ne!(omega_3, Fr::one());
+        assert_eq!(omega_3.square() * omega_3, Fr::one());
+
+        let omega = root_of_unity::<Fr>(4, 1).unwrap();
+        let mut power = Fr::one();
+        for i in 1..=48 {
+            power *= omega;
+            assert_eq!(power == Fr::one(), i == 48);
+        }
+        assert!(root_of_unity::<Fr>(4, 2).is_err());
+        assert!(root_of_unity::<Fr>(Fr::S + 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_mixed_radix_fft() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+
+        for log3_n in 0..=Fr::THREE_ADICITY {
+            for log2_n in 0..8 {
+                let n = size(log2_n, log3_n);
+                let omega = root_of_unity::<Fr>(log2_n, log3_n).unwrap();
+                let coeffs = (0..n).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+
+                let mut v = coeffs.clone();
+                fft(&mut v, &worker, &omega, log2_n, log3_n);
+                assert_eq!(v, naive_dft(&coeffs, &omega), "size {}", n);
+
+                if log3_n == 0 {
+                    let mut expected = coeffs;
+                    serial_fft(&mut expected, &omega, log2_n);
+                    assert_eq!(v, expected);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::ops::AddAssign;
 use std::sync::{Arc, RwLock};
 use std::ptr::NonNull;
@@ -18,10 +19,18 @@ use ff::PrimeField;
 use group::{prime::PrimeCurveAffine, Group};
 use log::{error, info};
 use rust_gpu_tools::{program_closures, Device, Program};
-use yastl::Scope;
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+    Scope,
+};
 use crate::{
     error::{EcError, EcResult},
+    layout::GpuAffine,
+    multiexp_cpu::{combine_windows, MultiexpTrace, TracedMultiexp},
+    scalar::WindowScalar,
     threadpool::Worker,
+    xyzz_cpu::PointXyzz,
 };
 // use std::error::Error;
 /// On the GPU, the exponents are split into windows, this is the maximum number of such windows.
@@ -53,6 +62,25 @@ const fn work_units(compute_units: u32, compute_capabilities: Option<(u32, u32)>
     }
 }
 
+/// Converts the raw results of a kernel that uses [`crate::BucketCoordinates::Xyzz`] into points.
+type XyzzConverter<C> = fn(&[u8]) -> Vec<C>;
+
+/// Reads points in XYZZ coordinates over the base field of `G` from raw bytes and converts them
+/// into projective points.
+fn xyzz_to_curve<G: GpuAffine>(bytes: &[u8]) -> Vec<G::Curve> {
+    bytes
+        .chunks(std::mem::size_of::<PointXyzz<G::Base>>())
+        .map(|chunk| {
+            // It is safe as the chunk has the size of a point and `G::Curve` has the layout of
+            // Jacobian coordinates over `G::Base`, which is guaranteed by `GpuAffine`.
+            unsafe {
+                let point = (chunk.as_ptr() as *const PointXyzz<G::Base>).read_unaligned();
+                point.to_curve()
+            }
+        })
+        .collect()
+}
+
 /// Multiexp kernel for a single GPU.
 pub struct SingleMultiexpKernel<'a, G>
 where
@@ -68,19 +96,24 @@ where
     /// multiexp calculations. If it returns true, the calculation will be aborted with an
     /// [`EcError::Aborted`].
     maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    /// The memory of the device in bytes.
+    memory: u64,
+    /// The size of a bucket in bytes.
+    bucket_size: usize,
+    /// Converts the results if the buckets are in XYZZ instead of Jacobian coordinates.
+    xyzz_converter: Option<XyzzConverter<G::Curve>>,
 
     _phantom: std::marker::PhantomData<G::Scalar>,
 }
 
 /// Calculates the maximum number of terms that can be put onto the GPU memory.
-fn calc_chunk_size<G>(mem: u64, work_units: usize) -> usize
+fn calc_chunk_size<G>(mem: u64, work_units: usize, proj_size: usize) -> usize
 where
     G: PrimeCurveAffine,
     G::Scalar: PrimeField,
 {
     let aff_size = std::mem::size_of::<G>();
     let exp_size = exp_size::<G::Scalar>();
-    let proj_size = std::mem::size_of::<G::Curve>();
 
     // Leave `MEMORY_PADDING` percent of the memory free.
     let max_memory = ((mem as f64) * (1f64 - MEMORY_PADDING)) as usize;
@@ -103,6 +136,50 @@ fn exp_size<F: PrimeField>() -> usize {
     std::mem::size_of::<F::Repr>()
 }
 
+/// Converts the exponents into the representation the GPU kernel expects, which is the
+/// little-endian byte representation of an element of the scalar field `F`.
+///
+/// Exponents that already are in that representation are borrowed instead of copied.
+fn to_exponents<F: PrimeField, E: WindowScalar>(exponents: &[E]) -> EcResult<Cow<'_, [u8]>> {
+    let exp_size = exp_size::<F>();
+    if E::num_bits() as usize > 8 * exp_size {
+        return Err(EcError::Simple(
+            "The exponents are bigger than the ones the GPU kernel supports.",
+        ));
+    }
+    if E::num_bits() as usize == 8 * exp_size {
+        if let Some(bytes) = E::as_le_bytes(exponents) {
+            return Ok(Cow::Borrowed(bytes));
+        }
+    }
+    let mut bytes = vec![0u8; exponents.len() * exp_size];
+    bytes
+        .par_chunks_mut(exp_size)
+        .zip(exponents.par_iter())
+        .for_each(|(repr, exp)| {
+            for (i, chunk) in repr.chunks_mut(8).enumerate() {
+                let bits = exp.get_bits(64 * i as u32, 64).to_le_bytes();
+                chunk.copy_from_slice(&bits[..chunk.len()]);
+            }
+        });
+    Ok(Cow::Owned(bytes))
+}
+
+/// Sums up the results of the `num_groups * num_windows` threads of the kernel per window.
+///
+/// The least significant window comes first, like in [`MultiexpTrace::windows`].
+fn window_sums<C: Group>(results: &[C], num_groups: usize, num_windows: usize) -> Vec<C> {
+    (0..num_windows)
+        .map(|i| {
+            let mut sum = C::identity();
+            for g in 0..num_groups {
+                sum.add_assign(&results[g * num_windows + i]);
+            }
+            sum
+        })
+        .collect()
+}
+
 impl<'a, G> SingleMultiexpKernel<'a, G>
 where
     G: PrimeCurveAffine + GpuName,
@@ -120,17 +197,37 @@ where
         let compute_units = device.compute_units();
         let compute_capability = device.compute_capability();
         let work_units = work_units(compute_units, compute_capability);
-        let chunk_size = calc_chunk_size::<G>(mem, work_units);
+        let proj_size = std::mem::size_of::<G::Curve>();
+        let chunk_size = calc_chunk_size::<G>(mem, work_units, proj_size);
 
         Ok(SingleMultiexpKernel {
             program,
             n: chunk_size,
             work_units,
             maybe_abort,
+            memory: mem,
+            bucket_size: proj_size,
+            xyzz_converter: None,
             _phantom: std::marker::PhantomData,
         })
     }
 
+    /// Use a kernel whose buckets are in XYZZ coordinates over the base field of the curve.
+    ///
+    /// The program must have been generated with [`crate::BucketCoordinates::Xyzz`]. The results
+    /// are converted into the projective points of the curve, which have the memory layout of
+    /// [`GpuAffine`].
+    pub fn with_xyzz_buckets(mut self) -> Self
+    where
+        G: GpuAffine,
+    {
+        let bucket_size = std::mem::size_of::<PointXyzz<G::Base>>();
+        self.n = calc_chunk_size::<G>(self.memory, self.work_units, bucket_size);
+        self.bucket_size = bucket_size;
+        self.xyzz_converter = Some(xyzz_to_curve::<G>);
+        self
+    }
+
 
 
 pub fn create_buffer_from_slice<T>(&self, slice: &[T]){
@@ -162,12 +259,13 @@ pub fn create_buffer_from_slice<T>(&self, slice: &[T]){
     /// The number of `bases` and `exponents` are determined by [`SingleMultiexpKernel`]`::n`, this
     /// means that it is guaranteed that this amount of calculations fit on the GPU this kernel is
     /// running on.
-    pub fn multiexp(
+    pub fn multiexp<E: WindowScalar>(
         &self,
         bases: &[G],
-        exponents: &[<G::Scalar as PrimeField>::Repr],
+        exponents: &[E],
     ) -> EcResult<G::Curve> {
         assert_eq!(bases.len(), exponents.len());
+        let exponents = &to_exponents::<G::Scalar, E>(exponents)?[..];
 
         if let Some(maybe_abort) = &self.maybe_abort {
             if maybe_abort() {
@@ -175,55 +273,17 @@ pub fn create_buffer_from_slice<T>(&self, slice: &[T]){
             }
         }
         let window_size = self.calc_window_size(bases.len());
-        // windows_size * num_windows needs to be >= 256 in order for the kernel to work correctly.
-        let num_windows = div_ceil(256, window_size);
-        let num_groups = self.work_units / num_windows;
+        let (num_groups, num_windows) = self.kernel_dimensions(window_size);
         let bucket_len = 1 << window_size;
 
-        // Each group will have `num_windows` threads and as there are `num_groups` groups, there will
-        // be `num_groups` * `num_windows` threads in total.
-        // Each thread will use `num_groups` * `num_windows` * `bucket_len` buckets.
-
-        let closures = program_closures!(|program, _arg| -> EcResult<Vec<G::Curve>> {
-
+        let results = self.run_kernel(bases, exponents, num_groups, num_windows, window_size)?;
 
-let s = Instant::now();
-            let base_buffer = program.create_buffer_from_slice(bases)?;
-            // self.create_buffer_from_slice(bases);
-            let exp_buffer = program.create_buffer_from_slice(exponents)?;
-            
-
-            // It is safe as the GPU will initialize that buffer
-            let bucket_buffer =
-                unsafe { program.create_buffer::<G::Curve>(self.work_units * bucket_len)? };
-            // It is safe as the GPU will initialize that buffer
-            let result_buffer = unsafe { program.create_buffer::<G::Curve>(self.work_units)? };
-
-            // The global work size follows CUDA's definition and is the number of
-            // `LOCAL_WORK_SIZE` sized thread groups.
-            let global_work_size = div_ceil(num_windows * num_groups, LOCAL_WORK_SIZE);
-
-            let kernel_name = format!("{}_multiexp", G::name());
-            let kernel = program.create_kernel(&kernel_name, global_work_size, LOCAL_WORK_SIZE)?;
-
-            kernel
-                .arg(&base_buffer)
-                .arg(&bucket_buffer)
-                .arg(&result_buffer)
-                .arg(&exp_buffer)
-                .arg(&(bases.len() as u32))
-                .arg(&(num_groups as u32))
-                .arg(&(num_windows as u32))
-                .arg(&(window_size as u32))
-                .run()?;
-
-            let mut results = vec![G::Curve::identity(); self.work_units];
-            program.read_into_buffer(&result_buffer, &mut results)?;
-println!("************************************************************************bellperson: {:?}\n",s.elapsed());
-            Ok(results)
-        });
-
-        let results = self.program.run(closures, ())?;
+        // The CUDA kernels below only have Jacobian buckets, hence the results of a kernel with
+        // XYZZ buckets are accumulated right away.
+        if self.xyzz_converter.is_some() {
+            let windows = window_sums(&results, num_groups, num_windows);
+            return Ok(combine_windows(window_size as u32, windows.iter()));
+        }
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 let my_window_size = window_size;
@@ -412,6 +472,117 @@ else{
         Ok(y)
     }
 
+    /// Returns the number of groups and windows the kernel uses for the given window size.
+    ///
+    /// The windows cover all bits of the exponents, the groups split the bases, so that there is
+    /// a thread for every window of every group.
+    fn kernel_dimensions(&self, window_size: usize) -> (usize, usize) {
+        let num_windows = div_ceil(8 * exp_size::<G::Scalar>(), window_size);
+        let num_groups = std::cmp::max(self.work_units / num_windows, 1);
+        (num_groups, num_windows)
+    }
+
+    /// Runs the kernel on the exponents in the representation of [`to_exponents`] and returns
+    /// the results of its `num_groups * num_windows` threads.
+    fn run_kernel(
+        &self,
+        bases: &[G],
+        exponents: &[u8],
+        num_groups: usize,
+        num_windows: usize,
+        window_size: usize,
+    ) -> EcResult<Vec<G::Curve>> {
+        let num_threads = num_groups * num_windows;
+        let bucket_len = 1 << window_size;
+
+        // Each group will have `num_windows` threads and as there are `num_groups` groups, there will
+        // be `num_groups` * `num_windows` threads in total.
+        // Each thread will use `num_groups` * `num_windows` * `bucket_len` buckets.
+
+        let closures = program_closures!(|program, _arg| -> EcResult<Vec<G::Curve>> {
+
+
+let s = Instant::now();
+            let base_buffer = program.create_buffer_from_slice(bases)?;
+            // self.create_buffer_from_slice(bases);
+            let exp_buffer = program.create_buffer_from_slice(exponents)?;
+            
+
+            // The buckets and results are raw bytes, as their representation depends on the
+            // coordinates the kernel uses.
+            let bucket_size = self.bucket_size;
+            // It is safe as the GPU will initialize that buffer
+            let bucket_buffer =
+                unsafe { program.create_buffer::<u8>(num_threads * bucket_len * bucket_size)? };
+            // It is safe as the GPU will initialize that buffer
+            let result_buffer =
+                unsafe { program.create_buffer::<u8>(num_threads * bucket_size)? };
+
+            // The global work size follows CUDA's definition and is the number of
+            // `LOCAL_WORK_SIZE` sized thread groups.
+            let global_work_size = div_ceil(num_threads, LOCAL_WORK_SIZE);
+
+            let kernel_name = format!("{}_multiexp", G::name());
+            let kernel = program.create_kernel(&kernel_name, global_work_size, LOCAL_WORK_SIZE)?;
+
+            kernel
+                .arg(&base_buffer)
+                .arg(&bucket_buffer)
+                .arg(&result_buffer)
+                .arg(&exp_buffer)
+                .arg(&(bases.len() as u32))
+                .arg(&(num_groups as u32))
+                .arg(&(num_windows as u32))
+                .arg(&(window_size as u32))
+                .run()?;
+
+            let mut result_bytes = vec![0u8; num_threads * bucket_size];
+            program.read_into_buffer(&result_buffer, &mut result_bytes)?;
+            let results = match self.xyzz_converter {
+                Some(convert) => convert(&result_bytes),
+                None => result_bytes
+                    .chunks(bucket_size)
+                    // It is safe as the results have the memory layout of `G::Curve`.
+                    .map(|chunk| unsafe { (chunk.as_ptr() as *const G::Curve).read_unaligned() })
+                    .collect(),
+            };
+println!("************************************************************************bellperson: {:?}\n",s.elapsed());
+            Ok(results)
+        });
+
+        self.program.run(closures, ())
+    }
+
+    /// Runs the multiexp with the given window size and returns the sum of every window, the
+    /// least significant window comes first.
+    ///
+    /// Window `i` is not yet multiplied by `2^(i * window_size)`, see [`MultiexpTrace::windows`].
+    /// Unlike [`SingleMultiexpKernel::multiexp`], the window size doesn't depend on the device,
+    /// so that the windows can be compared with the ones of the CPU.
+    pub fn multiexp_windows<E: WindowScalar>(
+        &self,
+        bases: &[G],
+        exponents: &[E],
+        window_size: usize,
+    ) -> EcResult<Vec<G::Curve>> {
+        assert_eq!(bases.len(), exponents.len());
+        if window_size == 0 || window_size > MAX_WINDOW_SIZE {
+            return Err(EcError::Simple(
+                "The window size must be between one bit and `MAX_WINDOW_SIZE` bits.",
+            ));
+        }
+        let exponents = &to_exponents::<G::Scalar, E>(exponents)?[..];
+
+        if let Some(maybe_abort) = &self.maybe_abort {
+            if maybe_abort() {
+                return Err(EcError::Aborted);
+            }
+        }
+        let (num_groups, num_windows) = self.kernel_dimensions(window_size);
+        let results = self.run_kernel(bases, exponents, num_groups, num_windows, window_size)?;
+        Ok(window_sums(&results, num_groups, num_windows))
+    }
+
     /// Calculates the window size, based on the given number of terms.
     ///
     /// For best performance, the window size is reduced, so that maximum parallelism is possible.
@@ -455,6 +626,20 @@ where
         Self::create_optional_abort(programs, devices, Some(maybe_abort))
     }
 
+    /// Use kernels whose buckets are in XYZZ coordinates over the base field of the curve, see
+    /// [`SingleMultiexpKernel::with_xyzz_buckets`].
+    pub fn with_xyzz_buckets(self) -> Self
+    where
+        G: GpuAffine,
+    {
+        let kernels = self
+            .kernels
+            .into_iter()
+            .map(SingleMultiexpKernel::with_xyzz_buckets)
+            .collect();
+        MultiexpKernel { kernels }
+    }
+
     fn create_optional_abort(
         programs: Vec<Program>,
         devices: &[&Device],
@@ -493,13 +678,13 @@ where
 
     /// Calculate multiexp on all available GPUs.
     ///
-    /// It needs to run within a [`yastl::Scope`]. This method usually isn't called directly, use
+    /// It needs to run within a [`rayon::Scope`]. This method usually isn't called directly, use
     /// [`MultiexpKernel::multiexp`] instead.
-    pub fn parallel_multiexp<'s>(
+    pub fn parallel_multiexp<'s, E: WindowScalar>(
         &'s mut self,
         scope: &Scope<'s>,
         bases: &'s [G],
-        exps: &'s [<G::Scalar as PrimeField>::Repr],
+        exps: &'s [E],
         results: &'s mut [G::Curve],
         error: Arc<RwLock<EcResult<()>>>,
     ) {
@@ -518,7 +703,7 @@ where
             .zip(results.iter_mut())
         {
             let error = error.clone();
-            scope.execute(move || {
+            scope.spawn(move |_| {
                 let mut acc = G::Curve::identity();
                 for (bases, exps) in bases.chunks(kern.n).zip(exps.chunks(kern.n)) {
                     if error.read().unwrap().is_err() {
@@ -542,11 +727,11 @@ where
     /// Calculate multiexp.
     ///
     /// This is the main entry point.
-    pub fn multiexp(
+    pub fn multiexp<E: WindowScalar>(
         &mut self,
         pool: &Worker,
         bases_arc: Arc<Vec<G>>,
-        exps: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
+        exps: Arc<Vec<E>>,
         skip: usize,
     ) -> EcResult<G::Curve> {
         // Bases are skipped by `self.1` elements, when converted from (Arc<Vec<G>>, usize) to Source
@@ -574,6 +759,103 @@ where
         Ok(acc)
     }
 
+    /// Calculate multiexp in the reproducible mode.
+    ///
+    /// Instead of splitting the terms evenly between the devices, they are split into chunks of
+    /// `chunk_size` terms, independent of the number of devices and their memory. The devices
+    /// process contiguous runs of whole chunks. All devices use windows of `window_size` bits.
+    /// The partial sum of every chunk and the sum of every window are recorded in the returned
+    /// [`MultiexpTrace`], so that it can be compared with the trace of
+    /// [`crate::multiexp_cpu::multiexp_cpu_reproducible`] with the same window and chunk size.
+    pub fn multiexp_reproducible<E: WindowScalar>(
+        &mut self,
+        pool: &Worker,
+        bases_arc: Arc<Vec<G>>,
+        exps: Arc<Vec<E>>,
+        skip: usize,
+        window_size: u32,
+        chunk_size: usize,
+    ) -> EcResult<TracedMultiexp<G::Curve>> {
+        assert!(chunk_size > 0, "The chunk size must be at least one term.");
+
+        let bases = &bases_arc[skip..(skip + exps.len())];
+        let exps = &exps[..];
+        let num_chunks = div_ceil(exps.len(), chunk_size);
+        let chunks_per_device = std::cmp::max(1, div_ceil(num_chunks, self.kernels.len()));
+        // The windows of the GPU cover all bits of the exponent type of the kernel, the ones
+        // above the bits of `E` are empty. Only the windows the CPU has as well are kept.
+        let num_windows = div_ceil(E::exponent_bits::<G::Scalar>() as usize, window_size as usize);
+        // The sum of every window of every chunk.
+        let mut chunk_windows = vec![Vec::new(); num_chunks];
+        let error = Arc::new(RwLock::new(Ok(())));
+
+        pool.scoped(|s| {
+            for (((bases, exps), kern), results) in bases
+                .chunks(chunks_per_device * chunk_size)
+                .zip(exps.chunks(chunks_per_device * chunk_size))
+                .zip(self.kernels.iter_mut())
+                .zip(chunk_windows.chunks_mut(chunks_per_device))
+            {
+                let error = error.clone();
+                s.spawn(move |_| {
+                    for ((bases, exps), result) in bases
+                        .chunks(chunk_size)
+                        .zip(exps.chunks(chunk_size))
+                        .zip(results.iter_mut())
+                    {
+                        // A chunk might be bigger than what fits on the device at once.
+                        let mut windows = vec![G::Curve::identity(); num_windows];
+                        for (bases, exps) in bases.chunks(kern.n).zip(exps.chunks(kern.n)) {
+                            if error.read().unwrap().is_err() {
+                                return;
+                            }
+                            match kern.multiexp_windows(bases, exps, window_size as usize) {
+                                Ok(sums) => {
+                                    for (window, sum) in windows.iter_mut().zip(&sums) {
+                                        window.add_assign(sum);
+                                    }
+                                }
+                                Err(e) => {
+                                    *error.write().unwrap() = Err(e);
+                                    return;
+                                }
+                            }
+                        }
+                        *result = windows;
+                    }
+                });
+            }
+        });
+
+        Arc::try_unwrap(error)
+            .expect("only one ref left")
+            .into_inner()
+            .unwrap()?;
+
+        // Sum up the chunks in order, so that the result doesn't depend on the devices.
+        let windows = (0..num_windows)
+            .map(|i| {
+                let mut sum = G::Curve::identity();
+                for chunk in &chunk_windows {
+                    sum.add_assign(&chunk[i]);
+                }
+                sum
+            })
+            .collect::<Vec<_>>();
+        let chunks = chunk_windows
+            .iter()
+            .map(|chunk| combine_windows(window_size, chunk.iter()))
+            .collect();
+
+        let trace = MultiexpTrace {
+            chunk_size,
+            chunks,
+            window_size,
+            windows,
+        };
+        Ok((combine_windows(window_size, trace.windows.iter()), trace))
+    }
+
     /// Returns the number of kernels (one per device).
     pub fn num_kernels(&self) -> usize {
         self.kernels.len()
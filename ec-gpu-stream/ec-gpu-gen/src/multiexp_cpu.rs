@@ -1,16 +1,18 @@
 #![allow(missing_docs)]
-use std::convert::TryInto;
+use std::cmp;
 use std::io;
 use std::iter;
-use std::ops::AddAssign;
+use std::ops::{AddAssign, Range};
 use std::sync::Arc;
 
 use bitvec::prelude::{BitVec, Lsb0};
-use ff::{Field, PrimeField};
-use group::{prime::PrimeCurveAffine, Group};
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use group::{prime::PrimeCurveAffine, Group, GroupEncoding};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 
 use crate::error::EcError;
+use crate::scalar::WindowScalar;
 use crate::threadpool::{Waiter, Worker};
 
 /// An object that builds a source of bases.
@@ -87,7 +89,7 @@ pub trait QueryDensity: Sized {
 
     fn iter(self) -> Self::Iter;
     fn get_query_size(self) -> Option<usize>;
-    fn generate_exps<F: PrimeField>(self, exponents: Arc<Vec<F::Repr>>) -> Arc<Vec<F::Repr>>;
+    fn generate_exps<E: WindowScalar>(self, exponents: Arc<Vec<E>>) -> Arc<Vec<E>>;
 }
 
 #[derive(Clone)]
@@ -110,7 +112,7 @@ impl<'a> QueryDensity for &'a FullDensity {
         None
     }
 
-    fn generate_exps<F: PrimeField>(self, exponents: Arc<Vec<F::Repr>>) -> Arc<Vec<F::Repr>> {
+    fn generate_exps<E: WindowScalar>(self, exponents: Arc<Vec<E>>) -> Arc<Vec<E>> {
         exponents
     }
 }
@@ -132,7 +134,7 @@ impl<'a> QueryDensity for &'a DensityTracker {
         Some(self.bv.len())
     }
 
-    fn generate_exps<F: PrimeField>(self, exponents: Arc<Vec<F::Repr>>) -> Arc<Vec<F::Repr>> {
+    fn generate_exps<E: WindowScalar>(self, exponents: Arc<Vec<E>>) -> Arc<Vec<E>> {
         let exps: Vec<_> = exponents
             .iter()
             .zip(self.bv.iter())
@@ -206,86 +208,141 @@ impl DensityTracker {
     }
 }
 
-// Right shift the repr of a field element by `n` bits.
-fn shr(le_bytes: &mut [u8], mut n: u32) {
-    if n >= 8 * le_bytes.len() as u32 {
-        le_bytes.iter_mut().for_each(|byte| *byte = 0);
-        return;
+/// The minimum number of terms of a chunk, so that merging the buckets of the chunks doesn't
+/// outweigh the gain of the additional parallelism.
+const MIN_CHUNK_SIZE: usize = 1 << 12;
+
+/// Returns the number of chunks the terms of a single window are split into.
+///
+/// If there are fewer windows than threads, the terms of each window are split into several
+/// chunks, so that all threads have some work to do.
+fn num_chunks(num_terms: usize, num_windows: usize, num_threads: usize) -> usize {
+    let wanted = (num_threads + num_windows - 1) / num_windows;
+    cmp::max(1, cmp::min(wanted, num_terms / MIN_CHUNK_SIZE))
+}
+
+/// Intermediate results of a multiexp, recorded in the reproducible mode.
+///
+/// The values only depend on the input and on the parameters of the reproducible mode, but not on
+/// the number of threads or devices. Traces of different runs, machines or implementations can
+/// therefore be compared in order to find the first chunk (and window) a mismatch originates from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiexpTrace<C> {
+    /// The number of terms of a chunk. Chunk `i` contains the terms
+    /// `i * chunk_size..(i + 1) * chunk_size`.
+    pub chunk_size: usize,
+    /// The partial sum of each chunk, in order.
+    pub chunks: Vec<C>,
+    /// The window size.
+    pub window_size: u32,
+    /// The sum of each window over all terms, least significant window first. The sum of window
+    /// `i` is not yet multiplied by `2^(i * window_size)`.
+    pub windows: Vec<C>,
+}
+
+impl<C: PartialEq> MultiexpTrace<C> {
+    /// Returns the index of the first chunk whose partial sum differs from the one of `other`.
+    ///
+    /// If the traces have a different number of chunks, the first missing chunk is a mismatch.
+    pub fn first_mismatching_chunk(&self, other: &Self) -> Option<usize> {
+        first_mismatch(&self.chunks, &other.chunks)
+    }
+
+    /// Returns the index of the first window whose sum differs from the one of `other`.
+    pub fn first_mismatching_window(&self, other: &Self) -> Option<usize> {
+        first_mismatch(&self.windows, &other.windows)
     }
+}
 
-    // Shift each full byte towards the least significant end.
-    while n >= 8 {
-        let mut replacement = 0;
-        for byte in le_bytes.iter_mut().rev() {
-            std::mem::swap(&mut replacement, byte);
+impl<C: GroupEncoding> MultiexpTrace<C> {
+    /// Dumps the trace in a line based text format, so that it can be compared with a simple
+    /// `diff`. The points are written in their hex encoded canonical encoding.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "chunk_size {}", self.chunk_size)?;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            writeln!(writer, "chunk {} {}", index, hex::encode(chunk.to_bytes()))?;
         }
-        n -= 8;
-    }
-
-    // Starting at the most significant byte, shift the byte's `n` least significant bits into the
-    // `n` most signficant bits of the next byte.
-    if n > 0 {
-        let mut shift_in = 0;
-        for byte in le_bytes.iter_mut().rev() {
-            // Copy the byte's `n` least significant bits.
-            let shift_out = *byte << (8 - n);
-            // Shift the byte by `n` bits; zeroing its `n` most significant bits.
-            *byte >>= n;
-            // Replace the `n` most significant bits with the bits shifted out of the previous byte.
-            *byte |= shift_in;
-            shift_in = shift_out;
+        writeln!(writer, "window_size {}", self.window_size)?;
+        for (index, window) in self.windows.iter().enumerate() {
+            writeln!(
+                writer,
+                "window {} {}",
+                index,
+                hex::encode(window.to_bytes())
+            )?;
         }
+        Ok(())
     }
 }
 
-fn multiexp_inner<Q, D, G, S>(
+/// The result of a multiexp in the reproducible mode, together with its trace.
+pub type TracedMultiexp<C> = (C, MultiexpTrace<C>);
+
+fn first_mismatch<C: PartialEq>(left: &[C], right: &[C]) -> Option<usize> {
+    left.iter()
+        .zip(right.iter())
+        .position(|(l, r)| l != r)
+        .or_else(|| (left.len() != right.len()).then(|| cmp::min(left.len(), right.len())))
+}
+
+/// Runs the multiexp with window size `c`.
+///
+/// If `reproducible_chunk_size` is set, the terms are split into chunks of that size (instead of
+/// a split that depends on the number of threads) and the partial sums are recorded in a
+/// [`MultiexpTrace`].
+#[allow(clippy::type_complexity)]
+fn multiexp_inner<Q, D, G, E, S>(
     bases: S,
     density_map: D,
-    exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
+    exponents: Arc<Vec<E>>,
     c: u32,
-) -> Result<<G as PrimeCurveAffine>::Curve, EcError>
+    reproducible_chunk_size: Option<usize>,
+) -> Result<(G::Curve, Option<MultiexpTrace<G::Curve>>), EcError>
 where
     for<'a> &'a Q: QueryDensity,
     D: Send + Sync + 'static + Clone + AsRef<Q>,
     G: PrimeCurveAffine,
+    E: WindowScalar,
     S: SourceBuilder<G>,
 {
-    // Perform this region of the multiexp
+    // Sort the terms of `range` into buckets for the window starting at bit `skip`. The first
+    // term of the range uses the base at `base_offset` (relative to the start of the source).
     let this = move |bases: S,
                      density_map: D,
-                     exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
-                     skip: u32|
+                     exponents: Arc<Vec<E>>,
+                     skip: u32,
+                     range: Range<usize>,
+                     base_offset: usize|
           -> Result<_, EcError> {
         // Accumulate the result
         let mut acc = G::Curve::identity();
 
         // Build a source for the bases
         let mut bases = bases.new();
+        if base_offset > 0 {
+            bases.skip(base_offset)?;
+        }
 
         // Create space for the buckets
         let mut buckets = vec![<G as PrimeCurveAffine>::Curve::identity(); (1 << c) - 1];
 
-        let zero = G::Scalar::zero().to_repr();
-        let one = G::Scalar::one().to_repr();
-
         // only the first round uses this
         let handle_trivial = skip == 0;
 
         // Sort the bases into buckets
-        for (&exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
+        let density = density_map.as_ref().iter().skip(range.start);
+        for (exp, density) in exponents[range].iter().zip(density) {
             if density {
-                if exp.as_ref() == zero.as_ref() {
+                if exp.is_zero() {
                     bases.skip(1)?;
-                } else if exp.as_ref() == one.as_ref() {
+                } else if exp.is_one() {
                     if handle_trivial {
                         bases.add_assign_mixed(&mut acc)?;
                     } else {
                         bases.skip(1)?;
                     }
                 } else {
-                    let mut exp = exp;
-                    shr(exp.as_mut(), skip);
-                    let exp = u64::from_le_bytes(exp.as_ref()[..8].try_into().unwrap()) % (1 << c);
+                    let exp = exp.get_bits(skip, c);
 
                     if exp != 0 {
                         bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
@@ -296,57 +353,282 @@ where
             }
         }
 
-        // Summation by parts
-        // e.g. 3a + 2b + 1c = a +
-        //                    (a) + b +
-        //                    ((a) + b) + c
-        let mut running_sum = G::Curve::identity();
-        for exp in buckets.into_iter().rev() {
-            running_sum.add_assign(&exp);
-            acc.add_assign(&running_sum);
-        }
+        Ok((acc, buckets))
+    };
 
-        Ok(acc)
+    let num_terms = exponents.len();
+    let num_bits = E::exponent_bits::<G::Scalar>();
+    let num_windows = ((num_bits + c - 1) / c) as usize;
+    let chunk_size = match reproducible_chunk_size {
+        Some(chunk_size) => chunk_size,
+        None => {
+            let num_chunks = num_chunks(num_terms, num_windows, rayon::current_num_threads());
+            cmp::max(1, (num_terms + num_chunks - 1) / num_chunks)
+        }
     };
+    let num_chunks = cmp::max(1, (num_terms + chunk_size - 1) / chunk_size);
+
+    // The chunks are ranges of terms. As only terms that are part of the density map have a base,
+    // the offset into the bases needs to be calculated for each chunk.
+    let mut chunks = Vec::with_capacity(num_chunks);
+    let mut density = density_map.as_ref().iter();
+    let mut base_offset = 0;
+    for start in (0..cmp::max(num_terms, 1)).step_by(chunk_size) {
+        let end = cmp::min(start + chunk_size, num_terms);
+        chunks.push((start..end, base_offset));
+        base_offset += density.by_ref().take(end - start).filter(|d| *d).count();
+    }
 
-    let parts = (0..<G::Scalar as PrimeField>::NUM_BITS)
+    let parts = (0..num_bits)
         .into_par_iter()
         .step_by(c as usize)
-        .map(|skip| this(bases.clone(), density_map.clone(), exponents.clone(), skip))
-        .collect::<Vec<Result<_, _>>>();
-
-    parts.into_iter().rev().try_fold(
-        <G as PrimeCurveAffine>::Curve::identity(),
-        |mut acc, part| {
-            for _ in 0..c {
-                acc = acc.double();
+        .map(|skip| -> Result<_, EcError> {
+            let chunk_parts = chunks
+                .par_iter()
+                .map(|(range, base_offset)| {
+                    this(
+                        bases.clone(),
+                        density_map.clone(),
+                        exponents.clone(),
+                        skip,
+                        range.clone(),
+                        *base_offset,
+                    )
+                })
+                .collect::<Vec<Result<_, _>>>();
+
+            if reproducible_chunk_size.is_some() {
+                // Reduce the buckets of every chunk on its own, so that the partial sums of the
+                // chunks can be recorded.
+                let chunk_sums = chunk_parts
+                    .into_iter()
+                    .map(|chunk_part| {
+                        let (acc, buckets) = chunk_part?;
+                        Ok(sum_buckets::<G>(acc, buckets))
+                    })
+                    .collect::<Result<Vec<_>, EcError>>()?;
+                let sum = chunk_sums
+                    .iter()
+                    .fold(G::Curve::identity(), |mut sum, chunk_sum| {
+                        sum.add_assign(chunk_sum);
+                        sum
+                    });
+                return Ok((sum, chunk_sums));
+            }
+
+            // Merge the buckets of all chunks, always in the same order.
+            let mut chunk_parts = chunk_parts.into_iter();
+            let (mut acc, mut buckets) =
+                chunk_parts.next().expect("there is at least one chunk")?;
+            for chunk_part in chunk_parts {
+                let (chunk_acc, chunk_buckets) = chunk_part?;
+                acc.add_assign(&chunk_acc);
+                for (bucket, chunk_bucket) in buckets.iter_mut().zip(chunk_buckets.iter()) {
+                    bucket.add_assign(chunk_bucket);
+                }
             }
 
-            acc.add_assign(&part?);
-            Ok(acc)
-        },
+            Ok((sum_buckets::<G>(acc, buckets), Vec::new()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let result = combine_windows(c, parts.iter().map(|(sum, _)| sum));
+    let trace = reproducible_chunk_size.map(|chunk_size| MultiexpTrace {
+        chunk_size,
+        chunks: (0..num_chunks)
+            .map(|chunk| combine_windows(c, parts.iter().map(|(_, chunk_sums)| &chunk_sums[chunk])))
+            .collect(),
+        window_size: c,
+        windows: parts.iter().map(|(sum, _)| *sum).collect(),
+    });
+    Ok((result, trace))
+}
+
+/// Combines the sums of the windows of size `c`, the least significant window comes first.
+pub(crate) fn combine_windows<'a, C: Group>(
+    c: u32,
+    windows: impl DoubleEndedIterator<Item = &'a C>,
+) -> C {
+    windows.rev().fold(C::identity(), |mut acc, window| {
+        for _ in 0..c {
+            acc = acc.double();
+        }
+        acc.add_assign(window);
+        acc
+    })
+}
+
+/// Reduces the buckets of a window and adds them to `acc`, bucket `i` is multiplied by `i + 1`.
+fn sum_buckets<G: PrimeCurveAffine>(mut acc: G::Curve, buckets: Vec<G::Curve>) -> G::Curve {
+    // Summation by parts
+    // e.g. 3a + 2b + 1c = a +
+    //                    (a) + b +
+    //                    ((a) + b) + c
+    let mut running_sum = G::Curve::identity();
+    for exp in buckets.into_iter().rev() {
+        running_sum.add_assign(&exp);
+        acc.add_assign(&running_sum);
+    }
+    acc
+}
+
+/// The largest window size the cost model will pick. With 2^20 buckets per window the bucket
+/// memory alone is already in the hundreds of megabytes.
+const MAX_WINDOW_SIZE: u32 = 20;
+
+/// The cost of the bookkeeping that is done for every term in every window (extracting the bits
+/// of the exponent, walking the density map and the source of bases). It's measured in the same
+/// unit as the [`WindowCostModel`], i.e. base field multiplications of a 64-bit limb field.
+const TERM_OVERHEAD: f64 = 24.0;
+
+/// Cost model that is used to pick the window size `c` of the Pippenger multiexp.
+///
+/// All costs are relative to each other, the unit doesn't matter as long as it's the same for all
+/// of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowCostModel {
+    /// Cost of adding an affine base into a projective bucket.
+    pub mixed_add: f64,
+    /// Cost of adding two projective points, as done during the bucket reduction.
+    pub add: f64,
+    /// Cost of doubling a projective point, as done when the windows are combined.
+    pub double: f64,
+    /// Cost of the bookkeeping for a single term in a single window, independent of the curve.
+    pub term_overhead: f64,
+}
+
+impl WindowCostModel {
+    /// Cost model for Jacobian coordinates, where a multiplication in the base field costs
+    /// `field_mul`.
+    ///
+    /// The operation counts are the ones of the formulas used in `ec.cl`, where a squaring is
+    /// counted as a multiplication: madd-2007-bl (7M + 4S), add-2007-bl (11M + 5S) and
+    /// dbl-2009-l (2M + 5S).
+    pub fn jacobian(field_mul: f64) -> Self {
+        Self {
+            mixed_add: 11.0 * field_mul,
+            add: 16.0 * field_mul,
+            double: 7.0 * field_mul,
+            term_overhead: TERM_OVERHEAD,
+        }
+    }
+
+    /// Cost model for the curve `G`.
+    ///
+    /// The cost of a base field multiplication is estimated from the size of the projective
+    /// coordinates, it grows quadratically with the number of 64-bit limbs. This makes e.g. G2
+    /// additions of BLS12-381 (over `Fp2`) more expensive than G1 additions (over `Fp`).
+    pub fn for_curve<G: PrimeCurveAffine>() -> Self {
+        let coordinate_limbs = std::mem::size_of::<G::Curve>() / 3 / 8;
+        let field_mul = (coordinate_limbs * coordinate_limbs).max(1) as f64;
+        Self::jacobian(field_mul)
+    }
+
+    /// Returns the estimated cost of a multiexp with `num_terms` terms of `num_bits`-bit exponents
+    /// and the given window size, when the work is distributed over `num_threads` threads.
+    pub fn estimate(
+        &self,
+        window_size: u32,
+        num_terms: usize,
+        num_bits: u32,
+        num_threads: usize,
+    ) -> f64 {
+        let num_threads = num_threads.max(1);
+        let num_windows = (num_bits + window_size - 1) / window_size;
+        let num_chunks = num_chunks(num_terms, num_windows as usize, num_threads);
+        let num_buckets = ((1u64 << window_size) - 1) as f64;
+
+        // Every term is sorted into a bucket. The windows as well as chunks of terms within a
+        // window are processed in parallel, the slowest thread determines the time.
+        let num_tasks = num_windows as usize * num_chunks;
+        let accumulate_rounds = (num_tasks + num_threads - 1) / num_threads;
+        let chunk_terms = (num_terms + num_chunks - 1) / num_chunks;
+        let accumulate =
+            accumulate_rounds as f64 * chunk_terms as f64 * (self.mixed_add + self.term_overhead);
+
+        // Per window, the buckets of the chunks are merged and then summed up by parts, which
+        // needs two additions per bucket.
+        let reduce_rounds = (num_windows as usize + num_threads - 1) / num_threads;
+        let reduce = reduce_rounds as f64 * (num_chunks + 1) as f64 * num_buckets * self.add;
+
+        // Combining the windows is done sequentially on a single thread.
+        let combine = f64::from(num_bits) * self.double + f64::from(num_windows) * self.add;
+        accumulate + reduce + combine
+    }
+
+    /// Returns the window size with the lowest estimated cost.
+    pub fn window_size(&self, num_terms: usize, num_bits: u32, num_threads: usize) -> u32 {
+        (1..=cmp::min(MAX_WINDOW_SIZE, num_bits.max(1)))
+            .map(|c| (c, self.estimate(c, num_terms, num_bits, num_threads)))
+            .fold((1, f64::INFINITY), |best, (c, cost)| {
+                if cost < best.1 {
+                    (c, cost)
+                } else {
+                    best
+                }
+            })
+            .0
+    }
+}
+
+/// Returns the window size that [`multiexp_cpu`] uses for `num_terms` terms of the curve `G` with
+/// exponents of type `E`, when running on `num_threads` threads.
+pub fn optimal_window_size<G: PrimeCurveAffine, E: WindowScalar>(
+    num_terms: usize,
+    num_threads: usize,
+) -> u32 {
+    WindowCostModel::for_curve::<G>().window_size(
+        num_terms,
+        E::exponent_bits::<G::Scalar>(),
+        num_threads,
     )
 }
 
 /// Perform multi-exponentiation. The caller is responsible for ensuring the
 /// query size is the same as the number of exponents.
-pub fn multiexp_cpu<'b, Q, D, G, S>(
+///
+/// The exponents can be any [`WindowScalar`], e.g. the representation of field elements or plain
+/// integers. The window size is picked by the [`WindowCostModel`] of the curve.
+pub fn multiexp_cpu<'b, Q, D, G, E, S>(
     pool: &Worker,
     bases: S,
     density_map: D,
-    exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
+    exponents: Arc<Vec<E>>,
 ) -> Waiter<Result<<G as PrimeCurveAffine>::Curve, EcError>>
 where
     for<'a> &'a Q: QueryDensity,
     D: Send + Sync + 'static + Clone + AsRef<Q>,
     G: PrimeCurveAffine,
+    E: WindowScalar,
     S: SourceBuilder<G>,
 {
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
+    let c = WindowCostModel::for_curve::<G>().window_size(
+        exponents.len(),
+        E::exponent_bits::<G::Scalar>(),
+        pool.num_threads(),
+    );
+    multiexp_cpu_with_window_size(pool, bases, density_map, exponents, c)
+}
+
+/// Perform multi-exponentiation with a given window size. The caller is responsible for ensuring
+/// the query size is the same as the number of exponents.
+///
+/// This is useful if the best window size for a certain machine was determined by benchmarks.
+pub fn multiexp_cpu_with_window_size<'b, Q, D, G, E, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<E>>,
+    c: u32,
+) -> Waiter<Result<<G as PrimeCurveAffine>::Curve, EcError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: PrimeCurveAffine,
+    E: WindowScalar,
+    S: SourceBuilder<G>,
+{
+    assert!(c > 0, "The window size must be at least one bit.");
 
     if let Some(query_size) = density_map.as_ref().get_query_size() {
         // If the density map has a known query size, it should not be
@@ -354,7 +636,50 @@ where
         assert!(query_size == exponents.len());
     }
 
-    pool.compute(move || multiexp_inner(bases, density_map, exponents, c))
+    // The parallel iterators within `multiexp_inner()` are running on the thread pool of the
+    // worker, as that's where the computation is spawned on.
+    pool.compute(move || {
+        multiexp_inner(bases, density_map, exponents, c, None).map(|(result, _)| result)
+    })
+}
+
+/// Perform multi-exponentiation in the reproducible mode. The caller is responsible for ensuring
+/// the query size is the same as the number of exponents.
+///
+/// The work is split independent of the number of threads, the terms are split into chunks of
+/// `chunk_size` terms. Next to the result, the partial sums of the chunks and windows are
+/// returned as [`MultiexpTrace`]. The chunks and windows can be compared with the trace of
+/// [`crate::multiexp::MultiexpKernel::multiexp_reproducible`] with the same window and chunk
+/// size, if the density map contains all terms.
+pub fn multiexp_cpu_reproducible<'b, Q, D, G, E, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<E>>,
+    c: u32,
+    chunk_size: usize,
+) -> Waiter<Result<TracedMultiexp<G::Curve>, EcError>>
+where
+    for<'a> &'a Q: QueryDensity,
+    D: Send + Sync + 'static + Clone + AsRef<Q>,
+    G: PrimeCurveAffine,
+    E: WindowScalar,
+    S: SourceBuilder<G>,
+{
+    assert!(c > 0, "The window size must be at least one bit.");
+    assert!(chunk_size > 0, "The chunk size must be at least one term.");
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        assert!(query_size == exponents.len());
+    }
+
+    pool.compute(move || {
+        let (result, trace) = multiexp_inner(bases, density_map, exponents, c, Some(chunk_size))?;
+        Ok((
+            result,
+            trace.expect("the trace is recorded in reproducible mode"),
+        ))
+    })
 }
 
 #[cfg(test)]
@@ -362,29 +687,30 @@ mod tests {
     use super::*;
 
     use blstrs::Bls12;
+    use ff::{Field, PrimeField};
     use group::Curve;
     use pairing::Engine;
     use rand::Rng;
     use rand_core::SeedableRng;
     use rand_xorshift::XorShiftRng;
 
-    #[test]
-    fn test_with_bls12() {
-        fn naive_multiexp<G: PrimeCurveAffine>(
-            bases: Arc<Vec<G>>,
-            exponents: &[G::Scalar],
-        ) -> G::Curve {
-            assert_eq!(bases.len(), exponents.len());
-
-            let mut acc = G::Curve::identity();
+    fn naive_multiexp<G: PrimeCurveAffine>(
+        bases: Arc<Vec<G>>,
+        exponents: &[G::Scalar],
+    ) -> G::Curve {
+        assert_eq!(bases.len(), exponents.len());
 
-            for (base, exp) in bases.iter().zip(exponents.iter()) {
-                acc.add_assign(&base.mul(*exp));
-            }
+        let mut acc = G::Curve::identity();
 
-            acc
+        for (base, exp) in bases.iter().zip(exponents.iter()) {
+            acc.add_assign(&base.mul(*exp));
         }
 
+        acc
+    }
+
+    #[test]
+    fn test_with_bls12() {
         const SAMPLES: usize = 1 << 14;
 
         let rng = &mut rand::thread_rng();
@@ -412,6 +738,262 @@ mod tests {
         assert_eq!(naive, fast);
     }
 
+    #[test]
+    fn test_with_window_size() {
+        const SAMPLES: usize = 1 << 8;
+
+        let rng = &mut rand::thread_rng();
+        let v: Vec<<Bls12 as Engine>::Fr> = (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::Fr::random(&mut *rng))
+            .collect();
+        let g = Arc::new(
+            (0..SAMPLES)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut *rng).to_affine())
+                .collect::<Vec<_>>(),
+        );
+        let naive = naive_multiexp(g.clone(), &v);
+
+        let pool = Worker::new();
+        let v = Arc::new(v.into_iter().map(|fr| fr.to_repr()).collect::<Vec<_>>());
+        for c in [1, 2, 7, 13] {
+            let fast =
+                multiexp_cpu_with_window_size(&pool, (g.clone(), 0), FullDensity, v.clone(), c)
+                    .wait()
+                    .unwrap();
+            assert_eq!(naive, fast, "Window size {} gives a wrong result.", c);
+        }
+    }
+
+    #[test]
+    fn test_window_scalars() {
+        const SAMPLES: usize = 1 << 8;
+
+        fn from_limbs(limbs: &[u64]) -> <Bls12 as Engine>::Fr {
+            let shift = <Bls12 as Engine>::Fr::from(u64::MAX) + <Bls12 as Engine>::Fr::one();
+            limbs
+                .iter()
+                .rev()
+                .fold(<Bls12 as Engine>::Fr::zero(), |acc, limb| {
+                    acc * shift + <Bls12 as Engine>::Fr::from(*limb)
+                })
+        }
+
+        let rng = &mut rand::thread_rng();
+        let pool = Worker::new();
+        let g = Arc::new(
+            (0..SAMPLES)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut *rng).to_affine())
+                .collect::<Vec<_>>(),
+        );
+
+        let mut limbs = (0..SAMPLES).map(|_| rng.gen()).collect::<Vec<[u64; 4]>>();
+        // Make sure the trivial exponents are covered.
+        limbs[0] = [0; 4];
+        limbs[1] = [1, 0, 0, 0];
+        let expected = naive_multiexp(
+            g.clone(),
+            &limbs
+                .iter()
+                .map(|l| from_limbs(&l[..1]))
+                .collect::<Vec<_>>(),
+        );
+        let exps = Arc::new(limbs.iter().map(|l| l[0]).collect::<Vec<u64>>());
+        let result = multiexp_cpu(&pool, (g.clone(), 0), FullDensity, exps)
+            .wait()
+            .unwrap();
+        assert_eq!(result, expected);
+
+        let expected = naive_multiexp(
+            g.clone(),
+            &limbs
+                .iter()
+                .map(|l| from_limbs(&l[..2]))
+                .collect::<Vec<_>>(),
+        );
+        let exps = limbs
+            .iter()
+            .map(|l| u128::from(l[0]) | u128::from(l[1]) << 64)
+            .collect::<Vec<u128>>();
+        let result = multiexp_cpu(&pool, (g.clone(), 0), FullDensity, Arc::new(exps))
+            .wait()
+            .unwrap();
+        assert_eq!(result, expected);
+
+        // The integers may be bigger than the modulus of the scalar field.
+        let expected = naive_multiexp(
+            g.clone(),
+            &limbs.iter().map(|l| from_limbs(l)).collect::<Vec<_>>(),
+        );
+        let result = multiexp_cpu(&pool, (g, 0), FullDensity, Arc::new(limbs))
+            .wait()
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_split_windows() {
+        // Enough terms so that each window is split into several chunks.
+        const SAMPLES: usize = MIN_CHUNK_SIZE * 5 + 123;
+        const WINDOW_SIZE: u32 = 16;
+
+        let rng = &mut rand::thread_rng();
+        let mut density = DensityTracker::new();
+        let mut dense_exps = Vec::new();
+        let mut exps = Vec::new();
+        for i in 0..SAMPLES {
+            density.add_element();
+            let exp = <Bls12 as Engine>::Fr::random(&mut *rng);
+            if rng.gen() {
+                density.inc(i);
+                dense_exps.push(exp);
+            }
+            exps.push(exp.to_repr());
+        }
+        let g = Arc::new(
+            (0..dense_exps.len())
+                .map(|_| <Bls12 as Engine>::G1::random(&mut *rng).to_affine())
+                .collect::<Vec<_>>(),
+        );
+        let naive = naive_multiexp(g.clone(), &dense_exps);
+
+        // Use more threads than there are windows, so that the windows are split.
+        let num_windows = (<Bls12 as Engine>::Fr::NUM_BITS + WINDOW_SIZE - 1) / WINDOW_SIZE;
+        let num_threads = num_windows as usize * 4;
+        assert!(num_chunks(SAMPLES, num_windows as usize, num_threads) > 1);
+        let pool = Worker::with_config(num_threads, "test-split-windows").unwrap();
+        let split = multiexp_cpu_with_window_size(
+            &pool,
+            (g, 0),
+            Arc::new(density),
+            Arc::new(exps),
+            WINDOW_SIZE,
+        )
+        .wait()
+        .unwrap();
+
+        assert_eq!(naive, split);
+    }
+
+    #[test]
+    fn test_reproducible() {
+        const SAMPLES: usize = 1000;
+        const CHUNK_SIZE: usize = 300;
+        const WINDOW_SIZE: u32 = 5;
+
+        let rng = &mut rand::thread_rng();
+        let g = Arc::new(
+            (0..SAMPLES)
+                .map(|_| <Bls12 as Engine>::G1::random(&mut *rng).to_affine())
+                .collect::<Vec<_>>(),
+        );
+        let v = (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::Fr::random(&mut *rng))
+            .collect::<Vec<_>>();
+        let v_repr = Arc::new(v.iter().map(|exp| exp.to_repr()).collect::<Vec<_>>());
+
+        let traces = [1, 3, 8]
+            .iter()
+            .map(|&num_threads| {
+                let pool = Worker::with_config(num_threads, "test-reproducible").unwrap();
+                multiexp_cpu_reproducible(
+                    &pool,
+                    (g.clone(), 0),
+                    FullDensity,
+                    v_repr.clone(),
+                    WINDOW_SIZE,
+                    CHUNK_SIZE,
+                )
+                .wait()
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let (result, trace) = &traces[0];
+        assert_eq!(*result, naive_multiexp(g.clone(), &v));
+        assert_eq!(trace.chunks.len(), 4);
+        for (index, chunk) in trace.chunks.iter().enumerate() {
+            let range = index * CHUNK_SIZE..cmp::min((index + 1) * CHUNK_SIZE, SAMPLES);
+            let bases = Arc::new(g[range.clone()].to_vec());
+            assert_eq!(*chunk, naive_multiexp(bases, &v[range]));
+        }
+        // The windows cover the 255 bits of the scalar field, not the 256 bits of its bytes.
+        assert_eq!(trace.windows.len(), 51);
+        assert_eq!(combine_windows(WINDOW_SIZE, trace.windows.iter()), *result);
+
+        // The trace doesn't depend on the number of threads.
+        for (other_result, other_trace) in &traces[1..] {
+            assert_eq!(result, other_result);
+            assert_eq!(trace.first_mismatching_chunk(other_trace), None);
+            assert_eq!(trace.first_mismatching_window(other_trace), None);
+            assert_eq!(trace, other_trace);
+        }
+
+        let mut broken = trace.clone();
+        broken.chunks[2] = broken.chunks[2].double();
+        assert_eq!(trace.first_mismatching_chunk(&broken), Some(2));
+        broken.chunks.truncate(2);
+        assert_eq!(trace.first_mismatching_chunk(&broken), Some(2));
+
+        let mut dump = Vec::new();
+        trace.write(&mut dump).unwrap();
+        let dump = String::from_utf8(dump).unwrap();
+        assert_eq!(dump.lines().count(), 1 + 4 + 1 + trace.windows.len());
+        assert!(dump.starts_with("chunk_size 300\nchunk 0 "));
+    }
+
+    #[test]
+    fn test_num_chunks() {
+        // Enough windows for all threads.
+        assert_eq!(num_chunks(1 << 20, 16, 8), 1);
+        assert_eq!(num_chunks(1 << 20, 16, 16), 1);
+        // More threads than windows.
+        assert_eq!(num_chunks(1 << 20, 16, 64), 4);
+        assert_eq!(num_chunks(1 << 20, 16, 65), 5);
+        // Too few terms to be worth splitting.
+        assert_eq!(num_chunks(MIN_CHUNK_SIZE * 2, 16, 64), 2);
+        assert_eq!(num_chunks(MIN_CHUNK_SIZE - 1, 16, 64), 1);
+        assert_eq!(num_chunks(0, 16, 64), 1);
+    }
+
+    #[test]
+    fn test_window_size_cost_model() {
+        type G1 = <Bls12 as Engine>::G1Affine;
+        type G2 = <Bls12 as Engine>::G2Affine;
+        type Exp = <<Bls12 as Engine>::Fr as PrimeField>::Repr;
+
+        for num_threads in [1, 4, 16, 64] {
+            let mut previous = 1;
+            for log_terms in 0..28 {
+                let num_terms = 1 << log_terms;
+                let g1 = optimal_window_size::<G1, Exp>(num_terms, num_threads);
+                let g2 = optimal_window_size::<G2, Exp>(num_terms, num_threads);
+                assert!((1..=MAX_WINDOW_SIZE).contains(&g1));
+                assert!((1..=MAX_WINDOW_SIZE).contains(&g2));
+                assert!(
+                    g1 >= previous,
+                    "More terms must not lead to a smaller window size."
+                );
+                previous = g1;
+            }
+        }
+
+        // More threads can process more windows in parallel, hence smaller windows are preferred.
+        let num_terms = 1 << 16;
+        assert!(
+            optimal_window_size::<G1, Exp>(num_terms, 64)
+                <= optimal_window_size::<G1, Exp>(num_terms, 1)
+        );
+
+        // The representations of the scalars are estimated with the bits of the field.
+        let model = WindowCostModel::for_curve::<G1>();
+        for num_terms in [1, 1 << 10, 1 << 20] {
+            assert_eq!(
+                optimal_window_size::<G1, Exp>(num_terms, 4),
+                model.window_size(num_terms, <Bls12 as Engine>::Fr::NUM_BITS, 4)
+            );
+        }
+    }
+
     #[test]
     fn test_extend_density_regular() {
         let mut rng = XorShiftRng::from_seed([
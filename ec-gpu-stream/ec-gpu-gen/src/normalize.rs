@@ -0,0 +1,103 @@
+use std::marker::PhantomData;
+
+use ec_gpu::GpuName;
+use rust_gpu_tools::{program_closures, Program};
+
+use crate::error::{EcError, EcResult};
+use crate::layout::GpuAffine;
+use crate::normalize_cpu::{num_segments, SEGMENT_SIZE};
+
+/// The local work size of the batch normalization kernel.
+const LOCAL_WORK_SIZE: usize = 64;
+
+/// Batch normalization kernel for a single GPU.
+///
+/// The program must contain the kernel of [`crate::SourceBuilder::add_batch_normalization`] for
+/// the curve `C`.
+pub struct BatchNormalizeKernel<'a, C>
+where
+    C: GpuName,
+{
+    program: Program,
+    /// An optional function which will be called at places where it is possible to abort the
+    /// normalization. If it returns true, the calculation will be aborted with an
+    /// [`EcError::Aborted`].
+    maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, C> BatchNormalizeKernel<'a, C>
+where
+    C: GpuName,
+{
+    /// Create a new batch normalization kernel instance for a device.
+    ///
+    /// The `maybe_abort` function is called when it is possible to abort the computation, without
+    /// leaving the GPU in a weird state. If that function returns `true`, execution is aborted.
+    pub fn create(
+        program: Program,
+        maybe_abort: Option<&'a (dyn Fn() -> bool + Send + Sync)>,
+    ) -> EcResult<Self> {
+        Ok(Self {
+            program,
+            maybe_abort,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Converts projective points into affine points on the GPU.
+    ///
+    /// It's the GPU counterpart of `batch_normalize` of the `group` crate. The projective points
+    /// are read and the affine points are written in the memory layout of [`GpuAffine`], like the
+    /// results of the multiexp.
+    pub fn normalize<G: GpuAffine>(&self, points: &[G::Curve]) -> EcResult<Vec<G>> {
+        let field_size = std::mem::size_of::<G>() / 2;
+        if std::mem::size_of::<G::Curve>() != 3 * field_size {
+            return Err(EcError::Simple(
+                "The projective points must have three and the affine points two coordinates.",
+            ));
+        }
+        let n = points.len();
+        if n > u32::MAX as usize {
+            return Err(EcError::Simple(
+                "Too many points for the normalization kernel.",
+            ));
+        }
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        if let Some(maybe_abort) = &self.maybe_abort {
+            if maybe_abort() {
+                return Err(EcError::Aborted);
+            }
+        }
+
+        let closures = program_closures!(|program, _arg| -> EcResult<Vec<G>> {
+            let points_buffer = program.create_buffer_from_slice(points)?;
+            // It is safe as the GPU will initialize those buffers.
+            let result_buffer = unsafe { program.create_buffer::<G>(n)? };
+            let scratch_buffer = unsafe { program.create_buffer::<u8>(n * field_size)? };
+
+            let kernel_name = format!("{}_batch_normalize", C::name());
+            let num_threads = num_segments(n, SEGMENT_SIZE);
+            let num_groups = (num_threads + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+            let kernel = program.create_kernel(&kernel_name, num_groups, LOCAL_WORK_SIZE)?;
+            kernel
+                .arg(&points_buffer)
+                .arg(&result_buffer)
+                .arg(&scratch_buffer)
+                .arg(&(n as u32))
+                .arg(&(SEGMENT_SIZE as u32))
+                .run()?;
+
+            // The kernel writes the affine points in the memory layout of `G`, which is guaranteed
+            // by `GpuAffine`.
+            let mut result = vec![G::identity(); n];
+            program.read_into_buffer(&result_buffer, &mut result)?;
+            Ok(result)
+        });
+
+        self.program.run(closures, ())
+    }
+}
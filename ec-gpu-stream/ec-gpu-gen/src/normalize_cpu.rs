@@ -0,0 +1,160 @@
+use ff::Field;
+
+use crate::curve_cpu::PointAffine;
+use crate::threadpool::Worker;
+
+/// The number of consecutive points a single thread of the GPU kernel normalizes, see
+/// [`crate::SourceBuilder::add_batch_normalization`].
+pub const SEGMENT_SIZE: usize = 64;
+
+/// A point in Jacobian coordinates, where `x = X / Z^2` and `y = Y / Z^3`.
+///
+/// It has the same memory layout as the results of the GPU multiexp and the projective points of
+/// `blstrs`. The point at infinity has `Z = 0`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointJacobian<F> {
+    /// The `X` coordinate.
+    pub x: F,
+    /// The `Y` coordinate.
+    pub y: F,
+    /// The `Z` coordinate.
+    pub z: F,
+}
+
+/// Returns the number of segments `n` points are split into, if every segment has
+/// `segment_size` consecutive points. The last one might be smaller.
+///
+/// On the GPU every segment is normalized by a single thread.
+pub fn num_segments(n: usize, segment_size: usize) -> usize {
+    assert!(segment_size > 0, "The segments must not be empty.");
+    (n + segment_size - 1) / segment_size
+}
+
+/// Normalizes the points of a single segment with Montgomery's trick, which needs a single
+/// inversion and six multiplications per point.
+///
+/// It's the same algorithm the GPU kernel uses for each segment.
+fn normalize_segment<F: Field>(points: &[PointJacobian<F>], result: &mut [PointAffine<F>]) {
+    // The products of all non-zero `Z` coordinates before the index.
+    let mut prefix_products = Vec::with_capacity(points.len());
+    let mut acc = F::one();
+    for point in points {
+        prefix_products.push(acc);
+        if !bool::from(point.z.is_zero()) {
+            acc *= point.z;
+        }
+    }
+
+    let mut inv = acc.invert().expect("the product of non-zero elements");
+    for ((point, prefix_product), result) in points.iter().zip(prefix_products).zip(result).rev() {
+        *result = if bool::from(point.z.is_zero()) {
+            PointAffine {
+                x: F::zero(),
+                y: F::zero(),
+            }
+        } else {
+            let z_inv = inv * prefix_product;
+            inv *= point.z;
+            let zz_inv = z_inv.square();
+            PointAffine {
+                x: point.x * zz_inv,
+                y: point.y * zz_inv * z_inv,
+            }
+        };
+    }
+}
+
+/// Converts points from Jacobian into affine coordinates (multithreaded).
+///
+/// The points are split into segments of `segment_size` points like on the GPU, each one is
+/// normalized with a single inversion. The point at infinity is mapped to `(0, 0)`, like in
+/// `blstrs`.
+pub fn batch_normalize<F: Field>(
+    points: &[PointJacobian<F>],
+    worker: &Worker,
+    segment_size: usize,
+) -> Vec<PointAffine<F>> {
+    let mut result = vec![
+        PointAffine {
+            x: F::zero(),
+            y: F::zero(),
+        };
+        points.len()
+    ];
+    worker.scope(num_segments(points.len(), segment_size), |scope, chunk| {
+        let chunk_size = chunk * segment_size;
+        for (points, result) in points.chunks(chunk_size).zip(result.chunks_mut(chunk_size)) {
+            scope.spawn(move |_| {
+                for (points, result) in points
+                    .chunks(segment_size)
+                    .zip(result.chunks_mut(segment_size))
+                {
+                    normalize_segment(points, result);
+                }
+            });
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+
+    #[test]
+    fn test_num_segments() {
+        assert_eq!(num_segments(0, 64), 0);
+        assert_eq!(num_segments(1, 64), 1);
+        assert_eq!(num_segments(64, 64), 1);
+        assert_eq!(num_segments(65, 64), 2);
+        assert_eq!(num_segments(10, 1), 10);
+    }
+
+    #[test]
+    fn test_batch_normalize() {
+        let mut rng = rand::thread_rng();
+        let worker = Worker::new();
+
+        for len in [0, 1, 7, 100] {
+            let expected = (0..len)
+                .map(|i| {
+                    // Every third point is the point at infinity.
+                    if i % 3 == 0 {
+                        PointAffine {
+                            x: Fr::zero(),
+                            y: Fr::zero(),
+                        }
+                    } else {
+                        PointAffine {
+                            x: Fr::random(&mut rng),
+                            y: Fr::random(&mut rng),
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let points = expected
+                .iter()
+                .enumerate()
+                .map(|(i, point)| {
+                    let z = if i % 3 == 0 {
+                        Fr::zero()
+                    } else {
+                        Fr::random(&mut rng)
+                    };
+                    PointJacobian {
+                        x: point.x * z.square(),
+                        y: point.y * z.square() * z,
+                        z,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            for segment_size in [1, 3, SEGMENT_SIZE, 1000] {
+                assert_eq!(batch_normalize(&points, &worker, segment_size), expected);
+            }
+        }
+    }
+}
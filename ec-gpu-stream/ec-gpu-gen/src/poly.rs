@@ -0,0 +1,584 @@
+use ff::PrimeField;
+
+use crate::domain::EvaluationDomain;
+use crate::error::{EcError, EcResult};
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+use crate::fft::{FftBuffer, FftKernel};
+use crate::fft_cpu;
+use crate::threadpool::Worker;
+use crate::vector_cpu;
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+use ec_gpu::GpuName;
+
+/// The FFTs the polynomial operations are built on.
+///
+/// It's implemented for the [`Worker`], which calculates the FFTs on the CPU, and for the
+/// [`FftKernel`], which calculates them on the GPU.
+pub trait FftEngine<F: PrimeField> {
+    /// Evaluates the polynomial given by its coefficients `a` at the powers of `omega`.
+    fn fft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()>;
+
+    /// Interpolates the evaluations `a` at the powers of `omega` into coefficients.
+    fn ifft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()>;
+
+    /// Evaluates the polynomial given by its coefficients `a` at `g` times the powers of `omega`.
+    fn coset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()>;
+
+    /// Interpolates the evaluations `a` at `g` times the powers of `omega` into coefficients.
+    fn icoset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()>;
+}
+
+impl<F: PrimeField> FftEngine<F> for Worker {
+    fn fft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        fft_cpu::fft(a, self, omega, log_n);
+        Ok(())
+    }
+
+    fn ifft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        fft_cpu::ifft(a, self, omega, log_n);
+        Ok(())
+    }
+
+    fn coset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        fft_cpu::coset_fft(a, self, omega, log_n, g);
+        Ok(())
+    }
+
+    fn icoset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        fft_cpu::icoset_fft(a, self, omega, log_n, g);
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+impl<'a, F: PrimeField + GpuName> FftEngine<F> for FftKernel<'a, F> {
+    fn fft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        self.radix_fft(a, omega, log_n)
+    }
+
+    fn ifft(&mut self, a: &mut [F], omega: &F, log_n: u32) -> EcResult<()> {
+        FftKernel::ifft(self, a, omega, log_n)
+    }
+
+    fn coset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        FftKernel::coset_fft(self, a, omega, log_n, g)
+    }
+
+    fn icoset_fft(&mut self, a: &mut [F], omega: &F, log_n: u32, g: &F) -> EcResult<()> {
+        FftKernel::icoset_fft(self, a, omega, log_n, g)
+    }
+}
+
+/// FFTs and element-wise operations on vectors that stay in the memory of the engine.
+///
+/// With the [`FftKernel`] the vectors stay on the GPU, so that several steps can be chained
+/// without transferring data between the host and the GPU. The [`Worker`] keeps them in host
+/// memory, so that the same code also runs without a GPU.
+pub trait FftBufferEngine<F: PrimeField> {
+    /// A vector of field elements in the memory of the engine.
+    type Buffer;
+
+    /// Copies `a` into a new buffer.
+    fn upload(&mut self, a: &[F]) -> EcResult<Self::Buffer>;
+
+    /// Copies the elements of `buffer` back into host memory.
+    fn download(&mut self, buffer: &Self::Buffer) -> EcResult<Vec<F>>;
+
+    /// Evaluates the polynomial given by its coefficients in `buffer` at the powers of `omega`.
+    fn fft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()>;
+
+    /// Interpolates the evaluations in `buffer` at the powers of `omega` into coefficients.
+    fn ifft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()>;
+
+    /// Evaluates the polynomial given by its coefficients in `buffer` at `g` times the powers of
+    /// `omega`.
+    fn coset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()>;
+
+    /// Interpolates the evaluations in `buffer` at `g` times the powers of `omega` into
+    /// coefficients.
+    fn icoset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()>;
+
+    /// Adds the elements of `b` to the elements of `a` at the same index.
+    fn add_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()>;
+
+    /// Subtracts the elements of `b` from the elements of `a` at the same index.
+    fn sub_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()>;
+
+    /// Multiplies the elements of `a` by the elements of `b` at the same index.
+    fn mul_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()>;
+
+    /// Multiplies all elements of `buffer` by `c`.
+    fn scale_buffer(&mut self, buffer: &mut Self::Buffer, c: &F) -> EcResult<()>;
+
+    /// Inverts all elements of `buffer`, zeros stay zero.
+    fn batch_inverse_buffer(&mut self, buffer: &mut Self::Buffer) -> EcResult<()>;
+
+    /// Returns a new buffer with the powers `[x^0, x^1, ..., x^(n-1)]`.
+    fn powers_buffer(&mut self, x: &F, n: usize) -> EcResult<Self::Buffer>;
+}
+
+impl<F: PrimeField> FftBufferEngine<F> for Worker {
+    type Buffer = Vec<F>;
+
+    fn upload(&mut self, a: &[F]) -> EcResult<Self::Buffer> {
+        Ok(a.to_vec())
+    }
+
+    fn download(&mut self, buffer: &Self::Buffer) -> EcResult<Vec<F>> {
+        Ok(buffer.clone())
+    }
+
+    fn fft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()> {
+        FftEngine::fft(self, buffer, omega, log_n)
+    }
+
+    fn ifft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()> {
+        FftEngine::ifft(self, buffer, omega, log_n)
+    }
+
+    fn coset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        FftEngine::coset_fft(self, buffer, omega, log_n, g)
+    }
+
+    fn icoset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        FftEngine::icoset_fft(self, buffer, omega, log_n, g)
+    }
+
+    fn add_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        vector_cpu::add_assign(a, self, b);
+        Ok(())
+    }
+
+    fn sub_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        vector_cpu::sub_assign(a, self, b);
+        Ok(())
+    }
+
+    fn mul_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        vector_cpu::mul_assign(a, self, b);
+        Ok(())
+    }
+
+    fn scale_buffer(&mut self, buffer: &mut Self::Buffer, c: &F) -> EcResult<()> {
+        fft_cpu::scale(buffer, self, c);
+        Ok(())
+    }
+
+    fn batch_inverse_buffer(&mut self, buffer: &mut Self::Buffer) -> EcResult<()> {
+        vector_cpu::batch_inverse(buffer, self);
+        Ok(())
+    }
+
+    fn powers_buffer(&mut self, x: &F, n: usize) -> EcResult<Self::Buffer> {
+        let mut powers = vec![F::zero(); n];
+        vector_cpu::powers(&mut powers, self, x);
+        Ok(powers)
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+impl<'a, F: PrimeField + GpuName> FftBufferEngine<F> for FftKernel<'a, F> {
+    type Buffer = FftBuffer<F>;
+
+    fn upload(&mut self, a: &[F]) -> EcResult<Self::Buffer> {
+        FftKernel::upload(self, a)
+    }
+
+    fn download(&mut self, buffer: &Self::Buffer) -> EcResult<Vec<F>> {
+        let mut output = vec![F::zero(); buffer.len()];
+        FftKernel::download(self, buffer, &mut output)?;
+        Ok(output)
+    }
+
+    fn fft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()> {
+        self.radix_fft_buffer(buffer, omega, log_n)
+    }
+
+    fn ifft_buffer(&mut self, buffer: &mut Self::Buffer, omega: &F, log_n: u32) -> EcResult<()> {
+        FftKernel::ifft_buffer(self, buffer, omega, log_n)
+    }
+
+    fn coset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        FftKernel::coset_fft_buffer(self, buffer, omega, log_n, g)
+    }
+
+    fn icoset_fft_buffer(
+        &mut self,
+        buffer: &mut Self::Buffer,
+        omega: &F,
+        log_n: u32,
+        g: &F,
+    ) -> EcResult<()> {
+        FftKernel::icoset_fft_buffer(self, buffer, omega, log_n, g)
+    }
+
+    fn add_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        FftKernel::add_assign_buffer(self, a, b)
+    }
+
+    fn sub_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        FftKernel::sub_assign_buffer(self, a, b)
+    }
+
+    fn mul_assign_buffer(&mut self, a: &mut Self::Buffer, b: &Self::Buffer) -> EcResult<()> {
+        FftKernel::mul_assign_buffer(self, a, b)
+    }
+
+    fn scale_buffer(&mut self, buffer: &mut Self::Buffer, c: &F) -> EcResult<()> {
+        FftKernel::scale_buffer(self, buffer, c)
+    }
+
+    fn batch_inverse_buffer(&mut self, buffer: &mut Self::Buffer) -> EcResult<()> {
+        FftKernel::batch_inverse_buffer(self, buffer)
+    }
+
+    fn powers_buffer(&mut self, x: &F, n: usize) -> EcResult<Self::Buffer> {
+        FftKernel::powers_buffer(self, x, n)
+    }
+}
+
+/// Returns a copy of `a` that is padded with zeros to `size` elements.
+fn padded<F: PrimeField>(a: &[F], size: usize) -> Vec<F> {
+    let mut padded = Vec::with_capacity(size);
+    padded.extend_from_slice(a);
+    padded.resize(size, F::zero());
+    padded
+}
+
+/// Multiplies the polynomials given by their coefficients `a` and `b`.
+///
+/// Both are evaluated with FFTs of the `engine` over a domain that is big enough for the product,
+/// multiplied point-wise and interpolated again. All of it happens in the memory of the `engine`.
+/// The result has `a.len() + b.len() - 1` coefficients.
+pub fn multiply<F, E>(engine: &mut E, a: &[F], b: &[F]) -> EcResult<Vec<F>>
+where
+    F: PrimeField,
+    E: FftBufferEngine<F>,
+{
+    if a.is_empty() || b.is_empty() {
+        return Ok(Vec::new());
+    }
+    let product_len = a.len() + b.len() - 1;
+    let domain = EvaluationDomain::<F>::for_size(product_len)?;
+    let (omega, log_n) = (domain.omega(), domain.log_size());
+
+    let mut a = engine.upload(&padded(a, domain.size()))?;
+    let mut b = engine.upload(&padded(b, domain.size()))?;
+    engine.fft_buffer(&mut a, omega, log_n)?;
+    engine.fft_buffer(&mut b, omega, log_n)?;
+    engine.mul_assign_buffer(&mut a, &b)?;
+    engine.ifft_buffer(&mut a, omega, log_n)?;
+
+    let mut product = engine.download(&a)?;
+    product.truncate(product_len);
+    Ok(product)
+}
+
+/// Divides the evaluations `evals` over the coset of `domain` by the vanishing polynomial
+/// `Z(x) = x^n - 1` (multithreaded).
+///
+/// `n` must be a power of two that isn't larger than the size of the domain. Then `Z(x)` only
+/// takes `size / n` distinct values on the coset, as `(g * omega^i)^n = g^n * omega^(i * n)`.
+pub fn divide_by_vanishing_on_coset<F: PrimeField>(
+    evals: &mut [F],
+    worker: &Worker,
+    domain: &EvaluationDomain<F>,
+    n: usize,
+) -> EcResult<()> {
+    assert_eq!(
+        evals.len(),
+        domain.size(),
+        "The evaluations must cover the domain."
+    );
+    if !n.is_power_of_two() || n > domain.size() {
+        return Err(EcError::Simple(
+            "The vanishing polynomial must be of a subgroup of the domain.",
+        ));
+    }
+
+    // `Z(g * omega^i)` only depends on `i mod (size / n)`.
+    let num_values = domain.size() / n;
+    let g_n = domain.generator().pow_vartime(&[n as u64]);
+    let omega_n = domain.omega().pow_vartime(&[n as u64]);
+    let mut omega_i = F::one();
+    let mut values_inv = Vec::with_capacity(num_values);
+    for _ in 0..num_values {
+        let value = g_n * omega_i - F::one();
+        values_inv.push(
+            Option::<F>::from(value.invert())
+                .ok_or(EcError::Simple("The coset intersects the vanishing set."))?,
+        );
+        omega_i *= omega_n;
+    }
+
+    worker.scope(evals.len(), |scope, chunk| {
+        let values_inv = &values_inv;
+        for (i, evals) in evals.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                for (j, eval) in evals.iter_mut().enumerate() {
+                    *eval *= values_inv[(i * chunk + j) % num_values];
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Divides the polynomial given by its coefficients `a` by the vanishing polynomial
+/// `Z(x) = x^n - 1`, where `n` is a power of two.
+///
+/// The polynomial is evaluated with a coset FFT of the `engine`, where `Z(x)` has no zeros, then
+/// divided point-wise and interpolated again. `a` must be a multiple of `Z(x)`, else the result is
+/// meaningless. The result has `a.len() - n` coefficients.
+///
+/// The division is done on the host by the `worker`, as `Z(x)` takes `size / n` distinct values
+/// on the coset, which isn't an element-wise operation of the engines.
+pub fn divide_by_vanishing<F, E>(
+    engine: &mut E,
+    worker: &Worker,
+    a: &[F],
+    n: usize,
+) -> EcResult<Vec<F>>
+where
+    F: PrimeField,
+    E: FftEngine<F>,
+{
+    if a.len() <= n {
+        return Ok(Vec::new());
+    }
+    let domain = EvaluationDomain::<F>::for_size(a.len())?;
+
+    let mut a_evals = padded(a, domain.size());
+    engine.coset_fft(
+        &mut a_evals,
+        domain.omega(),
+        domain.log_size(),
+        domain.generator(),
+    )?;
+    divide_by_vanishing_on_coset(&mut a_evals, worker, &domain, n)?;
+    engine.icoset_fft(
+        &mut a_evals,
+        domain.omega(),
+        domain.log_size(),
+        domain.generator(),
+    )?;
+
+    a_evals.truncate(a.len() - n);
+    Ok(a_evals)
+}
+
+/// Calculates the coefficients of the quotient `H(x) = (A(x) * B(x) - C(x)) / Z(x)`, where `Z(x)`
+/// is the vanishing polynomial of the `domain`.
+///
+/// `A`, `B` and `C` are given by their evaluations `a`, `b` and `c` over the domain, hence
+/// `A(x) * B(x) - C(x)` must vanish on the domain, else the result is meaningless. They are
+/// interpolated, evaluated over the coset of the domain, combined and divided there. All of it
+/// happens in the memory of the `engine`, only `H` is transferred back. `H` has a degree of at most
+/// `n - 2`, so the result has `n - 1` coefficients.
+pub fn vanishing_quotient<F, E>(
+    engine: &mut E,
+    domain: &EvaluationDomain<F>,
+    a: &[F],
+    b: &[F],
+    c: &[F],
+) -> EcResult<Vec<F>>
+where
+    F: PrimeField,
+    E: FftBufferEngine<F>,
+{
+    let (omega, log_n, g) = (domain.omega(), domain.log_size(), domain.generator());
+    for evals in [a, b, c] {
+        assert_eq!(
+            evals.len(),
+            domain.size(),
+            "The evaluations must cover the domain."
+        );
+    }
+    // `Z(x)` has the same value `g^n - 1` on the whole coset.
+    let vanishing_inv = Option::<F>::from(domain.evaluate_vanishing(g).invert())
+        .ok_or(EcError::Simple("The coset intersects the vanishing set."))?;
+
+    let mut a = engine.upload(a)?;
+    let mut b = engine.upload(b)?;
+    let mut c = engine.upload(c)?;
+    for buffer in [&mut a, &mut b, &mut c] {
+        engine.ifft_buffer(buffer, omega, log_n)?;
+        engine.coset_fft_buffer(buffer, omega, log_n, g)?;
+    }
+    engine.mul_assign_buffer(&mut a, &b)?;
+    engine.sub_assign_buffer(&mut a, &c)?;
+    engine.scale_buffer(&mut a, &vanishing_inv)?;
+    engine.icoset_fft_buffer(&mut a, omega, log_n, g)?;
+
+    let mut h = engine.download(&a)?;
+    h.truncate(domain.size() - 1);
+    Ok(h)
+}
+
+/// Evaluates the polynomial given by its coefficients `a` at `x` (multithreaded).
+///
+/// Each thread evaluates a chunk of the coefficients with Horner's rule, the results are shifted
+/// by the power of `x` of the first coefficient of the chunk.
+pub fn evaluate<F: PrimeField>(a: &[F], worker: &Worker, x: &F) -> F {
+    let chunk = if a.len() < worker.num_threads() {
+        a.len().max(1)
+    } else {
+        a.len() / worker.num_threads()
+    };
+    let mut results = vec![F::zero(); (a.len() + chunk - 1) / chunk];
+
+    worker.scope(a.len(), |scope, _| {
+        for (i, (a, result)) in a.chunks(chunk).zip(results.iter_mut()).enumerate() {
+            scope.spawn(move |_| {
+                let horner = a.iter().rev().fold(F::zero(), |acc, coeff| acc * x + coeff);
+                *result = horner * x.pow_vartime(&[(i * chunk) as u64]);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .fold(F::zero(), |sum, result| sum + result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    /// Multiplies the polynomials with the schoolbook algorithm.
+    fn naive_multiply(a: &[Fr], b: &[Fr]) -> Vec<Fr> {
+        let mut product = vec![Fr::zero(); a.len() + b.len() - 1];
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                product[i + j] += *a * b;
+            }
+        }
+        product
+    }
+
+    #[test]
+    fn test_multiply() {
+        let rng = &mut rand::thread_rng();
+        let mut engine = Worker::new();
+
+        for (a_len, b_len) in [(1, 1), (1, 7), (5, 12), (64, 64), (100, 29)] {
+            let a = (0..a_len)
+                .map(|_| Fr::random(&mut *rng))
+                .collect::<Vec<_>>();
+            let b = (0..b_len)
+                .map(|_| Fr::random(&mut *rng))
+                .collect::<Vec<_>>();
+            let product = multiply(&mut engine, &a, &b).unwrap();
+            assert_eq!(product, naive_multiply(&a, &b));
+        }
+        assert!(multiply(&mut engine, &[], &[Fr::one()]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_divide_by_vanishing() {
+        let rng = &mut rand::thread_rng();
+        let mut engine = Worker::new();
+        let worker = Worker::new();
+
+        for (h_len, n) in [(1, 1), (3, 4), (16, 16), (50, 8), (7, 64)] {
+            let h = (0..h_len)
+                .map(|_| Fr::random(&mut *rng))
+                .collect::<Vec<_>>();
+            // `Z(x) = x^n - 1`
+            let mut z = vec![Fr::zero(); n + 1];
+            z[0] = -Fr::one();
+            z[n] = Fr::one();
+            let a = naive_multiply(&h, &z);
+
+            let quotient = divide_by_vanishing(&mut engine, &worker, &a, n).unwrap();
+            assert_eq!(quotient, h);
+        }
+    }
+
+    #[test]
+    fn test_vanishing_quotient() {
+        let rng = &mut rand::thread_rng();
+        let mut engine = Worker::new();
+        let worker = Worker::new();
+
+        for log_n in 1..8 {
+            let domain = EvaluationDomain::<Fr>::new(log_n).unwrap();
+            let n = domain.size();
+            let mut a = (0..n).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let mut b = (0..n).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let c = a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| *a * b)
+                .collect::<Vec<_>>();
+
+            let h = vanishing_quotient(&mut engine, &domain, &a, &b, &c).unwrap();
+            assert_eq!(h.len(), n - 1);
+
+            // `A(x) * B(x) - C(x) = H(x) * Z(x)`
+            domain.ifft(&mut a, &worker);
+            domain.ifft(&mut b, &worker);
+            let mut c_coeffs = c;
+            domain.ifft(&mut c_coeffs, &worker);
+            let mut lhs = naive_multiply(&a, &b);
+            for (lhs, c) in lhs.iter_mut().zip(c_coeffs.iter()) {
+                *lhs -= c;
+            }
+            let mut z = vec![Fr::zero(); n + 1];
+            z[0] = -Fr::one();
+            z[n] = Fr::one();
+            let mut rhs = naive_multiply(&h, &z);
+            rhs.resize(lhs.len(), Fr::zero());
+            assert_eq!(lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let rng = &mut rand::thread_rng();
+        let worker = Worker::new();
+
+        for len in [0, 1, 2, 17, 1000] {
+            let a = (0..len).map(|_| Fr::random(&mut *rng)).collect::<Vec<_>>();
+            let x = Fr::random(&mut *rng);
+            let expected = a
+                .iter()
+                .rev()
+                .fold(Fr::zero(), |acc, coeff| acc * x + *coeff);
+            assert_eq!(evaluate(&a, &worker, &x), expected);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use ff::PrimeField;
+
+/// A scalar that can be split into windows of bits, as it's done by the multiexp.
+///
+/// The bits are numbered in little-endian order, bit `0` is the least significant one. It's
+/// implemented for the byte representations of prime fields (`[u8; N]`, which are assumed to be
+/// little-endian), for `u64`, `u128` and for 256-bit integers as little-endian `[u64; 4]` limbs.
+pub trait WindowScalar: Copy + Send + Sync + 'static {
+    /// The number of bits of the scalar type. All bits at higher positions are zero.
+    fn num_bits() -> u32;
+
+    /// The number of bits of the scalar, when it's an exponent of a group with the scalar field
+    /// `F`.
+    ///
+    /// It's [`WindowScalar::num_bits`], except for the byte representation of `F`, which is
+    /// canonical and hence has `F::NUM_BITS` bits and not all bits of its bytes.
+    fn exponent_bits<F: PrimeField>() -> u32 {
+        Self::num_bits()
+    }
+
+    /// Returns `count` bits, starting at bit `skip`, as the least significant bits of the result.
+    ///
+    /// `count` must not be larger than 64. Bits beyond [`WindowScalar::num_bits`] are zero.
+    fn get_bits(&self, skip: u32, count: u32) -> u64;
+
+    /// Returns whether the scalar is zero.
+    fn is_zero(&self) -> bool;
+
+    /// Returns whether the scalar is one.
+    fn is_one(&self) -> bool;
+
+    /// Returns the scalars as one slice of little-endian bytes, if that is their memory layout.
+    ///
+    /// The multiexp kernels use it to upload such scalars without converting them first.
+    fn as_le_bytes(scalars: &[Self]) -> Option<&[u8]> {
+        let _ = scalars;
+        None
+    }
+}
+
+/// Returns a mask for the `count` least significant bits.
+fn mask(count: u32) -> u64 {
+    debug_assert!(count <= 64, "At most 64 bits can be returned.");
+    if count == 64 {
+        u64::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
+
+/// Returns `count` bits starting at bit `skip` of little-endian limbs.
+fn get_limb_bits(limbs: &[u64], skip: u32, count: u32) -> u64 {
+    let index = (skip / 64) as usize;
+    let shift = skip % 64;
+    let low = limbs.get(index).copied().unwrap_or(0);
+    let high = limbs.get(index + 1).copied().unwrap_or(0);
+    let bits = ((u128::from(high) << 64 | u128::from(low)) >> shift) as u64;
+    bits & mask(count)
+}
+
+impl<const N: usize> WindowScalar for [u8; N] {
+    fn num_bits() -> u32 {
+        8 * N as u32
+    }
+
+    fn exponent_bits<F: PrimeField>() -> u32 {
+        if F::Repr::default().as_ref().len() == N {
+            F::NUM_BITS
+        } else {
+            Self::num_bits()
+        }
+    }
+
+    fn get_bits(&self, skip: u32, count: u32) -> u64 {
+        // The requested bits span at most 9 bytes.
+        let index = (skip / 8) as usize;
+        let bits = self
+            .iter()
+            .skip(index)
+            .take(9)
+            .enumerate()
+            .fold(0u128, |bits, (i, byte)| bits | u128::from(*byte) << (8 * i));
+        ((bits >> (skip % 8)) as u64) & mask(count)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.iter().all(|byte| *byte == 0)
+    }
+
+    fn is_one(&self) -> bool {
+        match self.split_first() {
+            Some((first, rest)) => *first == 1 && rest.iter().all(|byte| *byte == 0),
+            None => false,
+        }
+    }
+
+    fn as_le_bytes(scalars: &[Self]) -> Option<&[u8]> {
+        // It is safe as `[u8; N]` has no padding, hence the slice is `N * len` contiguous bytes.
+        Some(unsafe {
+            std::slice::from_raw_parts(scalars.as_ptr() as *const u8, N * scalars.len())
+        })
+    }
+}
+
+impl WindowScalar for u64 {
+    fn num_bits() -> u32 {
+        64
+    }
+
+    fn get_bits(&self, skip: u32, count: u32) -> u64 {
+        get_limb_bits(&[*self], skip, count)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn is_one(&self) -> bool {
+        *self == 1
+    }
+}
+
+impl WindowScalar for u128 {
+    fn num_bits() -> u32 {
+        128
+    }
+
+    fn get_bits(&self, skip: u32, count: u32) -> u64 {
+        get_limb_bits(&[*self as u64, (*self >> 64) as u64], skip, count)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn is_one(&self) -> bool {
+        *self == 1
+    }
+}
+
+impl WindowScalar for [u64; 4] {
+    fn num_bits() -> u32 {
+        256
+    }
+
+    fn get_bits(&self, skip: u32, count: u32) -> u64 {
+        get_limb_bits(self, skip, count)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == [0; 4]
+    }
+
+    fn is_one(&self) -> bool {
+        *self == [1, 0, 0, 0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cmp;
+
+    use blstrs::Scalar as Fr;
+    use rand::Rng;
+
+    /// Returns a mask for the bits that are left of a scalar of `num_bits` after skipping `skip`.
+    fn remaining(num_bits: u32, skip: u32) -> u64 {
+        mask(cmp::min(num_bits - skip, 64))
+    }
+
+    #[test]
+    fn test_get_bits_consistency() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let limbs: [u64; 4] = rng.gen();
+            let mut bytes = [0u8; 32];
+            for (chunk, limb) in bytes.chunks_mut(8).zip(limbs.iter()) {
+                chunk.copy_from_slice(&limb.to_le_bytes());
+            }
+            let wide = u128::from(limbs[0]) | u128::from(limbs[1]) << 64;
+
+            for skip in 0..270 {
+                for count in [1, 7, 8, 13, 63, 64] {
+                    let expected = limbs.get_bits(skip, count);
+                    assert_eq!(bytes.get_bits(skip, count), expected);
+                    if skip < 128 {
+                        assert_eq!(wide.get_bits(skip, count), expected & remaining(128, skip));
+                    }
+                    if skip < 64 {
+                        assert_eq!(
+                            limbs[0].get_bits(skip, count),
+                            expected & remaining(64, skip)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_bits() {
+        let limbs = [0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210, 0, u64::MAX];
+        assert_eq!(limbs.get_bits(0, 4), 0xf);
+        assert_eq!(limbs.get_bits(4, 8), 0xde);
+        assert_eq!(limbs.get_bits(60, 8), 0x00);
+        assert_eq!(limbs.get_bits(56, 16), 0x1001);
+        assert_eq!(limbs.get_bits(64, 64), 0xfedc_ba98_7654_3210);
+        assert_eq!(limbs.get_bits(250, 10), 0x3f);
+        assert_eq!(limbs.get_bits(256, 64), 0);
+        assert_eq!(0xffu128.get_bits(4, 64), 0xf);
+        assert_eq!(u64::MAX.get_bits(63, 64), 1);
+    }
+
+    #[test]
+    fn test_exponent_bits() {
+        assert_eq!(<[u8; 32]>::num_bits(), 256);
+        assert_eq!(<[u8; 32]>::exponent_bits::<Fr>(), Fr::NUM_BITS);
+        assert_eq!(<[u8; 16]>::exponent_bits::<Fr>(), 128);
+        assert_eq!(<[u64; 4]>::exponent_bits::<Fr>(), 256);
+        assert_eq!(u64::exponent_bits::<Fr>(), 64);
+    }
+
+    #[test]
+    fn test_zero_and_one() {
+        assert!(0u64.is_zero() && !0u64.is_one());
+        assert!(1u128.is_one() && !1u128.is_zero());
+        assert!([0u64; 4].is_zero() && [1u64, 0, 0, 0].is_one());
+        assert!(![0u64, 1, 0, 0].is_one());
+        let mut bytes = [0u8; 32];
+        assert!(bytes.is_zero());
+        bytes[0] = 1;
+        assert!(bytes.is_one());
+        bytes[31] = 1;
+        assert!(!bytes.is_one() && !bytes.is_zero());
+    }
+
+    #[test]
+    fn test_as_le_bytes() {
+        let scalars = [[1u8, 2, 3], [4, 5, 6]];
+        assert_eq!(
+            <[u8; 3]>::as_le_bytes(&scalars),
+            Some(&[1u8, 2, 3, 4, 5, 6][..])
+        );
+        assert_eq!(<[u8; 3]>::as_le_bytes(&[]), Some(&[][..]));
+        assert_eq!(u64::as_le_bytes(&[1, 2]), None);
+    }
+}
@@ -1,27 +1,80 @@
-use std::collections::HashSet;
+#[cfg(any(feature = "opencl", feature = "cuda", feature = "host-c"))]
+use std::env;
 use std::fmt::{self, Write};
-use std::hash::{Hash, Hasher};
+#[cfg(any(feature = "opencl", feature = "cuda"))]
+use std::fs;
 use std::marker::PhantomData;
 use std::mem;
-#[cfg(any(feature = "opencl", feature = "cuda"))]
+#[cfg(feature = "host-c")]
+use std::path::Path;
+#[cfg(any(feature = "opencl", feature = "cuda", feature = "host-c"))]
 use std::path::PathBuf;
-#[cfg(any(feature = "opencl", feature = "cuda"))]
-use std::{env, fs};
 
-use ec_gpu::{GpuField, GpuName};
+use ec_gpu::{
+    GpuCubicExtension, GpuCurve, GpuEdwardsCurve, GpuField, GpuName, GpuQuadraticExtension,
+};
 use group::prime::PrimeCurveAffine;
 
 static COMMON_SRC: &str = include_str!("cl/common.cl");
 static FIELD_SRC: &str = include_str!("cl/field.cl");
 static FIELD2_SRC: &str = include_str!("cl/field2.cl");
+static FIELD6_SRC: &str = include_str!("cl/field6.cl");
+static FIELD12_SRC: &str = include_str!("cl/field12.cl");
 static EC_SRC: &str = include_str!("cl/ec.cl");
+static EDWARDS_SRC: &str = include_str!("cl/edwards.cl");
 static FFT_SRC: &str = include_str!("cl/fft.cl");
 static MULTIEXP_SRC: &str = include_str!("cl/multiexp.cl");
+static DECOMPRESSION_SRC: &str = include_str!("cl/decompression.cl");
+static NORMALIZE_SRC: &str = include_str!("cl/normalize.cl");
+static VECTOR_SRC: &str = include_str!("cl/vector.cl");
 
-#[derive(Clone, Copy)]
-enum Limb32Or64 {
-    Limb32,
-    Limb64,
+/// The limbs the source is generated with.
+///
+/// It's the object-safe counterpart of [`Limb`], so that the items, which are stored as trait
+/// objects, can generate their source for any limb type. Integers and field elements are given as
+/// little-endian 32-bit limbs, like the constants of [`GpuField`].
+trait LimbSource {
+    /// Generates the constants and type definitions of a prime field, see [`params`].
+    fn params(&self, modulus: &[u32], one: &[u32], r2: &[u32]) -> String;
+    /// Generates the PTX implementation of the addition and subtraction of a prime field, see
+    /// [`field_add_sub_nvidia`].
+    fn field_add_sub_nvidia(&self, modulus: &[u32]) -> String;
+    /// Returns the limbs of an integer.
+    fn limbs(&self, value: &[u32]) -> Vec<String>;
+    /// Returns the limbs of a field element that is in the Montgomery form of [`GpuField`], see
+    /// [`montgomery_limbs`].
+    fn montgomery_limbs(&self, value: &[u32], modulus: &[u32]) -> Vec<String>;
+}
+
+/// The [`LimbSource`] of the limb type `L`.
+struct Limbs<L: Limb>(PhantomData<L>);
+
+impl<L: Limb> LimbSource for Limbs<L> {
+    fn params(&self, modulus: &[u32], one: &[u32], r2: &[u32]) -> String {
+        params::<L>(modulus, one, r2)
+    }
+
+    fn field_add_sub_nvidia(&self, modulus: &[u32]) -> String {
+        // The carries of unsaturated limbs are handled without PTX.
+        if is_saturated::<L>() {
+            field_add_sub_nvidia::<L>(L::from_u32_limbs(modulus).len()).expect("preallocated")
+        } else {
+            String::new()
+        }
+    }
+
+    fn limbs(&self, value: &[u32]) -> Vec<String> {
+        to_strings(L::from_u32_limbs(value))
+    }
+
+    fn montgomery_limbs(&self, value: &[u32], modulus: &[u32]) -> Vec<String> {
+        to_strings(montgomery_limbs::<L>(value, modulus))
+    }
+}
+
+/// Formats the values of the limbs.
+fn to_strings<L: Limb>(limbs: Vec<L>) -> Vec<String> {
+    limbs.iter().map(|limb| limb.value().to_string()).collect()
 }
 
 /// This trait is used to uniquely identify items by some identifier (`name`) and to return the GPU
@@ -30,20 +83,10 @@ trait NameAndSource {
     /// The name to identify the item.
     fn name(&self) -> String;
     /// The GPU source code that is generated.
-    fn source(&self, limb: Limb32Or64) -> String;
-}
-
-impl PartialEq for dyn NameAndSource {
-    fn eq(&self, other: &Self) -> bool {
-        self.name() == other.name()
-    }
-}
-
-impl Eq for dyn NameAndSource {}
-
-impl Hash for dyn NameAndSource {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name().hash(state)
+    fn source(&self, limb: &dyn LimbSource) -> String;
+    /// The names of the items that need to be defined before this one.
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
     }
 }
 
@@ -55,7 +98,7 @@ impl fmt::Debug for dyn NameAndSource {
             f.debug_map()
                 .entries(vec![
                     ("name", self.name()),
-                    ("source", self.source(Limb32Or64::Limb32)),
+                    ("source", self.source(&Limbs::<Limb32>(PhantomData))),
                 ])
                 .finish()
         } else {
@@ -96,21 +139,93 @@ impl<F: GpuField> Default for Field<F> {
     }
 }
 
-fn field_source<F: GpuField>(limb: Limb32Or64) -> String {
-    match limb {
-        Limb32Or64::Limb32 => [
-            params::<F, Limb32>(),
-            field_add_sub_nvidia::<F, Limb32>().expect("preallocated"),
-            String::from(FIELD_SRC),
-        ]
-        .join("\n"),
-        Limb32Or64::Limb64 => [
-            params::<F, Limb64>(),
-            field_add_sub_nvidia::<F, Limb64>().expect("preallocated"),
-            String::from(FIELD_SRC),
-        ]
-        .join("\n"),
+fn field_source<F: GpuField>(limb: &dyn LimbSource) -> String {
+    let modulus = F::modulus();
+    [
+        limb.params(&modulus, &F::one(), &F::r2()),
+        exponent_params::<F>(limb),
+        limb.field_add_sub_nvidia(&modulus),
+        String::from(FIELD_SRC),
+    ]
+    .join("\n")
+}
+
+/// Subtracts `b` from `a`, both given as little-endian 32-bit limbs. `a` must not be smaller than
+/// `b`.
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut borrow = 0;
+    a.iter()
+        .enumerate()
+        .map(|(i, limb)| {
+            let (diff, borrow_a) = limb.overflowing_sub(*b.get(i).unwrap_or(&0));
+            let (diff, borrow_b) = diff.overflowing_sub(borrow);
+            borrow = u32::from(borrow_a || borrow_b);
+            diff
+        })
+        .collect()
+}
+
+/// Shifts little-endian 32-bit limbs to the right by `bits` bits.
+fn shr_limbs(limbs: &[u32], bits: u32) -> Vec<u32> {
+    let (skip, shift) = ((bits / 32) as usize, bits % 32);
+    (0..limbs.len())
+        .map(|i| {
+            let low = limbs.get(i + skip).copied().unwrap_or(0);
+            let high = limbs.get(i + skip + 1).copied().unwrap_or(0);
+            ((u64::from(high) << 32 | u64::from(low)) >> shift) as u32
+        })
+        .collect()
+}
+
+/// Returns the number of trailing zero bits of little-endian 32-bit limbs, which must not be zero.
+fn trailing_zeros_limbs(limbs: &[u32]) -> u32 {
+    let zero_limbs = limbs.iter().take_while(|limb| **limb == 0).count();
+    32 * zero_limbs as u32 + limbs[zero_limbs].trailing_zeros()
+}
+
+/// Generates the exponents that are used for inversion, the Legendre symbol and square roots of
+/// the prime field `F`.
+///
+/// The modulus is `p = 2^S * t + 1` with an odd `t`. Square roots are only generated if the root
+/// of unity is known, see [`GpuField::root_of_unity`].
+fn exponent_params<F: GpuField>(limb: &dyn LimbSource) -> String {
+    let p = F::modulus();
+    let p_minus_1 = sub_limbs(&p, &[1]);
+    let s = trailing_zeros_limbs(&p_minus_1);
+    let t = shr_limbs(&p_minus_1, s);
+    let mut params = vec![
+        format!(
+            "CONSTANT FIELD FIELD_P_MINUS_2 = {};",
+            field_initializer(&sub_limbs(&p, &[2]), limb)
+        ),
+        format!(
+            "CONSTANT FIELD FIELD_P_MINUS_1_OVER_2 = {};",
+            field_initializer(&shr_limbs(&p_minus_1, 1), limb)
+        ),
+    ];
+    if let Some(root_of_unity) = sqrt_root_of_unity::<F>() {
+        params.extend([
+            "#define FIELD_HAS_SQRT".to_string(),
+            format!("#define FIELD_S {}", s),
+            format!(
+                "CONSTANT FIELD FIELD_T_MINUS_1_OVER_2 = {};",
+                field_initializer(&shr_limbs(&t, 1), limb)
+            ),
+            format!(
+                "CONSTANT FIELD FIELD_ROOT_OF_UNITY = {};",
+                montgomery_initializer(&root_of_unity, &p, limb)
+            ),
+        ]);
     }
+    params.join("\n")
+}
+
+/// Returns the root of unity that is used for square roots in the prime field `F`, if it's known.
+fn sqrt_root_of_unity<F: GpuField>() -> Option<Vec<u32>> {
+    let p = F::modulus();
+    let s = trailing_zeros_limbs(&sub_limbs(&p, &[1]));
+    // If `S = 1`, then `-1` is the root of unity.
+    F::root_of_unity().or_else(|| (s == 1).then(|| sub_limbs(&p, &F::one())))
 }
 
 impl<F: GpuField> NameAndSource for Field<F> {
@@ -121,7 +236,15 @@ impl<F: GpuField> NameAndSource for Field<F> {
         }
     }
 
-    fn source(&self, limb: Limb32Or64) -> String {
+    fn dependencies(&self) -> Vec<String> {
+        match self {
+            // An extension field is defined in terms of its sub-field.
+            Self::Field(_) => F::sub_field_name().into_iter().collect(),
+            Self::SubField(_) => Vec::new(),
+        }
+    }
+
+    fn source(&self, limb: &dyn LimbSource) -> String {
         match self {
             Self::Field(_) => {
                 // If it's an extension field.
@@ -144,49 +267,355 @@ impl<F: GpuField> NameAndSource for Field<F> {
     }
 }
 
+/// Formats an integer, given as 32-bit limbs, as initializer of a field struct.
+fn field_initializer(limbs: &[u32], limb: &dyn LimbSource) -> String {
+    format!("{{ {{ {} }} }}", limb.limbs(limbs).join(", "))
+}
+
+/// Formats a prime field element, given as 32-bit limbs in the Montgomery form of [`GpuField`],
+/// as initializer of a field struct.
+fn montgomery_initializer(limbs: &[u32], modulus: &[u32], limb: &dyn LimbSource) -> String {
+    format!(
+        "{{ {{ {} }} }}",
+        limb.montgomery_limbs(limbs, modulus).join(", ")
+    )
+}
+
+/// Struct that generates the source of a cubic extension field, e.g. `Fp6` on top of `Fp2`.
+struct CubicExtension<F: GpuCubicExtension> {
+    field: PhantomData<F>,
+}
+
+impl<F: GpuCubicExtension> NameAndSource for CubicExtension<F> {
+    fn name(&self) -> String {
+        F::name()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![F::SubField::name()]
+    }
+
+    fn source(&self, limb: &dyn LimbSource) -> String {
+        let [c0, c1] = F::non_residue();
+        let modulus = F::SubField::modulus();
+        let non_residue = format!(
+            "CONSTANT FIELD2 FIELD6_NON_RESIDUE = {{ {}, {} }};",
+            montgomery_initializer(&c0, &modulus, limb),
+            montgomery_initializer(&c1, &modulus, limb)
+        );
+        [non_residue, String::from(FIELD6_SRC)]
+            .join("\n")
+            .replace("FIELD6", &F::name())
+            .replace("FIELD2", &F::SubField::name())
+    }
+}
+
+/// Struct that generates the source of a quadratic extension of a cubic extension field, e.g.
+/// `Fp12` on top of `Fp6`.
+struct QuadraticExtension<F: GpuQuadraticExtension> {
+    field: PhantomData<F>,
+}
+
+impl<F: GpuQuadraticExtension> NameAndSource for QuadraticExtension<F> {
+    fn name(&self) -> String {
+        F::name()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![F::SubField::name()]
+    }
+
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        String::from(FIELD12_SRC)
+            .replace("FIELD12", &F::name())
+            .replace("FIELD6", &F::SubField::name())
+    }
+}
+
 /// Struct that generates FFT GPU source code.
-struct Fft<F: GpuName>(PhantomData<F>);
+struct Fft<F: GpuName> {
+    field: PhantomData<F>,
+    /// Whether the radix-3 rounds for FFT sizes of `2^k * 3^m` are included.
+    radix3: bool,
+}
 
 impl<F: GpuName> NameAndSource for Fft<F> {
     fn name(&self) -> String {
         F::name()
     }
 
-    fn source(&self, _limb: Limb32Or64) -> String {
-        String::from(FFT_SRC).replace("FIELD", &F::name())
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        let define = if self.radix3 {
+            "#define FIELD_RADIX3_FFT\n"
+        } else {
+            ""
+        };
+        [define, FFT_SRC].concat().replace("FIELD", &F::name())
     }
 }
 
-/// Struct that generates multiexp GPU smource code.
-struct Multiexp<P: GpuName, F: GpuName, Exp: GpuName> {
+/// Struct that generates the GPU source code of element-wise vector operations.
+struct VectorOps<F: GpuField> {
+    field: PhantomData<F>,
+}
+
+impl<F: GpuField> NameAndSource for VectorOps<F> {
+    fn name(&self) -> String {
+        F::name()
+    }
+
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        VECTOR_SRC.replace("FIELD", &F::name())
+    }
+}
+
+/// The coefficients `a` and `b` of a curve equation, see [`GpuCurve`].
+type CurveCoefficients = (Vec<Vec<u32>>, Vec<Vec<u32>>);
+
+/// Formats an element of a prime field or of a quadratic extension field, given as its
+/// coefficients (see [`GpuCurve::a`]), as initializer of a field struct.
+fn element_initializer(
+    coefficients: &[Vec<u32>],
+    modulus: &[u32],
+    limb: &dyn LimbSource,
+) -> String {
+    match coefficients {
+        [coefficient] => montgomery_initializer(coefficient, modulus, limb),
+        _ => format!(
+            "{{ {} }}",
+            coefficients
+                .iter()
+                .map(|coefficient| montgomery_initializer(coefficient, modulus, limb))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Struct that generates the elliptic curve arithmetic GPU source code.
+struct Curve<P: GpuName, F: GpuField> {
     curve_point: PhantomData<P>,
     field: PhantomData<F>,
+    /// The coefficients of the curve equation. If they are not known, `a = 0` is assumed, which
+    /// is the case for the curves used for pairings.
+    coefficients: Option<CurveCoefficients>,
+}
+
+impl<P: GpuName, F: GpuField> NameAndSource for Curve<P, F> {
+    fn name(&self) -> String {
+        P::name()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![F::name()]
+    }
+
+    fn source(&self, limb: &dyn LimbSource) -> String {
+        let params = match &self.coefficients {
+            Some((a, b)) => {
+                let modulus = F::modulus();
+                let mut params = vec![
+                    format!(
+                        "CONSTANT FIELD POINT_A = {};",
+                        element_initializer(a, &modulus, limb)
+                    ),
+                    format!(
+                        "CONSTANT FIELD POINT_B = {};",
+                        element_initializer(b, &modulus, limb)
+                    ),
+                ];
+                if a.iter().flatten().all(|limb| *limb == 0) {
+                    params.push("#define POINT_A_IS_ZERO".to_string());
+                }
+                params.join("\n")
+            }
+            None => "#define POINT_A_IS_ZERO".to_string(),
+        };
+        [params, String::from(EC_SRC)]
+            .join("\n")
+            .replace("FIELD", &F::name())
+            .replace("POINT", &P::name())
+    }
+}
+
+/// Struct that generates the twisted Edwards curve arithmetic GPU source code.
+struct EdwardsCurve<P: GpuEdwardsCurve, F: GpuField> {
+    curve_point: PhantomData<P>,
+    field: PhantomData<F>,
+}
+
+impl<P: GpuEdwardsCurve, F: GpuField> NameAndSource for EdwardsCurve<P, F> {
+    fn name(&self) -> String {
+        P::name()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        vec![F::name()]
+    }
+
+    fn source(&self, limb: &dyn LimbSource) -> String {
+        let modulus = F::modulus();
+        let params = [
+            format!(
+                "CONSTANT FIELD POINT_A = {};",
+                element_initializer(&P::a(), &modulus, limb)
+            ),
+            format!(
+                "CONSTANT FIELD POINT_D = {};",
+                element_initializer(&P::d(), &modulus, limb)
+            ),
+        ];
+        [params.join("\n"), String::from(EDWARDS_SRC)]
+            .join("\n")
+            .replace("FIELD", &F::name())
+            .replace("POINT", &P::name())
+    }
+}
+
+/// The coordinates the buckets of the multiexp kernels of short Weierstrass curves are
+/// accumulated in.
+///
+/// It changes the representation of the results of those kernels, they are converted back into
+/// points on the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BucketCoordinates {
+    /// Jacobian coordinates `(X, Y, Z)`, where `x = X / Z^2` and `y = Y / Z^3`. The results have
+    /// the same memory layout as the projective points of `blstrs`.
+    Jacobian,
+    /// XYZZ coordinates `(X, Y, ZZ, ZZZ)`, where `x = X / ZZ` and `y = Y / ZZZ`. Adding an affine
+    /// point is cheaper than in Jacobian coordinates. The results are
+    /// [`crate::xyzz_cpu::PointXyzz`]s.
+    Xyzz,
+}
+
+impl Default for BucketCoordinates {
+    fn default() -> Self {
+        Self::Jacobian
+    }
+}
+
+/// Struct that generates multiexp GPU smource code.
+struct Multiexp<P: GpuName, Exp: GpuName> {
+    curve_point: PhantomData<P>,
     exponent: PhantomData<Exp>,
 }
 
-impl<P: GpuName, F: GpuName, Exp: GpuName> Multiexp<P, F, Exp> {
+impl<P: GpuName, Exp: GpuName> Multiexp<P, Exp> {
     pub fn new() -> Self {
         Self {
             curve_point: PhantomData::<P>,
-            field: PhantomData::<F>,
             exponent: PhantomData::<Exp>,
         }
     }
 }
 
-impl<P: GpuName, F: GpuName, Exp: GpuName> NameAndSource for Multiexp<P, F, Exp> {
+impl<P: GpuName, Exp: GpuName> NameAndSource for Multiexp<P, Exp> {
+    fn name(&self) -> String {
+        P::name()
+    }
+
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        String::from(MULTIEXP_SRC)
+            .replace("POINT", &P::name())
+            .replace("EXPONENT", &Exp::name())
+    }
+}
+
+/// Struct that generates the point decompression GPU source code.
+struct PointDecompression<P: GpuCurve, Exp: GpuField> {
+    curve_point: PhantomData<P>,
+    /// The scalar field, its modulus is the order of the subgroup.
+    exponent: PhantomData<Exp>,
+}
+
+impl<P: GpuCurve, Exp: GpuField> NameAndSource for PointDecompression<P, Exp> {
+    fn name(&self) -> String {
+        P::name()
+    }
+
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        format!(
+            "#define POINT_COMPRESSED_BYTES {}\n{}",
+            compressed_point_size::<P>(),
+            DECOMPRESSION_SRC
+        )
+        .replace("FIELD", &P::BaseField::name())
+        .replace("POINT", &P::name())
+        .replace("EXPONENT", &Exp::name())
+    }
+}
+
+/// Struct that generates the batch normalization GPU source code.
+struct BatchNormalization<P: GpuName, F: GpuName> {
+    curve_point: PhantomData<P>,
+    field: PhantomData<F>,
+}
+
+impl<P: GpuName, F: GpuName> NameAndSource for BatchNormalization<P, F> {
     fn name(&self) -> String {
         P::name()
     }
 
-    fn source(&self, _limb: Limb32Or64) -> String {
-        let ec = String::from(EC_SRC)
+    fn source(&self, _limb: &dyn LimbSource) -> String {
+        String::from(NORMALIZE_SRC)
             .replace("FIELD", &F::name())
-            .replace("POINT", &P::name());
-        let multiexp = String::from(MULTIEXP_SRC)
             .replace("POINT", &P::name())
-            .replace("EXPONENT", &Exp::name());
-        [ec, multiexp].concat()
+    }
+}
+
+/// Returns the size in bytes of a compressed point of the curve `C`, see
+/// [`SourceBuilder::add_point_decompression`].
+pub(crate) fn compressed_point_size<C: GpuCurve>() -> usize {
+    4 * C::BaseField::modulus().len()
+}
+
+/// Items of the same kind, like fields or FFTs, in the order they were added.
+///
+/// Every name is only stored once. The order is kept, so that the same configuration always
+/// generates byte-for-byte the same source. This matters as e.g. the CUDA kernel is only
+/// recompiled if the hash of its source changes.
+#[derive(Default)]
+struct Items(Vec<Box<dyn NameAndSource>>);
+
+impl Items {
+    /// Adds the item, unless there is already an item with the same name.
+    fn insert(&mut self, item: Box<dyn NameAndSource>) {
+        if !self.0.iter().any(|existing| existing.name() == item.name()) {
+            self.0.push(item);
+        }
+    }
+
+    /// Adds the item, an existing item with the same name is replaced at its position.
+    fn replace(&mut self, item: Box<dyn NameAndSource>) {
+        match self
+            .0
+            .iter()
+            .position(|existing| existing.name() == item.name())
+        {
+            Some(position) => self.0[position] = item,
+            None => self.0.push(item),
+        }
+    }
+
+    /// Returns the source of all items.
+    ///
+    /// The items are emitted in the order they were added, except that an item is always emitted
+    /// after the items it depends on.
+    fn source(&self, limb: &dyn LimbSource) -> String {
+        let mut remaining = self.0.iter().collect::<Vec<_>>();
+        let mut source = String::new();
+        while !remaining.is_empty() {
+            let position = remaining
+                .iter()
+                .position(|item| {
+                    item.dependencies().iter().all(|dependency| {
+                        !remaining.iter().any(|other| other.name() == *dependency)
+                    })
+                })
+                .expect("items must not have cyclic dependencies");
+            source.push_str(&remaining.remove(position).source(limb));
+        }
+        source
     }
 }
 
@@ -205,19 +634,30 @@ impl<P: GpuName, F: GpuName, Exp: GpuName> NameAndSource for Multiexp<P, F, Exp>
 ///     .add_multiexp::<G2Affine, Fp2>()
 ///     .build_32_bit_limbs();
 ///```
-// In the `HashSet`s the concrete types cannot be used, as each item of the set should be able to
-// have its own (different) generic type.
+// In the `Items` the concrete types cannot be used, as each item should be able to have its own
+// (different) generic type.
 // We distinguish between extension fields and other fields as sub-fields need to be defined first
 // in the source code (due to being C, where the order of declaration matters).
 pub struct SourceBuilder {
     /// The [`Field`]s that are used in this kernel.
-    fields: HashSet<Box<dyn NameAndSource>>,
-    /// The extension [`Field`]s that are used in this kernel.
-    extension_fields: HashSet<Box<dyn NameAndSource>>,
+    fields: Items,
+    /// The extension [`Field`]s that are used in this kernel. This includes the fields of a tower
+    /// of extension fields, like [`CubicExtension`]s and [`QuadraticExtension`]s.
+    extension_fields: Items,
+    /// The [`VectorOps`] that are used in this kernel.
+    vector_ops: Items,
     /// The [`Fft`]s that are used in this kernel.
-    ffts: HashSet<Box<dyn NameAndSource>>,
+    ffts: Items,
+    /// The [`Curve`]s and [`EdwardsCurve`]s that are used in this kernel.
+    curves: Items,
     /// The [`Multiexp`]s that are used in this kernel.
-    multiexps: HashSet<Box<dyn NameAndSource>>,
+    multiexps: Items,
+    /// The [`PointDecompression`]s that are used in this kernel.
+    point_decompressions: Items,
+    /// The [`BatchNormalization`]s that are used in this kernel.
+    batch_normalizations: Items,
+    /// The coordinates the buckets of the multiexps are accumulated in.
+    bucket_coordinates: BucketCoordinates,
     /// Additional source that is appended at the end of the generated source.
     extra_sources: Vec<String>,
 }
@@ -226,10 +666,15 @@ impl SourceBuilder {
     /// Create a new configuration to generation a GPU kernel.
     pub fn new() -> Self {
         Self {
-            fields: HashSet::new(),
-            extension_fields: HashSet::new(),
-            ffts: HashSet::new(),
-            multiexps: HashSet::new(),
+            fields: Items::default(),
+            extension_fields: Items::default(),
+            vector_ops: Items::default(),
+            ffts: Items::default(),
+            curves: Items::default(),
+            multiexps: Items::default(),
+            point_decompressions: Items::default(),
+            batch_normalizations: Items::default(),
+            bucket_coordinates: BucketCoordinates::default(),
             extra_sources: Vec::new(),
         }
     }
@@ -253,21 +698,107 @@ impl SourceBuilder {
         self
     }
 
+    /// Add a cubic extension field, e.g. `Fp6`, to the configuration.
+    ///
+    /// Its quadratic sub-field, e.g. `Fp2`, is added as well.
+    pub fn add_cubic_extension<F>(self) -> Self
+    where
+        F: GpuCubicExtension + 'static,
+    {
+        let mut config = self.add_field::<F::SubField>();
+        let field = CubicExtension::<F> { field: PhantomData };
+        config.extension_fields.insert(Box::new(field));
+        config
+    }
+
+    /// Add a quadratic extension of a cubic extension field, e.g. `Fp12`, to the configuration.
+    ///
+    /// All fields it is built on, e.g. `Fp6`, `Fp2` and `Fp`, are added as well.
+    pub fn add_quadratic_extension<F>(self) -> Self
+    where
+        F: GpuQuadraticExtension + 'static,
+    {
+        let mut config = self.add_cubic_extension::<F::SubField>();
+        let field = QuadraticExtension::<F> { field: PhantomData };
+        config.extension_fields.insert(Box::new(field));
+        config
+    }
+
+    /// Add kernel functions for element-wise operations on vectors of a prime field to the
+    /// configuration.
+    ///
+    /// Those are addition, subtraction, multiplication, scaling, batch inversion and the powers
+    /// of an element, see [`crate::fft::FftKernel::batch_inverse_buffer`] for example. Their CPU
+    /// counterparts are in [`crate::vector_cpu`].
+    pub fn add_vector_ops<F>(self) -> Self
+    where
+        F: GpuField + 'static,
+    {
+        let mut config = self.add_field::<F>();
+        let vector_ops = VectorOps::<F> { field: PhantomData };
+        config.vector_ops.insert(Box::new(vector_ops));
+        config
+    }
+
     /// Add an FFT kernel function to the configuration.
+    ///
+    /// It also adds the vector operations of [`SourceBuilder::add_vector_ops`], which are used for
+    /// the element-wise operations on [`crate::fft::FftBuffer`]s.
     pub fn add_fft<F>(self) -> Self
     where
         F: GpuField + 'static,
     {
-        let mut config = self.add_field::<F>();
-        let fft = Fft::<F>(PhantomData);
+        let mut config = self.add_vector_ops::<F>();
+        let fft = Fft::<F> {
+            field: PhantomData,
+            radix3: false,
+        };
         config.ffts.insert(Box::new(fft));
         config
     }
 
+    /// Add an FFT kernel function to the configuration, which also supports sizes of `2^k * 3^m`.
+    ///
+    /// Compared to [`SourceBuilder::add_fft`] it additionally contains the radix-3 rounds, which
+    /// are used by [`crate::fft::FftKernel::mixed_radix_fft`]. If both are added for the same
+    /// field, the one with the radix-3 rounds is used.
+    pub fn add_mixed_radix_fft<F>(self) -> Self
+    where
+        F: GpuField + 'static,
+    {
+        let mut config = self.add_vector_ops::<F>();
+        let fft = Fft::<F> {
+            field: PhantomData,
+            radix3: true,
+        };
+        config.ffts.replace(Box::new(fft));
+        config
+    }
+
+    /// Add the arithmetic of a short Weierstrass curve to the configuration.
+    ///
+    /// Its base field is added as well. The doubling formula is picked based on the coefficient
+    /// `a` of the curve equation.
+    pub fn add_curve<C>(self) -> Self
+    where
+        C: GpuCurve + 'static,
+    {
+        let mut config = self.add_field::<C::BaseField>();
+        let curve = Curve::<C, C::BaseField> {
+            curve_point: PhantomData,
+            field: PhantomData,
+            coefficients: Some((C::a(), C::b())),
+        };
+        // It might have been added by `add_multiexp()` before, without knowing the coefficients.
+        config.curves.replace(Box::new(curve));
+        config
+    }
+
     /// Add an Multiexp kernel function to the configuration.
     ///
     /// The field must be given explicitly as currently it cannot derived from the curve point
-    /// directly.
+    /// directly. The curve is assumed to have the coefficient `a = 0`, for other curves use
+    /// [`SourceBuilder::add_curve_multiexp`].
     pub fn add_multiexp<C, F>(self) -> Self
     where
         C: PrimeCurveAffine + GpuName,
@@ -275,11 +806,131 @@ impl SourceBuilder {
         F: GpuField + 'static,
     {
         let mut config = self.add_field::<F>().add_field::<C::Scalar>();
-        let multiexp = Multiexp::<C, F, C::Scalar>::new();
+        let curve = Curve::<C, F> {
+            curve_point: PhantomData,
+            field: PhantomData,
+            coefficients: None,
+        };
+        config.curves.insert(Box::new(curve));
+        let multiexp = Multiexp::<C, C::Scalar>::new();
         config.multiexps.insert(Box::new(multiexp));
         config
     }
 
+    /// Add an Multiexp kernel function for a curve that is described by [`GpuCurve`] to the
+    /// configuration.
+    ///
+    /// Contrary to [`SourceBuilder::add_multiexp`], this also supports curves with `a ≠ 0`.
+    pub fn add_curve_multiexp<C>(self) -> Self
+    where
+        C: PrimeCurveAffine + GpuCurve,
+        C::Scalar: GpuField,
+    {
+        let mut config = self.add_curve::<C>().add_field::<C::Scalar>();
+        let multiexp = Multiexp::<C, C::Scalar>::new();
+        config.multiexps.insert(Box::new(multiexp));
+        config
+    }
+
+    /// Add an Multiexp kernel function for a twisted Edwards curve to the configuration.
+    ///
+    /// The field must be given explicitly as currently it cannot derived from the curve point
+    /// directly. The buckets and results of the kernel are points in extended coordinates
+    /// (`X`, `Y`, `T`, `Z`), see [`crate::edwards_cpu::EdwardsExtended`].
+    pub fn add_edwards_multiexp<C, F>(self) -> Self
+    where
+        C: GpuEdwardsCurve + 'static,
+        F: GpuField + 'static,
+    {
+        let mut config = self.add_field::<F>().add_field::<C::ScalarField>();
+        let curve = EdwardsCurve::<C, F> {
+            curve_point: PhantomData,
+            field: PhantomData,
+        };
+        config.curves.insert(Box::new(curve));
+        let multiexp = Multiexp::<C, C::ScalarField>::new();
+        config.multiexps.insert(Box::new(multiexp));
+        config
+    }
+
+    /// Add a kernel function that decompresses points of a short Weierstrass curve to the
+    /// configuration.
+    ///
+    /// The points are in the compressed format of zcash, which is also used by `blstrs`: the
+    /// x-coordinate in big-endian, where the three most significant bits are the compression, the
+    /// infinity and the sort flag. The y-coordinate is recovered with a square root, the kernel
+    /// checks that the point is on the curve and optionally, that it is in the subgroup whose
+    /// order is the modulus of the scalar field `F`. See
+    /// [`crate::decompression::PointDecompressionKernel`].
+    ///
+    /// # Panics
+    ///
+    /// If the base field of the curve is an extension field, if square roots are not available in
+    /// it (see [`GpuField::root_of_unity`]) or if its modulus leaves no space for the flags.
+    pub fn add_point_decompression<C, F>(self) -> Self
+    where
+        C: GpuCurve + 'static,
+        F: GpuField + 'static,
+    {
+        assert!(
+            C::BaseField::sub_field_name().is_none(),
+            "Point decompression is only supported for curves over prime fields."
+        );
+        assert!(
+            sqrt_root_of_unity::<C::BaseField>().is_some(),
+            "Point decompression needs square roots in the base field."
+        );
+        let modulus = C::BaseField::modulus();
+        assert!(
+            modulus[modulus.len() - 1] >> 29 == 0,
+            "The modulus of the base field must leave three bits for the flags."
+        );
+        let mut config = self.add_curve::<C>().add_field::<F>();
+        let decompression = PointDecompression::<C, F> {
+            curve_point: PhantomData,
+            exponent: PhantomData,
+        };
+        config.point_decompressions.insert(Box::new(decompression));
+        config
+    }
+
+    /// Add a kernel function that converts points of a short Weierstrass curve from Jacobian into
+    /// affine coordinates to the configuration.
+    ///
+    /// The field must be given explicitly as currently it cannot derived from the curve point
+    /// directly. The points are split into segments, which are normalized with a single inversion
+    /// each. See [`crate::normalize::BatchNormalizeKernel`] and its CPU counterpart
+    /// [`crate::normalize_cpu::batch_normalize`].
+    pub fn add_batch_normalization<C, F>(self) -> Self
+    where
+        C: GpuName + 'static,
+        F: GpuField + 'static,
+    {
+        let mut config = self.add_field::<F>();
+        let curve = Curve::<C, F> {
+            curve_point: PhantomData,
+            field: PhantomData,
+            coefficients: None,
+        };
+        // The normalization doesn't depend on the coefficients, keep them if they are known.
+        config.curves.insert(Box::new(curve));
+        let normalization = BatchNormalization::<C, F> {
+            curve_point: PhantomData,
+            field: PhantomData,
+        };
+        config.batch_normalizations.insert(Box::new(normalization));
+        config
+    }
+
+    /// Set the coordinates the buckets of the multiexp kernels are accumulated in.
+    ///
+    /// It applies to all short Weierstrass curves of this configuration, twisted Edwards curves
+    /// always use extended coordinates. The default is [`BucketCoordinates::Jacobian`].
+    pub fn bucket_coordinates(mut self, coordinates: BucketCoordinates) -> Self {
+        self.bucket_coordinates = coordinates;
+        self
+    }
+
     /// Appends some given source at the end of the generated source.
     ///
     /// This is useful for cases where you use this library as building block, but have your own
@@ -294,41 +945,45 @@ impl SourceBuilder {
     ///
     /// On CUDA 32-bit limbs are recommended.
     pub fn build_32_bit_limbs(&self) -> String {
-        self.build(Limb32Or64::Limb32)
+        self.build_with_limbs::<Limb32>()
     }
 
     /// Generate the GPU kernel source code based on the current configuration with 64-bit limbs.
     ///
     /// On OpenCL 32-bit limbs are recommended.
     pub fn build_64_bit_limbs(&self) -> String {
-        self.build(Limb32Or64::Limb64)
+        self.build_with_limbs::<Limb64>()
+    }
+
+    /// Generate the GPU kernel source code based on the current configuration with limbs of type
+    /// `L`.
+    ///
+    /// Besides [`Limb32`] and [`Limb64`] this can be e.g. an [`UnsaturatedLimb`]. Field elements
+    /// with unsaturated limbs have a different memory layout and Montgomery form than the ones of
+    /// [`GpuField`]. The kernels that exchange field elements with the host, like the FFT and the
+    /// multiexp, expect saturated limbs, hence unsaturated limbs are meant for custom kernels,
+    /// see [`SourceBuilder::append_source`].
+    pub fn build_with_limbs<L: Limb>(&self) -> String {
+        self.build(&Limbs::<L>(PhantomData))
     }
 
     /// Generate the GPU kernel source code based on the current configuration.
-    fn build(&self, limb_size: Limb32Or64) -> String {
-        let fields = self
-            .fields
-            .iter()
-            .map(|field| field.source(limb_size))
-            .collect();
-        let extension_fields = self
-            .extension_fields
-            .iter()
-            .map(|field| field.source(limb_size))
-            .collect();
-        let ffts = self.ffts.iter().map(|fft| fft.source(limb_size)).collect();
-        let multiexps = self
-            .multiexps
-            .iter()
-            .map(|multiexp| multiexp.source(limb_size))
-            .collect();
+    fn build(&self, limb: &dyn LimbSource) -> String {
         let extra_sources = self.extra_sources.join("\n");
+        let bucket_coordinates = match self.bucket_coordinates {
+            BucketCoordinates::Jacobian => "",
+            BucketCoordinates::Xyzz => "#define XYZZ_BUCKETS\n",
+        };
         vec![
-            COMMON_SRC.to_string(),
-            fields,
-            extension_fields,
-            ffts,
-            multiexps,
+            format!("{}{}", bucket_coordinates, COMMON_SRC),
+            self.fields.source(limb),
+            self.extension_fields.source(limb),
+            self.vector_ops.source(limb),
+            self.ffts.source(limb),
+            self.curves.source(limb),
+            self.multiexps.source(limb),
+            self.point_decompressions.source(limb),
+            self.batch_normalizations.source(limb),
             extra_sources,
         ]
         .join("\n\n")
@@ -369,6 +1024,9 @@ pub trait Limb: Sized + Clone + Copy {
     fn calc_inv(a: Self) -> Self;
     /// Returns the limbs that represent `R ^ 2 mod P`.
     fn calculate_r2<F: GpuField>() -> Vec<Self>;
+    /// Returns the limbs of an integer that is given as little-endian 32-bit limbs. There are as
+    /// many limbs as are needed to store all the bits of the 32-bit limbs.
+    fn from_u32_limbs(limbs: &[u32]) -> Vec<Self>;
 }
 
 /// A 32-bit limb.
@@ -408,6 +1066,9 @@ impl Limb for Limb32 {
     fn calculate_r2<F: GpuField>() -> Vec<Self> {
         F::r2().into_iter().map(Self::new).collect()
     }
+    fn from_u32_limbs(limbs: &[u32]) -> Vec<Self> {
+        limbs.iter().copied().map(Self::new).collect()
+    }
 }
 
 /// A 64-bit limb.
@@ -458,6 +1119,202 @@ impl Limb for Limb64 {
             .map(|chunk| Self::new(((chunk[1] as u64) << 32) + (chunk[0] as u64)))
             .collect()
     }
+    fn from_u32_limbs(limbs: &[u32]) -> Vec<Self> {
+        limbs
+            .chunks(2)
+            .map(|chunk| {
+                let high = chunk.get(1).copied().unwrap_or(0);
+                Self::new(((high as u64) << 32) + (chunk[0] as u64))
+            })
+            .collect()
+    }
+}
+
+/// A limb of an unsaturated representation with radix `2^BITS`, it only uses the lower `BITS`
+/// bits of a 32-bit integer. `BITS` must be smaller than 32.
+///
+/// The products and carries are computed in 64-bit integers and split with shifts and masks, so
+/// that no add-with-carry instructions are needed. This is useful for OpenCL targets that lack
+/// fast carries. The Montgomery form uses `R = 2^(BITS * n)`, where `n` is the number of limbs.
+#[derive(Clone, Copy)]
+pub struct UnsaturatedLimb<const BITS: usize>(u32);
+impl<const BITS: usize> UnsaturatedLimb<BITS> {
+    /// Returns the mask of the bits that are used.
+    fn mask() -> u32 {
+        (1 << Self::bits()) - 1
+    }
+}
+impl<const BITS: usize> Limb for UnsaturatedLimb<BITS> {
+    type LimbType = u32;
+    fn zero() -> Self {
+        Self(0)
+    }
+    fn new(val: Self::LimbType) -> Self {
+        Self(val)
+    }
+    fn value(&self) -> Self::LimbType {
+        self.0
+    }
+    fn bits() -> usize {
+        assert!(
+            BITS > 0 && BITS < 32,
+            "Unsaturated limbs must have between 1 and 31 bits."
+        );
+        BITS
+    }
+    /// PTX is not used for unsaturated limbs.
+    fn ptx_info() -> (&'static str, &'static str) {
+        ("u32", "r")
+    }
+    fn opencl_type() -> &'static str {
+        "uint"
+    }
+    fn one_limbs<F: GpuField>() -> Vec<Self> {
+        montgomery_limbs(&F::one(), &F::modulus())
+    }
+    fn modulus_limbs<F: GpuField>() -> Vec<Self> {
+        Self::from_u32_limbs(&F::modulus())
+    }
+    fn calc_inv(a: Self) -> Self {
+        let mut inv = 1u32;
+        for _ in 0..Self::bits() - 1 {
+            inv = inv.wrapping_mul(inv);
+            inv = inv.wrapping_mul(a.value());
+        }
+        Self(inv.wrapping_neg() & Self::mask())
+    }
+    fn calculate_r2<F: GpuField>() -> Vec<Self> {
+        montgomery_r2_limbs(&F::r2(), &F::modulus())
+    }
+    fn from_u32_limbs(limbs: &[u32]) -> Vec<Self> {
+        let num_limbs = (32 * limbs.len() + Self::bits() - 1) / Self::bits();
+        (0..num_limbs)
+            .map(|i| Self(shr_limbs(limbs, (i * BITS) as u32)[0] & Self::mask()))
+            .collect()
+    }
+}
+
+/// A limb of an unsaturated representation with radix `2^BITS`, it only uses the lower `BITS`
+/// bits of a 64-bit integer. `BITS` must be larger than 32 and smaller than 64.
+///
+/// It's the 64-bit counterpart of [`UnsaturatedLimb`], e.g. `UnsaturatedLimb64<52>` for targets
+/// with 52-bit multiply-add instructions. The products are computed as the lower and upper 64 bits
+/// of the full product, the carries are split off with shifts and masks.
+#[derive(Clone, Copy)]
+pub struct UnsaturatedLimb64<const BITS: usize>(u64);
+impl<const BITS: usize> UnsaturatedLimb64<BITS> {
+    /// Returns the mask of the bits that are used.
+    fn mask() -> u64 {
+        (1 << Self::bits()) - 1
+    }
+}
+impl<const BITS: usize> Limb for UnsaturatedLimb64<BITS> {
+    type LimbType = u64;
+    fn zero() -> Self {
+        Self(0)
+    }
+    fn new(val: Self::LimbType) -> Self {
+        Self(val)
+    }
+    fn value(&self) -> Self::LimbType {
+        self.0
+    }
+    fn bits() -> usize {
+        assert!(
+            BITS > 32 && BITS < 64,
+            "Unsaturated 64-bit limbs must have between 33 and 63 bits."
+        );
+        BITS
+    }
+    /// PTX is not used for unsaturated limbs.
+    fn ptx_info() -> (&'static str, &'static str) {
+        ("u64", "l")
+    }
+    fn opencl_type() -> &'static str {
+        "ulong"
+    }
+    fn one_limbs<F: GpuField>() -> Vec<Self> {
+        montgomery_limbs(&F::one(), &F::modulus())
+    }
+    fn modulus_limbs<F: GpuField>() -> Vec<Self> {
+        Self::from_u32_limbs(&F::modulus())
+    }
+    fn calc_inv(a: Self) -> Self {
+        let mut inv = 1u64;
+        for _ in 0..Self::bits() - 1 {
+            inv = inv.wrapping_mul(inv);
+            inv = inv.wrapping_mul(a.value());
+        }
+        Self(inv.wrapping_neg() & Self::mask())
+    }
+    fn calculate_r2<F: GpuField>() -> Vec<Self> {
+        montgomery_r2_limbs(&F::r2(), &F::modulus())
+    }
+    fn from_u32_limbs(limbs: &[u32]) -> Vec<Self> {
+        let num_limbs = (32 * limbs.len() + Self::bits() - 1) / Self::bits();
+        (0..num_limbs)
+            .map(|i| {
+                let shifted = shr_limbs(limbs, (i * BITS) as u32);
+                let high = shifted.get(1).copied().unwrap_or(0);
+                Self(((high as u64) << 32 | shifted[0] as u64) & Self::mask())
+            })
+            .collect()
+    }
+}
+
+/// Returns whether the limbs `L` use all bits of their underlying type.
+fn is_saturated<L: Limb>() -> bool {
+    L::bits() == mem::size_of::<L::LimbType>() * 8
+}
+
+/// Returns by how many bits the Montgomery radix `R` of the limbs `L` is larger than the one of
+/// [`GpuField`], which is `2^(32 * modulus.len())`.
+fn radix_shift<L: Limb>(modulus: &[u32]) -> usize {
+    L::bits() * L::from_u32_limbs(modulus).len() - 32 * modulus.len()
+}
+
+/// Adds `b` to `a`, both given as little-endian 32-bit limbs. The sum must fit into the limbs of
+/// `a`.
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut carry = 0;
+    a.iter()
+        .enumerate()
+        .map(|(i, limb)| {
+            let sum = u64::from(*limb) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+            carry = sum >> 32;
+            sum as u32
+        })
+        .collect()
+}
+
+/// Returns `value * 2^exponent mod modulus`, all given as little-endian 32-bit limbs. `value`
+/// must be smaller than the modulus.
+fn mul_pow2_mod(value: &[u32], modulus: &[u32], exponent: usize) -> Vec<u32> {
+    // An additional limb, so that doubling cannot overflow.
+    let modulus_wide = [modulus, &[0]].concat();
+    let mut result = [value, &[0]].concat();
+    for _ in 0..exponent {
+        result = add_limbs(&result, &result);
+        if result.iter().rev().ge(modulus_wide.iter().rev()) {
+            result = sub_limbs(&result, &modulus_wide);
+        }
+    }
+    result.truncate(value.len());
+    result
+}
+
+/// Converts a field element from the Montgomery form of [`GpuField`] into the Montgomery form of
+/// the limbs `L`, both given as little-endian 32-bit limbs.
+///
+/// They only differ if `L` uses fewer bits than a multiple of 32 bits, like [`UnsaturatedLimb`].
+fn montgomery_limbs<L: Limb>(value: &[u32], modulus: &[u32]) -> Vec<L> {
+    L::from_u32_limbs(&mul_pow2_mod(value, modulus, radix_shift::<L>(modulus)))
+}
+
+/// Converts `R^2 mod P` of [`GpuField`] into the one of the limbs `L`, both given as little-endian
+/// 32-bit limbs.
+fn montgomery_r2_limbs<L: Limb>(r2: &[u32], modulus: &[u32]) -> Vec<L> {
+    L::from_u32_limbs(&mul_pow2_mod(r2, modulus, 2 * radix_shift::<L>(modulus)))
 }
 
 fn const_field<L: Limb>(name: &str, limbs: Vec<L>) -> String {
@@ -472,15 +1329,12 @@ fn const_field<L: Limb>(name: &str, limbs: Vec<L>) -> String {
     )
 }
 
-/// Generates CUDA/OpenCL constants and type definitions of prime-field `F`
-fn params<F, L>() -> String
-where
-    F: GpuField,
-    L: Limb,
-{
-    let one = L::one_limbs::<F>(); // Get Montgomery form of F::one()
-    let p = L::modulus_limbs::<F>(); // Get field modulus in non-Montgomery form
-    let r2 = L::calculate_r2::<F>();
+/// Generates CUDA/OpenCL constants and type definitions of the prime field with the given
+/// modulus, `one` and `r2` are in the Montgomery form of [`GpuField`].
+fn params<L: Limb>(modulus: &[u32], one: &[u32], r2: &[u32]) -> String {
+    let one = montgomery_limbs::<L>(one, modulus); // Get Montgomery form of one
+    let p = L::from_u32_limbs(modulus); // Get field modulus in non-Montgomery form
+    let r2 = montgomery_r2_limbs::<L>(r2, modulus);
     let limbs = one.len(); // Number of limbs
     let inv = L::calc_inv(p[0]);
     let limb_def = format!("#define FIELD_limb {}", L::opencl_type());
@@ -492,33 +1346,21 @@ where
     let zero_def = const_field("FIELD_ZERO", vec![L::zero(); limbs]);
     let inv_def = format!("#define FIELD_INV {}", inv.value());
     let typedef = "typedef struct { FIELD_limb val[FIELD_LIMBS]; } FIELD;".to_string();
-    [
-        limb_def,
-        limbs_def,
-        limb_bits_def,
-        inv_def,
-        typedef,
-        one_def,
-        p_def,
-        r2_def,
-        zero_def,
-    ]
-    .join("\n")
+    let mut params = vec![limb_def, limbs_def, limb_bits_def];
+    if !is_saturated::<L>() {
+        params.push("#define FIELD_UNSATURATED".to_string());
+    }
+    params.extend([inv_def, typedef, one_def, p_def, r2_def, zero_def]);
+    params.join("\n")
 }
 
 /// Generates PTX-Assembly implementation of FIELD_add_/FIELD_sub_
-fn field_add_sub_nvidia<F, L>() -> Result<String, std::fmt::Error>
-where
-    F: GpuField,
-    L: Limb,
-{
+fn field_add_sub_nvidia<L: Limb>(len: usize) -> Result<String, std::fmt::Error> {
     let mut result = String::new();
     let (ptx_type, ptx_reg) = L::ptx_info();
 
     writeln!(result, "#if defined(OPENCL_NVIDIA) || defined(CUDA)\n")?;
     for op in &["sub", "add"] {
-        let len = L::one_limbs::<F>().len();
-
         writeln!(
             result,
             "DEVICE FIELD FIELD_{}_nvidia(FIELD a, FIELD b) {{",
@@ -584,12 +1426,19 @@ where
 /// the source file is stored in the `_EC_GPU_OPENCL_KERNEL_SOURCE` environment variable, that will
 /// automatically be used by the `ec-gpu-gen` functionality that needs a kernel. OpenCL compiles
 /// the source at run time).
+///
+/// When the `host-c` feature is enabled it will compile the source as plain C into a shared
+/// library with the system C compiler. The path to the library is stored in the
+/// `_EC_GPU_HOST_C_KERNEL_LIBRARY` environment variable, which is used by the
+/// [`host_program!`](crate::host_program) macro.
 #[allow(unused_variables)]
 pub fn generate(source_builder: &SourceBuilder) {
     #[cfg(feature = "cuda")]
     generate_cuda(source_builder);
     #[cfg(feature = "opencl")]
     generate_opencl(source_builder);
+    #[cfg(feature = "host-c")]
+    generate_host_c(source_builder);
 }
 
 #[cfg(feature = "cuda")]
@@ -699,16 +1548,37 @@ fn generate_opencl(source_builder: &SourceBuilder) -> PathBuf {
     source_path
 }
 
-#[cfg(all(test, any(feature = "opencl", feature = "cuda")))]
+#[cfg(feature = "host-c")]
+fn generate_host_c(source_builder: &SourceBuilder) -> PathBuf {
+    // 64-bit limbs are the native word size of the CPUs the kernels run on.
+    let kernel_source = source_builder.build_64_bit_limbs();
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR was not set.");
+
+    let library_path = crate::host::compile(&kernel_source, Path::new(&out_dir))
+        .unwrap_or_else(|error| panic!("Cannot build the host C kernels: {}", error));
+
+    println!(
+        "cargo:rustc-env=_EC_GPU_HOST_C_KERNEL_LIBRARY={}",
+        library_path.to_str().unwrap()
+    );
+
+    library_path
+}
+
+#[cfg(all(test, any(feature = "opencl", feature = "cuda", feature = "host-c")))]
 mod tests {
     use super::*;
 
+    #[cfg(any(feature = "opencl", feature = "cuda"))]
     use std::sync::Mutex;
 
+    #[cfg(feature = "host-c")]
+    use crate::host;
     #[cfg(feature = "cuda")]
     use rust_gpu_tools::cuda;
     #[cfg(feature = "opencl")]
     use rust_gpu_tools::opencl;
+    #[cfg(any(feature = "opencl", feature = "cuda"))]
     use rust_gpu_tools::{program_closures, Device, GPUError, Program};
 
     use blstrs::Scalar;
@@ -742,8 +1612,10 @@ mod tests {
     }
 
     /// The `run` call needs to return a result, use this struct as placeholder.
+    #[cfg(any(feature = "opencl", feature = "cuda"))]
     #[derive(Debug)]
     struct NoError;
+    #[cfg(any(feature = "opencl", feature = "cuda"))]
     impl From<GPUError> for NoError {
         fn from(_error: GPUError) -> Self {
             Self
@@ -789,56 +1661,98 @@ mod tests {
         };
     }
 
-    fn call_kernel(name: &str, scalars: &[GpuScalar], uints: &[u32]) -> Scalar {
-        let closures = program_closures!(|program, _args| -> Result<Scalar, NoError> {
-            let mut cpu_buffer = vec![GpuScalar::default()];
-            let buffer = program.create_buffer_from_slice(&cpu_buffer).unwrap();
+    #[cfg(feature = "host-c")]
+    lazy_static! {
+        static ref HOST_C_PROGRAMS: (host::Program, host::Program) = {
+            let program_32 = host::Program::from_source(&test_source().build_32_bit_limbs());
+            let program_64 = host::Program::from_source(&test_source().build_64_bit_limbs());
+            (program_32.unwrap(), program_64.unwrap())
+        };
+    }
 
-            let mut kernel = program.create_kernel(name, 1, 64).unwrap();
-            for scalar in scalars {
-                kernel = kernel.arg(scalar);
-            }
-            for uint in uints {
-                kernel = kernel.arg(uint);
-            }
-            kernel.arg(&buffer).run().unwrap();
+    #[cfg(feature = "host-c")]
+    fn call_host_c_kernel(
+        program: &host::Program,
+        name: &str,
+        scalars: &[GpuScalar],
+        uints: &[u32],
+    ) -> Scalar {
+        let buffer = host::Buffer::from_slice(&[GpuScalar::default()]);
+        let mut kernel = program.create_kernel(name, 1, 64).unwrap();
+        for scalar in scalars {
+            kernel = kernel.arg(scalar);
+        }
+        for uint in uints {
+            kernel = kernel.arg(uint);
+        }
+        kernel.arg(&buffer).run().unwrap();
 
-            program.read_into_buffer(&buffer, &mut cpu_buffer).unwrap();
-            Ok(cpu_buffer[0].0)
-        });
+        let mut cpu_buffer = vec![GpuScalar::default()];
+        buffer.read_into(&mut cpu_buffer);
+        cpu_buffer[0].0
+    }
 
-        // For CUDA we only test 32-bit limbs.
-        #[cfg(all(feature = "cuda", not(feature = "opencl")))]
-        return CUDA_PROGRAM.lock().unwrap().run(closures, ()).unwrap();
+    /// Runs the kernel with all enabled backends and checks that their results are the same.
+    ///
+    /// For CUDA only 32-bit limbs are tested, for OpenCL and host C 32-bit and 64-bit limbs.
+    fn call_kernel(name: &str, scalars: &[GpuScalar], uints: &[u32]) -> Scalar {
+        let mut results = Vec::new();
 
-        // For OpenCL we test for 32 and 64-bi limbs.
-        #[cfg(all(feature = "opencl", not(feature = "cuda")))]
+        #[cfg(any(feature = "opencl", feature = "cuda"))]
         {
-            let result_32 = OPENCL_PROGRAM.lock().unwrap().0.run(closures, ()).unwrap();
-            let result_64 = OPENCL_PROGRAM.lock().unwrap().1.run(closures, ()).unwrap();
-            assert_eq!(
-                result_32, result_64,
-                "Results for 32-bit and 64-bit limbs must be the same."
-            );
-            result_32
+            let closures = program_closures!(|program, _args| -> Result<Scalar, NoError> {
+                let mut cpu_buffer = vec![GpuScalar::default()];
+                let buffer = program.create_buffer_from_slice(&cpu_buffer).unwrap();
+
+                let mut kernel = program.create_kernel(name, 1, 64).unwrap();
+                for scalar in scalars {
+                    kernel = kernel.arg(scalar);
+                }
+                for uint in uints {
+                    kernel = kernel.arg(uint);
+                }
+                kernel.arg(&buffer).run().unwrap();
+
+                program.read_into_buffer(&buffer, &mut cpu_buffer).unwrap();
+                Ok(cpu_buffer[0].0)
+            });
+
+            #[cfg(feature = "cuda")]
+            results.push((
+                "CUDA",
+                CUDA_PROGRAM.lock().unwrap().run(closures, ()).unwrap(),
+            ));
+
+            #[cfg(feature = "opencl")]
+            {
+                let programs = OPENCL_PROGRAM.lock().unwrap();
+                results.push(("OpenCL 32-bit", programs.0.run(closures, ()).unwrap()));
+                results.push(("OpenCL 64-bit", programs.1.run(closures, ()).unwrap()));
+            }
         }
 
-        // When both features are enabled, check if the results are the same
-        #[cfg(all(feature = "cuda", feature = "opencl"))]
+        #[cfg(feature = "host-c")]
         {
-            let cuda_result = CUDA_PROGRAM.lock().unwrap().run(closures, ()).unwrap();
-            let opencl_32_result = OPENCL_PROGRAM.lock().unwrap().0.run(closures, ()).unwrap();
-            let opencl_64_result = OPENCL_PROGRAM.lock().unwrap().1.run(closures, ()).unwrap();
-            assert_eq!(
-                opencl_32_result, opencl_64_result,
-                "Results for 32-bit and 64-bit limbs on OpenCL must be the same."
-            );
+            let (program_32, program_64) = &*HOST_C_PROGRAMS;
+            results.push((
+                "host C 32-bit",
+                call_host_c_kernel(program_32, name, scalars, uints),
+            ));
+            results.push((
+                "host C 64-bit",
+                call_host_c_kernel(program_64, name, scalars, uints),
+            ));
+        }
+
+        let (first_backend, first_result) = results[0];
+        for (backend, result) in &results[1..] {
             assert_eq!(
-                cuda_result, opencl_32_result,
-                "Results for CUDA and OpenCL must be the same."
+                *result, first_result,
+                "Results for {} and {} must be the same.",
+                backend, first_backend
             );
-            cuda_result
         }
+        first_result
     }
 
     #[test]
@@ -918,6 +1832,249 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_inverse() {
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let a = Scalar::random(&mut rng);
+            let b = a.invert().unwrap();
+            assert_eq!(call_kernel("test_inverse", &[GpuScalar(a)], &[]), b);
+        }
+        let zero = GpuScalar(Scalar::zero());
+        assert_eq!(call_kernel("test_inverse", &[zero], &[]), Scalar::zero());
+    }
+
+    #[test]
+    fn test_legendre() {
+        let mut rng = thread_rng();
+        let p_minus_1_over_2 = shr_limbs(&sub_limbs(&Scalar::modulus(), &[1]), 1);
+        let exponent: Vec<u64> = p_minus_1_over_2
+            .chunks(2)
+            .map(|limbs| u64::from(limbs[0]) | u64::from(limbs[1]) << 32)
+            .collect();
+        for _ in 0..10 {
+            let a = Scalar::random(&mut rng);
+            for b in [a, a.square(), Scalar::zero()] {
+                let expected = b.pow_vartime(&exponent);
+                assert_eq!(call_kernel("test_legendre", &[GpuScalar(b)], &[]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_limb_helpers() {
+        let a = [0, 0, 1, 0xffff_ffff];
+        assert_eq!(
+            sub_limbs(&a, &[1]),
+            vec![0xffff_ffff, 0xffff_ffff, 0, 0xffff_ffff]
+        );
+        assert_eq!(sub_limbs(&a, &a), vec![0; 4]);
+        assert_eq!(
+            shr_limbs(&a, 1),
+            vec![0, 0x8000_0000, 0x8000_0000, 0x7fff_ffff]
+        );
+        assert_eq!(
+            shr_limbs(&a, 36),
+            vec![0x1000_0000, 0xf000_0000, 0x0fff_ffff, 0]
+        );
+        assert_eq!(shr_limbs(&a, 0), a.to_vec());
+        assert_eq!(trailing_zeros_limbs(&a), 64);
+        assert_eq!(trailing_zeros_limbs(&[12]), 2);
+
+        // The scalar field of BLS12-381 has a 2-adicity of 32.
+        let p_minus_1 = sub_limbs(&Scalar::modulus(), &[1]);
+        assert_eq!(trailing_zeros_limbs(&p_minus_1), Scalar::S);
+    }
+
+    /// Returns the integer of the limbs as little-endian 32-bit limbs.
+    fn limbs_to_u32<L: Limb>(limbs: &[L]) -> Vec<u32>
+    where
+        L::LimbType: Into<u64>,
+    {
+        let mut result = vec![0u32; (limbs.len() * L::bits() + 31) / 32];
+        for (i, limb) in limbs.iter().enumerate() {
+            let value: u64 = limb.value().into();
+            for bit in 0..L::bits() {
+                let index = i * L::bits() + bit;
+                result[index / 32] |= (((value >> bit) & 1) as u32) << (index % 32);
+            }
+        }
+        result
+    }
+
+    /// Returns the scalar of an integer, given as 32-bit limbs, that is smaller than the modulus.
+    fn scalar_from_u32(limbs: &[u32]) -> Scalar {
+        let mut repr = [0u8; 32];
+        for (i, limb) in limbs.iter().enumerate() {
+            if i < 8 {
+                repr[4 * i..4 * (i + 1)].copy_from_slice(&limb.to_le_bytes());
+            } else {
+                assert_eq!(*limb, 0, "The integer must be smaller than the modulus.");
+            }
+        }
+        Scalar::from_repr(repr).unwrap()
+    }
+
+    fn check_limb_constants<L: Limb>()
+    where
+        L::LimbType: Into<u64>,
+    {
+        let p = L::modulus_limbs::<Scalar>();
+        let modulus = Scalar::modulus();
+        let p_u32 = limbs_to_u32(&p);
+        assert_eq!(p_u32[..modulus.len()], modulus[..]);
+        assert!(p_u32[modulus.len()..].iter().all(|limb| *limb == 0));
+
+        // `inv * p = -1 mod 2^bits`
+        let inv: u64 = L::calc_inv(p[0]).value().into();
+        let p0: u64 = p[0].value().into();
+        let mask = u64::MAX >> (64 - L::bits());
+        assert_eq!(inv & !mask, 0);
+        assert_eq!(inv.wrapping_mul(p0).wrapping_add(1) & mask, 0);
+
+        // `R = 2^(bits * n)`
+        let r_bits = (L::bits() * p.len()) as u64;
+        let two = Scalar::from(2);
+        let one = L::one_limbs::<Scalar>();
+        assert_eq!(one.len(), p.len());
+        assert_eq!(
+            scalar_from_u32(&limbs_to_u32(&one)),
+            two.pow_vartime([r_bits])
+        );
+        let r2 = L::calculate_r2::<Scalar>();
+        assert_eq!(r2.len(), p.len());
+        assert_eq!(
+            scalar_from_u32(&limbs_to_u32(&r2)),
+            two.pow_vartime([2 * r_bits])
+        );
+
+        // The generated source uses the same constants.
+        let params = params::<L>(&modulus, &<Scalar as GpuField>::one(), &Scalar::r2());
+        assert!(params.contains(&const_field("FIELD_P", p)));
+        assert!(params.contains(&const_field("FIELD_ONE", one)));
+        assert!(params.contains(&const_field("FIELD_R2", r2)));
+    }
+
+    #[test]
+    fn test_limb_constants() {
+        check_limb_constants::<Limb32>();
+        check_limb_constants::<Limb64>();
+        check_limb_constants::<UnsaturatedLimb<16>>();
+        check_limb_constants::<UnsaturatedLimb<26>>();
+        check_limb_constants::<UnsaturatedLimb<29>>();
+        check_limb_constants::<UnsaturatedLimb<31>>();
+        check_limb_constants::<UnsaturatedLimb64<33>>();
+        check_limb_constants::<UnsaturatedLimb64<52>>();
+        check_limb_constants::<UnsaturatedLimb64<63>>();
+    }
+
+    #[test]
+    fn test_from_u32_limbs() {
+        let a = [0x8765_4321, 0xffff_ffff, 0x1];
+        assert_eq!(limbs_to_u32(&Limb32::from_u32_limbs(&a)), a.to_vec());
+        assert_eq!(
+            limbs_to_u32(&Limb64::from_u32_limbs(&a)),
+            vec![0x8765_4321, 0xffff_ffff, 0x1, 0]
+        );
+        let limbs = UnsaturatedLimb::<16>::from_u32_limbs(&a);
+        assert_eq!(
+            limbs.iter().map(Limb::value).collect::<Vec<_>>(),
+            vec![0x4321, 0x8765, 0xffff, 0xffff, 0x1, 0]
+        );
+        // 96 bits are stored in four 26-bit limbs.
+        let limbs = UnsaturatedLimb::<26>::from_u32_limbs(&a);
+        assert_eq!(limbs.len(), 4);
+        assert_eq!(limbs_to_u32(&limbs), vec![0x8765_4321, 0xffff_ffff, 0x1, 0]);
+        let limbs = UnsaturatedLimb64::<52>::from_u32_limbs(&a);
+        assert_eq!(
+            limbs.iter().map(Limb::value).collect::<Vec<_>>(),
+            vec![0xf_ffff_8765_4321, 0x1fff]
+        );
+    }
+
+    #[test]
+    fn test_mul_pow2_mod() {
+        let modulus = Scalar::modulus();
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let a = Scalar::random(&mut rng);
+            let a_repr = a.to_repr();
+            let limbs = a_repr
+                .chunks(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            for exponent in [0, 1, 31, 100] {
+                let expected = a * Scalar::from(2).pow_vartime([exponent as u64]);
+                assert_eq!(
+                    scalar_from_u32(&mul_pow2_mod(&limbs, &modulus, exponent)),
+                    expected
+                );
+            }
+        }
+    }
+
+    /// Runs the arithmetic kernels with unsaturated limbs `L` on the host, a scalar is stored in
+    /// `N` of them.
+    #[cfg(feature = "host-c")]
+    fn check_unsaturated_limbs<L: Limb, const N: usize>()
+    where
+        L::LimbType: Copy + fmt::Debug + PartialEq,
+    {
+        let program = host::Program::from_source(&test_source().build_with_limbs::<L>()).unwrap();
+        let modulus = Scalar::modulus();
+        let to_limbs = |limbs: &[u32], montgomery: bool| -> [L::LimbType; N] {
+            let limbs = if montgomery {
+                montgomery_limbs::<L>(limbs, &modulus)
+            } else {
+                L::from_u32_limbs(limbs)
+            };
+            let values = limbs.iter().map(Limb::value).collect::<Vec<_>>();
+            values.try_into().unwrap()
+        };
+        // It's safe as the scalar consists of little-endian limbs in Montgomery form.
+        let from_scalar = |scalar: Scalar| -> [L::LimbType; N] {
+            to_limbs(&unsafe { mem::transmute::<Scalar, [u32; 8]>(scalar) }, true)
+        };
+        let run = |name: &str, scalars: &[Scalar]| -> [L::LimbType; N] {
+            let args = scalars.iter().copied().map(from_scalar).collect::<Vec<_>>();
+            let buffer = host::Buffer::from_slice(&[[L::zero().value(); N]]);
+            let mut kernel = program.create_kernel(name, 1, 64).unwrap();
+            for arg in &args {
+                kernel = kernel.arg(arg);
+            }
+            kernel.arg(&buffer).run().unwrap();
+            let mut result = [[L::zero().value(); N]];
+            buffer.read_into(&mut result);
+            result[0]
+        };
+
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let a = Scalar::random(&mut rng);
+            let b = Scalar::random(&mut rng);
+            assert_eq!(run("test_add", &[a, b]), from_scalar(a + b));
+            assert_eq!(run("test_sub", &[a, b]), from_scalar(a - b));
+            assert_eq!(run("test_mul", &[a, b]), from_scalar(a * b));
+            assert_eq!(run("test_sqr", &[a]), from_scalar(a.square()));
+            assert_eq!(run("test_double", &[a]), from_scalar(a.double()));
+            assert_eq!(run("test_inverse", &[a]), from_scalar(a.invert().unwrap()));
+            let a_repr = a
+                .to_repr()
+                .chunks(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(run("test_unmont", &[a]), to_limbs(&a_repr, false));
+        }
+    }
+
+    #[cfg(feature = "host-c")]
+    #[test]
+    fn test_unsaturated_limbs() {
+        // 256 bits are stored in ten 26-bit or in five 52-bit limbs.
+        check_unsaturated_limbs::<UnsaturatedLimb<26>, 10>();
+        check_unsaturated_limbs::<UnsaturatedLimb64<52>, 5>();
+    }
+
     #[test]
     fn test_unmont() {
         let mut rng = thread_rng();
@@ -1,9 +1,12 @@
 //! An interface for dealing with the kinds of parallel computations involved.
 use std::env;
+use std::sync::Arc;
 
 use crossbeam_channel::{bounded, Receiver};
 use once_cell::sync::Lazy;
-use yastl::Pool;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::error::{EcError, EcResult};
 
 /// The number of threads the thread pool should use.
 ///
@@ -11,11 +14,19 @@ use yastl::Pool;
 /// `EC_GPU_NUM_THREADS` environment variable.
 static NUM_THREADS: Lazy<usize> = Lazy::new(read_num_threads);
 
-/// The thread pool that is used for the computations.
+/// The name prefix of the threads that dispatch work to the GPUs.
+const DISPATCH_THREAD_NAME: &str = "ec-gpu-dispatch";
+
+/// The thread pool that is used to dispatch work to several GPUs at once.
 ///
 /// By default, it's size is equal to the number of CPUs. It can be set to a different value with
-/// the `EC_GPU_NUM_THREADS` environment variable.
-pub static THREAD_POOL: Lazy<Pool> = Lazy::new(|| Pool::new(*NUM_THREADS));
+/// the `EC_GPU_NUM_THREADS` environment variable. It's a [`Worker`] with its own thread pool, so
+/// that the threads that wait for the GPUs don't block the computations on the CPU, which run on
+/// the thread pool of the [`Worker`] that is passed in.
+pub static THREAD_POOL: Lazy<Worker> = Lazy::new(|| {
+    Worker::with_config(*NUM_THREADS, DISPATCH_THREAD_NAME)
+        .expect("Cannot create the GPU dispatch thread pool.")
+});
 
 /// Returns the number of threads.
 ///
@@ -28,21 +39,80 @@ fn read_num_threads() -> usize {
         .unwrap_or_else(num_cpus::get)
 }
 
+/// The name prefix of the threads of the default worker thread pool.
+const DEFAULT_THREAD_NAME: &str = "ec-gpu-worker";
+
+/// The thread pool that is used by [`Worker::new`].
+///
+/// By default, it's size is equal to the number of CPUs. It can be set to a different value with
+/// the `EC_GPU_NUM_THREADS` environment variable.
+static WORKER_POOL: Lazy<Arc<ThreadPool>> = Lazy::new(|| {
+    Arc::new(
+        build_thread_pool(*NUM_THREADS, DEFAULT_THREAD_NAME)
+            .expect("Cannot create the default worker thread pool."),
+    )
+});
+
+/// Creates a new thread pool, the threads are named `<thread_name>-<index>`.
+fn build_thread_pool(num_threads: usize, thread_name: &str) -> EcResult<ThreadPool> {
+    let thread_name = thread_name.to_string();
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(move |index| format!("{}-{}", thread_name, index))
+        .build()?;
+    Ok(pool)
+}
+
 /// A worker operates on a pool of threads.
-#[derive(Clone, Default)]
-pub struct Worker {}
+///
+/// All the CPU work of the multiexp and the FFT is done on the thread pool of the worker, this
+/// includes the parallel iterators of [`rayon`] that are used internally. Cloning a worker is cheap,
+/// the clones share the same thread pool.
+#[derive(Clone)]
+pub struct Worker {
+    pool: Arc<ThreadPool>,
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Worker {
-    /// Returns a new worker.
+    /// Returns a new worker that uses the default thread pool.
+    ///
+    /// The default thread pool is shared by all workers created this way. The number of threads
+    /// can be set with the `EC_GPU_NUM_THREADS` environment variable.
     pub fn new() -> Worker {
-        Worker {}
+        Worker {
+            pool: WORKER_POOL.clone(),
+        }
+    }
+
+    /// Returns a new worker with its own thread pool of `num_threads` threads.
+    ///
+    /// The threads are named `<thread_name>-<index>`. The thread pool is shut down once the last
+    /// clone of this worker is dropped.
+    pub fn with_config(num_threads: usize, thread_name: &str) -> EcResult<Worker> {
+        if num_threads == 0 {
+            return Err(EcError::Simple("A worker needs at least one thread."));
+        }
+        Ok(Worker {
+            pool: Arc::new(build_thread_pool(num_threads, thread_name)?),
+        })
+    }
+
+    /// Returns the number of threads of the thread pool.
+    pub fn num_threads(&self) -> usize {
+        self.pool.current_num_threads()
     }
 
     /// Returns binary logarithm (floored) of the number of threads.
     ///
     /// This means, the number of threads is `2^log_num_threads()`.
     pub fn log_num_threads(&self) -> u32 {
-        log2_floor(*NUM_THREADS)
+        log2_floor(self.num_threads())
     }
 
     /// Executes a function in a thread and returns a [`Waiter`] immediately.
@@ -53,7 +123,7 @@ impl Worker {
     {
         let (sender, receiver) = bounded(1);
 
-        THREAD_POOL.spawn(move || {
+        self.pool.spawn(move || {
             let res = f();
             sender.send(res).unwrap();
         });
@@ -63,33 +133,47 @@ impl Worker {
 
     /// Executes a function and returns the result once it is finished.
     ///
-    /// The function gets the [`yastl::Scope`] as well as the `chunk_size` as parameters. THe
+    /// The function gets the [`rayon::Scope`] as well as the `chunk_size` as parameters. THe
     /// `chunk_size` is number of elements per thread.
+    ///
+    /// Before version 0.6 the function got a `yastl::Scope`. Tasks that were started with
+    /// `scope.execute(|| ...)` are now started with `scope.spawn(|_| ...)`.
     pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
     where
-        F: FnOnce(&yastl::Scope<'a>, usize) -> R,
+        F: FnOnce(&rayon::Scope<'a>, usize) -> R + Send,
+        R: Send,
     {
-        let chunk_size = if elements < *NUM_THREADS {
+        let num_threads = self.num_threads();
+        let chunk_size = if elements < num_threads {
             1
         } else {
-            elements / *NUM_THREADS
+            elements / num_threads
         };
 
-        THREAD_POOL.scoped(|scope| f(scope, chunk_size))
+        self.pool.scope(|scope| f(scope, chunk_size))
     }
 
     /// Executes the passed in function, and returns the result once it is finished.
+    ///
+    /// Like for [`Worker::scope`], the function got a `yastl::Scope` before version 0.6.
     pub fn scoped<'a, F, R>(&self, f: F) -> R
     where
-        F: FnOnce(&yastl::Scope<'a>) -> R,
+        F: FnOnce(&rayon::Scope<'a>) -> R + Send,
+        R: Send,
     {
-        let (sender, receiver) = bounded(1);
-        THREAD_POOL.scoped(|s| {
-            let res = f(s);
-            sender.send(res).unwrap();
-        });
+        self.pool.scope(f)
+    }
 
-        receiver.recv().unwrap()
+    /// Executes the passed in function within the thread pool of this worker.
+    ///
+    /// Parallel iterators of [`rayon`] that are used within that function are running on this
+    /// thread pool.
+    pub fn install<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        self.pool.install(f)
     }
 }
 
@@ -140,6 +224,25 @@ pub mod tests {
         assert_eq!(log2_floor(8), 3);
     }
 
+    #[test]
+    fn test_worker_with_config() {
+        let worker = Worker::with_config(3, "test-worker").unwrap();
+        assert_eq!(worker.num_threads(), 3);
+        assert_eq!(worker.log_num_threads(), 1);
+
+        let name = worker
+            .compute(|| std::thread::current().name().map(ToString::to_string))
+            .wait()
+            .unwrap();
+        assert!(name.starts_with("test-worker-"), "unexpected name {}", name);
+
+        // Parallel iterators run on the thread pool of the worker.
+        let num_threads = worker.install(rayon::current_num_threads);
+        assert_eq!(num_threads, 3);
+
+        assert!(Worker::with_config(0, "test-worker").is_err());
+    }
+
     #[test]
     fn test_read_num_threads() {
         let num_cpus = num_cpus::get();
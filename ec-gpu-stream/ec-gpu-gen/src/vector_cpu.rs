@@ -0,0 +1,156 @@
+use ff::Field;
+
+use crate::threadpool::Worker;
+
+/// Applies `op` to the elements of `a` and `b` at the same index (multithreaded).
+fn zip_with<F, O>(a: &mut [F], worker: &Worker, b: &[F], op: O)
+where
+    F: Field,
+    O: Fn(&mut F, &F) + Sync,
+{
+    assert_eq!(a.len(), b.len(), "The vectors must have the same size.");
+    let op = &op;
+    worker.scope(a.len(), |scope, chunk| {
+        for (a, b) in a.chunks_mut(chunk).zip(b.chunks(chunk)) {
+            scope.spawn(move |_| {
+                for (a, b) in a.iter_mut().zip(b.iter()) {
+                    op(a, b);
+                }
+            });
+        }
+    });
+}
+
+/// Adds the elements of `b` to the elements of `a` at the same index (multithreaded).
+pub fn add_assign<F: Field>(a: &mut [F], worker: &Worker, b: &[F]) {
+    zip_with(a, worker, b, |a, b| *a += b);
+}
+
+/// Subtracts the elements of `b` from the elements of `a` at the same index (multithreaded).
+pub fn sub_assign<F: Field>(a: &mut [F], worker: &Worker, b: &[F]) {
+    zip_with(a, worker, b, |a, b| *a -= b);
+}
+
+/// Multiplies the elements of `a` by the elements of `b` at the same index (multithreaded).
+pub fn mul_assign<F: Field>(a: &mut [F], worker: &Worker, b: &[F]) {
+    zip_with(a, worker, b, |a, b| *a *= b);
+}
+
+/// Inverts all elements of `a`, zeros stay zero (multithreaded).
+///
+/// Every thread inverts a chunk with Montgomery's trick, which needs a single inversion and three
+/// multiplications per element.
+pub fn batch_inverse<F: Field>(a: &mut [F], worker: &Worker) {
+    worker.scope(a.len(), |scope, chunk| {
+        for a in a.chunks_mut(chunk) {
+            scope.spawn(move |_| {
+                // The products of all non-zero elements before the index.
+                let mut prefix_products = Vec::with_capacity(a.len());
+                let mut acc = F::one();
+                for x in a.iter() {
+                    prefix_products.push(acc);
+                    if !bool::from(x.is_zero()) {
+                        acc *= x;
+                    }
+                }
+
+                let mut inv = acc.invert().expect("the product of non-zero elements");
+                for (x, prefix_product) in a.iter_mut().zip(prefix_products).rev() {
+                    if !bool::from(x.is_zero()) {
+                        let tmp = *x;
+                        *x = inv * prefix_product;
+                        inv *= tmp;
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Writes the powers `[x^0, x^1, ..., x^(n-1)]` into `out`, where `n` is its length
+/// (multithreaded).
+pub fn powers<F: Field>(out: &mut [F], worker: &Worker, x: &F) {
+    worker.scope(out.len(), |scope, chunk| {
+        for (i, out) in out.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut power = x.pow_vartime([(i * chunk) as u64]);
+                for out in out.iter_mut() {
+                    *out = power;
+                    power *= x;
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+
+    fn random_vector(len: usize) -> Vec<Fr> {
+        let rng = &mut rand::thread_rng();
+        (0..len).map(|_| Fr::random(&mut *rng)).collect()
+    }
+
+    #[test]
+    fn test_elementwise() {
+        let worker = Worker::new();
+
+        for len in [0, 1, 7, 1000] {
+            let a = random_vector(len);
+            let b = random_vector(len);
+
+            let mut sum = a.clone();
+            add_assign(&mut sum, &worker, &b);
+            let mut difference = a.clone();
+            sub_assign(&mut difference, &worker, &b);
+            let mut product = a.clone();
+            mul_assign(&mut product, &worker, &b);
+
+            for i in 0..len {
+                assert_eq!(sum[i], a[i] + b[i]);
+                assert_eq!(difference[i], a[i] - b[i]);
+                assert_eq!(product[i], a[i] * b[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_inverse() {
+        for num_threads in [1, 3, 8] {
+            let worker = Worker::with_config(num_threads, "test").unwrap();
+
+            for len in [0, 1, 2, 33, 1000] {
+                let mut a = random_vector(len);
+                // Zeros in between must be skipped.
+                for i in (0..len).step_by(5) {
+                    a[i] = Fr::zero();
+                }
+
+                let mut inverses = a.clone();
+                batch_inverse(&mut inverses, &worker);
+                for (x, inverse) in a.iter().zip(inverses.iter()) {
+                    assert_eq!(*inverse, x.invert().unwrap_or(Fr::zero()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_powers() {
+        let worker = Worker::new();
+        let x = random_vector(1)[0];
+
+        for len in [0, 1, 2, 1000] {
+            let mut out = vec![Fr::zero(); len];
+            powers(&mut out, &worker, &x);
+            let mut power = Fr::one();
+            for out in out {
+                assert_eq!(out, power);
+                power *= x;
+            }
+        }
+    }
+}
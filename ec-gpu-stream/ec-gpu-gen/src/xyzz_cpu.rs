@@ -0,0 +1,323 @@
+use std::mem;
+
+use ff::Field;
+
+use crate::curve_cpu::{GroupOps, PointAffine};
+
+/// A short Weierstrass curve `y^2 = x^3 + a * x + b` over the field `F`.
+///
+/// It uses the same formulas as the GPU kernels with [`crate::BucketCoordinates::Xyzz`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WeierstrassCurve<F> {
+    /// The coefficient `a` of the curve equation.
+    pub a: F,
+    /// The coefficient `b` of the curve equation.
+    pub b: F,
+}
+
+/// A point in XYZZ coordinates, where `x = X / ZZ`, `y = Y / ZZZ` and `ZZ^3 = ZZZ^2`.
+///
+/// It has the same memory layout as the results of the GPU multiexp with
+/// [`crate::BucketCoordinates::Xyzz`]. The point at infinity has `ZZ = ZZZ = 0`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PointXyzz<F> {
+    /// The `X` coordinate.
+    pub x: F,
+    /// The `Y` coordinate.
+    pub y: F,
+    /// The `ZZ` coordinate.
+    pub zz: F,
+    /// The `ZZZ` coordinate.
+    pub zzz: F,
+}
+
+impl<F: Field> PointXyzz<F> {
+    /// Returns the point at infinity.
+    pub fn identity() -> Self {
+        Self {
+            x: F::one(),
+            y: F::one(),
+            zz: F::zero(),
+            zzz: F::zero(),
+        }
+    }
+
+    /// Returns whether it is the point at infinity.
+    pub fn is_identity(&self) -> bool {
+        self.zz.is_zero_vartime()
+    }
+
+    /// Converts the point into affine coordinates, the point at infinity has none.
+    pub fn to_affine(&self) -> Option<PointAffine<F>> {
+        let zz_inv = Option::<F>::from(self.zz.invert())?;
+        let zzz_inv = Option::<F>::from(self.zzz.invert())?;
+        Some(PointAffine {
+            x: self.x * zz_inv,
+            y: self.y * zzz_inv,
+        })
+    }
+
+    /// Converts the point into Jacobian coordinates `[X, Y, Z]`, without an inversion.
+    ///
+    /// With `Z = ZZ`, `X = X * ZZ` and `Y = Y * ZZZ` the affine coordinates stay the same, as
+    /// `ZZ^3 = ZZZ^2`. The point at infinity is mapped to a point with `Z = 0`.
+    pub fn to_jacobian(&self) -> [F; 3] {
+        [self.x * self.zz, self.y * self.zzz, self.zz]
+    }
+
+    /// Converts the point into the projective point of a curve implementation that stores Jacobian
+    /// coordinates over `F`, like `blstrs` does.
+    ///
+    /// # Safety
+    ///
+    /// The memory layout of `C` must be the `X`, `Y` and `Z` coordinates, without any padding,
+    /// as it's assumed for the results of the Jacobian GPU multiexp.
+    pub unsafe fn to_curve<C: Copy>(&self) -> C {
+        assert_eq!(
+            mem::size_of::<C>(),
+            3 * mem::size_of::<F>(),
+            "The point type must consist of three coordinates."
+        );
+        let jacobian = self.to_jacobian();
+        (jacobian.as_ptr() as *const C).read_unaligned()
+    }
+}
+
+impl<F: Field> From<PointAffine<F>> for PointXyzz<F> {
+    fn from(point: PointAffine<F>) -> Self {
+        Self {
+            x: point.x,
+            y: point.y,
+            zz: F::one(),
+            zzz: F::one(),
+        }
+    }
+}
+
+/// Points are equal if they represent the same affine point.
+impl<F: Field> PartialEq for PointXyzz<F> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.is_identity(), other.is_identity()) {
+            (true, true) => true,
+            (false, false) => {
+                self.x * other.zz == other.x * self.zz && self.y * other.zzz == other.y * self.zzz
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<F: Field> Eq for PointXyzz<F> {}
+
+impl<F: Field> WeierstrassCurve<F> {
+    /// Returns whether the point satisfies the curve equation.
+    pub fn is_on_curve(&self, point: &PointAffine<F>) -> bool {
+        point.y.square() == point.x.square() * point.x + self.a * point.x + self.b
+    }
+
+    /// Returns a point with the given x-coordinate, if there is one.
+    ///
+    /// If there are two such points, the one whose y-coordinate is returned by [`Field::sqrt`] is
+    /// chosen.
+    pub fn point_with_x(&self, x: F) -> Option<PointAffine<F>> {
+        let y_squared = x.square() * x + self.a * x + self.b;
+        let y = Option::<F>::from(y_squared.sqrt())?;
+        Some(PointAffine { x, y })
+    }
+}
+
+/// The bucket arithmetic of the GPU multiexp with [`crate::BucketCoordinates::Xyzz`].
+impl<F: Field> GroupOps for WeierstrassCurve<F> {
+    type Point = PointXyzz<F>;
+    type Affine = PointAffine<F>;
+
+    fn identity(&self) -> PointXyzz<F> {
+        PointXyzz::identity()
+    }
+
+    /// Doubles a point, with the formula "dbl-2008-s-1".
+    fn double(&self, point: &PointXyzz<F>) -> PointXyzz<F> {
+        if point.is_identity() {
+            return *point;
+        }
+        let u = point.y.double();
+        let v = u.square();
+        let w = u * v;
+        let s = point.x * v;
+        let xx = point.x.square();
+        let m = xx.double() + xx + self.a * point.zz.square();
+        let x = m.square() - s.double();
+        PointXyzz {
+            x,
+            y: m * (s - x) - w * point.y,
+            zz: v * point.zz,
+            zzz: w * point.zzz,
+        }
+    }
+
+    /// Adds two points, with the formula "add-2008-s".
+    fn add(&self, p: &PointXyzz<F>, q: &PointXyzz<F>) -> PointXyzz<F> {
+        if p.is_identity() {
+            return *q;
+        }
+        if q.is_identity() {
+            return *p;
+        }
+        let u1 = p.x * q.zz;
+        let u2 = q.x * p.zz;
+        let s1 = p.y * q.zzz;
+        let s2 = q.y * p.zzz;
+        // The formula calls them `P`, `PP`, `PPP` and `Q`.
+        let h = u2 - u1;
+        let r = s2 - s1;
+        if h.is_zero_vartime() && r.is_zero_vartime() {
+            return self.double(p);
+        }
+        let hh = h.square();
+        let hhh = h * hh;
+        let v = u1 * hh;
+        let x = r.square() - hhh - v.double();
+        PointXyzz {
+            x,
+            y: r * (v - x) - s1 * hhh,
+            zz: p.zz * q.zz * hh,
+            zzz: p.zzz * q.zzz * hhh,
+        }
+    }
+
+    /// Adds an affine point, with the formula "madd-2008-s".
+    fn add_mixed(&self, p: &PointXyzz<F>, q: &PointAffine<F>) -> PointXyzz<F> {
+        self.add(p, &PointXyzz::from(*q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use blstrs::Scalar as Fr;
+    use rand::RngCore;
+
+    /// The curve `y^2 = x^3 + x + 1`.
+    fn test_curve() -> WeierstrassCurve<Fr> {
+        WeierstrassCurve {
+            a: Fr::one(),
+            b: Fr::one(),
+        }
+    }
+
+    fn random_point<R: RngCore>(curve: &WeierstrassCurve<Fr>, rng: &mut R) -> PointAffine<Fr> {
+        loop {
+            if let Some(point) = curve.point_with_x(Fr::random(&mut *rng)) {
+                return point;
+            }
+        }
+    }
+
+    /// Adds two affine points with the chord-and-tangent rule, they must not be the inverse of
+    /// each other.
+    fn affine_add(
+        curve: &WeierstrassCurve<Fr>,
+        p: PointAffine<Fr>,
+        q: PointAffine<Fr>,
+    ) -> PointAffine<Fr> {
+        let lambda = if p == q {
+            (p.x.square().double() + p.x.square() + curve.a) * p.y.double().invert().unwrap()
+        } else {
+            (q.y - p.y) * (q.x - p.x).invert().unwrap()
+        };
+        let x = lambda.square() - p.x - q.x;
+        PointAffine {
+            x,
+            y: lambda * (p.x - x) - p.y,
+        }
+    }
+
+    /// Returns the point with random `ZZ` and `ZZZ` coordinates.
+    fn randomize<R: RngCore>(point: PointAffine<Fr>, rng: &mut R) -> PointXyzz<Fr> {
+        let z = Fr::random(rng);
+        PointXyzz {
+            x: point.x * z.square(),
+            y: point.y * z.square() * z,
+            zz: z.square(),
+            zzz: z.square() * z,
+        }
+    }
+
+    #[test]
+    fn test_xyzz_arithmetic() {
+        let mut rng = rand::thread_rng();
+        let identity = PointXyzz::identity();
+
+        for curve in [
+            test_curve(),
+            WeierstrassCurve {
+                a: Fr::zero(),
+                b: Fr::from(5),
+            },
+        ] {
+            for _ in 0..20 {
+                let p = random_point(&curve, &mut rng);
+                let q = random_point(&curve, &mut rng);
+                assert!(curve.is_on_curve(&p));
+                let p_xyzz = randomize(p, &mut rng);
+                let q_xyzz = randomize(q, &mut rng);
+
+                let sum = affine_add(&curve, p, q);
+                assert_eq!(curve.add(&p_xyzz, &q_xyzz).to_affine(), Some(sum));
+                assert_eq!(curve.add_mixed(&p_xyzz, &q).to_affine(), Some(sum));
+                let doubled = affine_add(&curve, p, p);
+                assert_eq!(curve.double(&p_xyzz).to_affine(), Some(doubled));
+                // Adding a point to itself falls back to doubling.
+                assert_eq!(curve.add_mixed(&p_xyzz, &p).to_affine(), Some(doubled));
+                assert_eq!(curve.add(&p_xyzz, &p_xyzz).to_affine(), Some(doubled));
+
+                assert_eq!(curve.add(&p_xyzz, &identity), p_xyzz);
+                assert_eq!(curve.add(&identity, &p_xyzz), p_xyzz);
+                assert_eq!(curve.add_mixed(&identity, &p), p_xyzz);
+                assert_eq!(curve.double(&identity), identity);
+                let minus_p = PointAffine { x: p.x, y: -p.y };
+                assert_eq!(curve.add_mixed(&p_xyzz, &minus_p), identity);
+            }
+        }
+    }
+
+    #[test]
+    fn test_xyzz_to_jacobian() {
+        let mut rng = rand::thread_rng();
+        let curve = test_curve();
+        for _ in 0..20 {
+            let p = random_point(&curve, &mut rng);
+            let [x, y, z] = randomize(p, &mut rng).to_jacobian();
+            let z_inv = z.invert().unwrap();
+            assert_eq!(x * z_inv.square(), p.x);
+            assert_eq!(y * z_inv.square() * z_inv, p.y);
+        }
+        let [_, _, z] = PointXyzz::<Fr>::identity().to_jacobian();
+        assert!(bool::from(z.is_zero()));
+
+        let p = random_point(&curve, &mut rng);
+        let point = randomize(p, &mut rng);
+        let curve_point: [Fr; 3] = unsafe { point.to_curve() };
+        assert_eq!(curve_point, point.to_jacobian());
+    }
+
+    #[test]
+    fn test_xyzz_multiexp() {
+        let mut rng = rand::thread_rng();
+        let curve = test_curve();
+        let p = random_point(&curve, &mut rng);
+        let q = random_point(&curve, &mut rng);
+
+        let p_times_3 = affine_add(&curve, affine_add(&curve, p, p), p);
+        assert_eq!(curve.mul(&p, &3u64).to_affine(), Some(p_times_3));
+        assert!(curve.mul(&p, &0u64).is_identity());
+
+        let expected = affine_add(&curve, p_times_3, affine_add(&curve, q, q));
+        assert_eq!(
+            curve.multiexp(&[p, q], &[3u64, 2u64]).to_affine(),
+            Some(expected)
+        );
+    }
+}
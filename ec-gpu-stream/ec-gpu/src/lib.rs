@@ -39,6 +39,72 @@ pub trait GpuField: GpuName {
     fn sub_field_name() -> Option<String> {
         None
     }
+
+    /// Returns a primitive `2^S`-th root of unity, where `2^S` is the largest power of two that
+    /// divides `p - 1`, as a vector of 32-bit limbs in little-endian Montgomery form (least
+    /// significant limb first).
+    ///
+    /// It's needed for square roots on the GPU. The default is `None`, then square roots are only
+    /// available for fields with `S = 1`, where the root of unity is `-1`.
+    fn root_of_unity() -> Option<Vec<u32>> {
+        None
+    }
+}
+
+/// A short Weierstrass curve `y^2 = x^3 + a * x + b`. It's implemented by the affine points of the
+/// curve.
+pub trait GpuCurve: GpuName {
+    /// The field the coordinates of the points are in.
+    type BaseField: GpuField;
+
+    /// Returns the coefficient `a` of the curve equation.
+    ///
+    /// It's returned as the coefficients of the base field element over the prime field, i.e. a
+    /// single one for a prime field and two (`c0` and `c1`) for a quadratic extension field. Each
+    /// one is a vector of 32-bit limbs in little-endian Montgomery form (least significant limb
+    /// first).
+    fn a() -> Vec<Vec<u32>>;
+
+    /// Returns the coefficient `b` of the curve equation, in the same form as [`GpuCurve::a`].
+    fn b() -> Vec<Vec<u32>>;
+}
+
+/// A twisted Edwards curve `a * x^2 + y^2 = 1 + d * x^2 * y^2`. It's implemented by the affine
+/// points of the curve.
+///
+/// The formulas that are used on the GPU are complete for curves where `a` is a square and `d` is
+/// not, like Jubjub or Bandersnatch.
+pub trait GpuEdwardsCurve: GpuName {
+    /// The field of the exponents of a multiexp, i.e. the scalar field of the prime order
+    /// subgroup.
+    type ScalarField: GpuField;
+
+    /// Returns the coefficient `a` of the curve equation, in the same form as [`GpuCurve::a`].
+    fn a() -> Vec<Vec<u32>>;
+
+    /// Returns the coefficient `d` of the curve equation, in the same form as [`GpuCurve::a`].
+    fn d() -> Vec<Vec<u32>>;
+}
+
+/// A cubic extension of a quadratic extension field, e.g. `Fp6 = Fp2[v] / (v^3 - ξ)`.
+///
+/// Together with [`GpuQuadraticExtension`] it describes towers of extension fields like
+/// `Fp2 → Fp6 → Fp12`, as they are used for pairings.
+pub trait GpuCubicExtension: GpuName {
+    /// The quadratic extension field this field is built on, e.g. `Fp2`.
+    type SubField: GpuField;
+
+    /// Returns the non-residue `ξ = c0 + u * c1` as its two coefficients. Each one is a vector of
+    /// 32-bit limbs in little-endian Montgomery form (least significant limb first).
+    fn non_residue() -> [Vec<u32>; 2];
+}
+
+/// A quadratic extension of a cubic extension field, e.g. `Fp12 = Fp6[w] / (w^2 - v)`.
+///
+/// The non-residue is always the generator `v` of the cubic extension field.
+pub trait GpuQuadraticExtension: GpuName {
+    /// The cubic extension field this field is built on, e.g. `Fp6`.
+    type SubField: GpuCubicExtension;
 }
 
 /// Macro to get a unique name of an item.
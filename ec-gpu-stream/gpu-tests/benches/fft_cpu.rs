@@ -0,0 +1,66 @@
+//! Compares the in-place CPU FFT with the one that shuffles into sub-FFTs.
+//!
+//! This benchmark runs on the CPU only, no GPU is needed.
+use blstrs::Scalar as Fr;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ec_gpu_gen::{
+    domain::EvaluationDomain,
+    fft_cpu::{in_place_fft, parallel_fft, serial_fft},
+    threadpool::Worker,
+};
+use ff::Field;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// The number of elements are `2^x` for each `x` of this list.
+const ELEMENTS_POWERS: [u32; 4] = [10, 14, 18, 22];
+
+fn bench_fft_cpu(crit: &mut Criterion) {
+    let mut group = crit.benchmark_group("fft_cpu");
+    // The difference between runs is so little, hence a low sample size is OK.
+    group.sample_size(10);
+
+    let worker = Worker::new();
+    let log_threads = worker.log_num_threads();
+    let max_elements = 1 << ELEMENTS_POWERS[ELEMENTS_POWERS.len() - 1];
+    let max_coeffs: Vec<_> = (0..max_elements)
+        .into_par_iter()
+        .map(|_| Fr::random(rand::thread_rng()))
+        .collect();
+
+    for log_n in ELEMENTS_POWERS {
+        let omega = *EvaluationDomain::<Fr>::new(log_n).unwrap().omega();
+        let coeffs = max_coeffs[0..1 << log_n].to_vec();
+
+        group.bench_with_input(
+            BenchmarkId::new("parallel", log_n),
+            &log_n,
+            |bencher, &log_n| {
+                bencher.iter(|| {
+                    let mut a = coeffs.clone();
+                    if log_n <= log_threads {
+                        serial_fft(&mut a, &omega, log_n);
+                    } else {
+                        parallel_fft(&mut a, &worker, &omega, log_n, log_threads);
+                    }
+                    black_box(a);
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("in_place", log_n),
+            &log_n,
+            |bencher, &log_n| {
+                bencher.iter(|| {
+                    let mut a = coeffs.clone();
+                    in_place_fft(&mut a, &worker, &omega, log_n);
+                    black_box(a);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_fft_cpu);
+criterion_main!(benches);
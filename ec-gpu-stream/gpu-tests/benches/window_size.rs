@@ -0,0 +1,90 @@
+//! Sweeps the window size of the CPU multiexp, in order to validate the choice of the cost model.
+//!
+//! This benchmark runs on the CPU only, no GPU is needed.
+use std::sync::Arc;
+
+use blstrs::Bls12;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ec_gpu_gen::{
+    multiexp_cpu::{multiexp_cpu_with_window_size, optimal_window_size, FullDensity},
+    scalar::WindowScalar,
+    threadpool::Worker,
+};
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, Group};
+use pairing::Engine;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// The number of elements are `2^x` for each `x` of this list.
+const ELEMENTS_POWERS: [usize; 3] = [10, 14, 18];
+/// The window sizes around the one the cost model picks that are benchmarked.
+const WINDOW_SIZE_SPREAD: u32 = 3;
+
+fn bench_window_size<G>(crit: &mut Criterion, name: &str)
+where
+    G: PrimeCurveAffine,
+    <G::Scalar as PrimeField>::Repr: WindowScalar,
+{
+    let mut group = crit.benchmark_group(name);
+    // The difference between runs is so little, hence a low sample size is OK.
+    group.sample_size(10);
+
+    let pool = Worker::new();
+    let max_elements = 1 << ELEMENTS_POWERS[ELEMENTS_POWERS.len() - 1];
+    let max_bases: Vec<G> = (0..max_elements)
+        .into_par_iter()
+        .map(|_| G::Curve::random(rand::thread_rng()).to_affine())
+        .collect();
+    let max_exponents: Vec<_> = (0..max_elements)
+        .into_par_iter()
+        .map(|_| G::Scalar::random(rand::thread_rng()).to_repr())
+        .collect();
+
+    for power in ELEMENTS_POWERS {
+        let num = 1 << power;
+        let bases = Arc::new(max_bases[0..num].to_vec());
+        let exponents = Arc::new(max_exponents[0..num].to_vec());
+
+        let chosen =
+            optimal_window_size::<G, <G::Scalar as PrimeField>::Repr>(num, pool.num_threads());
+        println!(
+            "{}: cost model picks window size {} for {} terms.",
+            name, chosen, num
+        );
+
+        let min = chosen.saturating_sub(WINDOW_SIZE_SPREAD).max(1);
+        for c in min..=(chosen + WINDOW_SIZE_SPREAD) {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}", num), c),
+                &c,
+                |bencher, &c| {
+                    bencher.iter(|| {
+                        black_box(
+                            multiexp_cpu_with_window_size(
+                                &pool,
+                                (bases.clone(), 0),
+                                FullDensity,
+                                exponents.clone(),
+                                c,
+                            )
+                            .wait()
+                            .unwrap(),
+                        );
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_window_size_g1(crit: &mut Criterion) {
+    bench_window_size::<<Bls12 as Engine>::G1Affine>(crit, "window_size_g1");
+}
+
+fn bench_window_size_g2(crit: &mut Criterion) {
+    bench_window_size::<<Bls12 as Engine>::G2Affine>(crit, "window_size_g2");
+}
+
+criterion_group!(benches, bench_window_size_g1, bench_window_size_g2);
+criterion_main!(benches);
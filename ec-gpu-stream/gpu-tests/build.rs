@@ -1,14 +1,10 @@
-#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+#[cfg(any(feature = "cuda", feature = "opencl", feature = "host-c"))]
+mod source_builder;
+
+#[cfg(not(any(feature = "cuda", feature = "opencl", feature = "host-c")))]
 fn main() {}
 
-#[cfg(any(feature = "cuda", feature = "opencl"))]
+#[cfg(any(feature = "cuda", feature = "opencl", feature = "host-c"))]
 fn main() {
-    use blstrs::{Fp, Fp2, G1Affine, G2Affine, Scalar};
-    use ec_gpu_gen::SourceBuilder;
-
-    let source_builder = SourceBuilder::new()
-        .add_fft::<Scalar>()
-        .add_multiexp::<G1Affine, Fp>()
-        .add_multiexp::<G2Affine, Fp2>();
-    ec_gpu_gen::generate(&source_builder);
+    ec_gpu_gen::generate(&source_builder::source_builder());
 }
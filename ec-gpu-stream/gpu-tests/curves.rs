@@ -0,0 +1,27 @@
+use blstrs::Fp;
+use ec_gpu::{GpuCurve, GpuName};
+use ff::Field;
+
+/// The curve `y^2 = x^3 + 4` of `G1Affine`, as `blstrs` doesn't implement [`GpuCurve`].
+pub struct Bls12G1;
+
+impl GpuName for Bls12G1 {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuCurve for Bls12G1 {
+    type BaseField = Fp;
+
+    fn a() -> Vec<Vec<u32>> {
+        vec![vec![0; 12]]
+    }
+
+    fn b() -> Vec<Vec<u32>> {
+        let four = Fp::one().double().double();
+        // `Fp` is stored in Montgomery form as little-endian limbs.
+        let limbs: [u32; 12] = unsafe { std::mem::transmute(four) };
+        vec![limbs.to_vec()]
+    }
+}
@@ -0,0 +1,12 @@
+use blstrs::{Fp, Fp2, G1Affine, G2Affine, Scalar};
+use ec_gpu_gen::SourceBuilder;
+
+/// The kernel configuration that is used by the tests and benchmarks.
+///
+/// It's shared between the `build.rs` and the golden-file test of the generated source.
+pub fn source_builder() -> SourceBuilder {
+    SourceBuilder::new()
+        .add_mixed_radix_fft::<Scalar>()
+        .add_multiexp::<G1Affine, Fp>()
+        .add_multiexp::<G2Affine, Fp2>()
+}
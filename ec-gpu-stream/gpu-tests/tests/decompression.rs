@@ -0,0 +1,81 @@
+#![cfg(feature = "opencl")]
+
+#[path = "../curves.rs"]
+mod curves;
+
+use blstrs::{Fp, G1Affine, G1Projective, Scalar as Fr};
+use ec_gpu_gen::{
+    decompression::PointDecompressionKernel,
+    rust_gpu_tools::{opencl, Device, Program},
+    SourceBuilder,
+};
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Group};
+
+use curves::Bls12G1;
+
+#[test]
+fn gpu_point_decompression_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let mut points = (0..1000)
+        .map(|_| G1Affine::from(G1Projective::random(&mut rng)))
+        .collect::<Vec<_>>();
+    points.push(G1Affine::identity());
+    let mut encodings = points
+        .iter()
+        .map(|point| point.to_compressed())
+        .collect::<Vec<_>>();
+    // An x-coordinate that is larger than the modulus.
+    let mut non_canonical = [0xff; 48];
+    non_canonical[0] = 0x9f;
+    encodings.push(non_canonical);
+    let mut expected = points.into_iter().map(Some).collect::<Vec<_>>();
+    expected.push(None);
+
+    // A point that is on the curve, but not in the prime order subgroup, as the cofactor isn't
+    // cleared.
+    let outside_subgroup = loop {
+        let x = Fp::random(&mut rng);
+        let y2 = x.square() * x + Fp::one().double().double();
+        if let Some(y) = Option::<Fp>::from(y2.sqrt()) {
+            let mut uncompressed = [0; 96];
+            uncompressed[..48].copy_from_slice(&x.to_bytes_be());
+            uncompressed[48..].copy_from_slice(&y.to_bytes_be());
+            break G1Affine::from_uncompressed_unchecked(&uncompressed).unwrap();
+        }
+    };
+    let outside_subgroup_encoding = outside_subgroup.to_compressed();
+    assert!(bool::from(
+        G1Affine::from_compressed(&outside_subgroup_encoding).is_none()
+    ));
+    encodings.push(outside_subgroup_encoding);
+
+    // The kernels of the build script don't contain the decompression, hence the source is
+    // compiled here.
+    let source = SourceBuilder::new()
+        .add_point_decompression::<Bls12G1, Fr>()
+        .build_64_bit_limbs();
+    for device in Device::all() {
+        let opencl_device = device.opencl_device().expect("Not an OpenCL device!");
+        let program =
+            opencl::Program::from_opencl(opencl_device, &source).expect("Cannot create program!");
+        let kern = PointDecompressionKernel::<Bls12G1>::create(Program::Opencl(program), None)
+            .expect("Cannot initialize kernel!");
+
+        // There is nothing to run for an empty input.
+        let empty = kern.decompress::<G1Affine>(&[], true);
+        assert!(empty.expect("GPU decompression failed!").is_empty());
+        assert!(kern.decompress::<G1Affine>(&[0; 47], true).is_err());
+
+        for check_subgroup in [false, true] {
+            let result = kern
+                .decompress::<G1Affine>(&encodings.concat(), check_subgroup)
+                .expect("GPU decompression failed!");
+            let mut expected = expected.clone();
+            expected.push((!check_subgroup).then(|| outside_subgroup));
+            assert_eq!(result, expected);
+        }
+    }
+}
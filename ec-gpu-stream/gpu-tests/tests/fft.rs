@@ -4,23 +4,15 @@ use std::time::Instant;
 
 use blstrs::Scalar as Fr;
 use ec_gpu_gen::{
+    domain::EvaluationDomain,
     fft::FftKernel,
-    fft_cpu::{parallel_fft, serial_fft},
+    fft_cpu::{self, parallel_fft, serial_fft},
+    mixed_radix, poly,
     rust_gpu_tools::Device,
     threadpool::Worker,
 };
 use ff::{Field, PrimeField};
 
-fn omega<F: PrimeField>(num_coeffs: usize) -> F {
-    // Compute omega, the 2^exp primitive root of unity
-    let exp = (num_coeffs as f32).log2().floor() as u32;
-    let mut omega = F::root_of_unity();
-    for _ in exp..F::S {
-        omega = omega.square();
-    }
-    omega
-}
-
 #[test]
 pub fn gpu_fft_consistency() {
     fil_logger::maybe_init();
@@ -40,7 +32,7 @@ pub fn gpu_fft_consistency() {
         let d = 1 << log_d;
 
         let mut v1_coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
-        let v1_omega = omega::<Fr>(v1_coeffs.len());
+        let v1_omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
         let mut v2_coeffs = v1_coeffs.clone();
         let v2_omega = v1_omega;
 
@@ -89,9 +81,10 @@ pub fn gpu_fft_many_consistency() {
         let mut v11_coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
         let mut v12_coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
         let mut v13_coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
-        let v11_omega = omega::<Fr>(v11_coeffs.len());
-        let v12_omega = omega::<Fr>(v12_coeffs.len());
-        let v13_omega = omega::<Fr>(v13_coeffs.len());
+        let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+        let v11_omega = omega;
+        let v12_omega = omega;
+        let v13_omega = omega;
 
         let mut v21_coeffs = v11_coeffs.clone();
         let mut v22_coeffs = v12_coeffs.clone();
@@ -134,3 +127,258 @@ pub fn gpu_fft_many_consistency() {
         println!("============================");
     }
 }
+
+#[test]
+pub fn gpu_ifft_and_coset_fft_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+    let g = Fr::multiplicative_generator();
+
+    for log_d in 1..=16 {
+        let d = 1 << log_d;
+        let coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+
+        println!("Testing inverse and coset FFT for {} elements...", d);
+
+        let mut gpu = coeffs.clone();
+        let mut cpu = coeffs.clone();
+        kern.ifft(&mut gpu, &omega, log_d).expect("GPU FFT failed!");
+        fft_cpu::ifft(&mut cpu, &worker, &omega, log_d);
+        assert_eq!(gpu, cpu);
+
+        let mut gpu = coeffs.clone();
+        let mut cpu = coeffs.clone();
+        kern.coset_fft(&mut gpu, &omega, log_d, &g)
+            .expect("GPU FFT failed!");
+        fft_cpu::coset_fft(&mut cpu, &worker, &omega, log_d, &g);
+        assert_eq!(gpu, cpu);
+
+        kern.icoset_fft(&mut gpu, &omega, log_d, &g)
+            .expect("GPU FFT failed!");
+        assert_eq!(gpu, coeffs);
+    }
+}
+
+#[test]
+pub fn gpu_evaluation_domain_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log_d in 1..=16 {
+        let domain = EvaluationDomain::<Fr>::new(log_d).unwrap();
+        let coeffs = (0..domain.size())
+            .map(|_| Fr::random(&mut rng))
+            .collect::<Vec<_>>();
+
+        let mut gpu = coeffs.clone();
+        let mut cpu = coeffs.clone();
+        domain.coset_fft_gpu(&mut gpu, &mut kern).unwrap();
+        domain.coset_fft(&mut cpu, &worker);
+        assert_eq!(gpu, cpu);
+
+        domain.ifft_gpu(&mut gpu, &mut kern).unwrap();
+        domain.ifft(&mut cpu, &worker);
+        assert_eq!(gpu, cpu);
+    }
+}
+
+#[test]
+pub fn gpu_four_step_fft_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log_d in 1..=20 {
+        let d = 1 << log_d;
+        let coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+
+        println!("Testing four-step FFT for {} elements...", d);
+
+        let mut gpu = coeffs.clone();
+        kern.four_step_fft(&mut gpu, &worker, &omega, log_d)
+            .expect("GPU FFT failed!");
+        let mut cpu = coeffs.clone();
+        fft_cpu::four_step_fft(&mut cpu, &worker, &omega, log_d);
+        let mut expected = coeffs;
+        serial_fft(&mut expected, &omega, log_d);
+
+        assert_eq!(cpu, expected);
+        assert_eq!(gpu, expected);
+    }
+}
+
+#[test]
+pub fn gpu_fft_batch_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log_d in 1..=16 {
+        for num_ffts in [1, 5, 32] {
+            let d = 1 << log_d;
+            let coeffs = (0..num_ffts * d)
+                .map(|_| Fr::random(&mut rng))
+                .collect::<Vec<_>>();
+            let omega = *EvaluationDomain::<Fr>::new(log_d).unwrap().omega();
+
+            println!("Testing batch of {} FFTs for {} elements...", num_ffts, d);
+
+            let mut gpu = coeffs.clone();
+            kern.radix_fft_batch(&mut gpu, &omega, log_d)
+                .expect("GPU FFT failed!");
+            let mut cpu = coeffs;
+            fft_cpu::fft_batch(&mut cpu, &worker, &omega, log_d);
+
+            assert_eq!(gpu, cpu);
+        }
+    }
+}
+
+#[test]
+pub fn gpu_poly_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let mut engine = Worker::new();
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log_d in 1..=14 {
+        let d = 1 << log_d;
+        let a = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let b = (0..d + 3).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        let gpu = poly::multiply(&mut kern, &a, &b).expect("GPU FFT failed!");
+        let cpu = poly::multiply(&mut engine, &a, &b).unwrap();
+        assert_eq!(gpu, cpu);
+
+        let quotient = poly::divide_by_vanishing(&mut kern, &worker, &gpu, 1 << (log_d / 2))
+            .expect("GPU FFT failed!");
+        let expected =
+            poly::divide_by_vanishing(&mut engine, &worker, &cpu, 1 << (log_d / 2)).unwrap();
+        assert_eq!(quotient, expected);
+    }
+}
+
+#[test]
+pub fn gpu_mixed_radix_fft_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log3_n in 0..=1 {
+        for log2_n in 1..=16 {
+            let d = mixed_radix::size(log2_n, log3_n);
+            let coeffs = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+            let omega = mixed_radix::root_of_unity::<Fr>(log2_n, log3_n).unwrap();
+
+            println!("Testing mixed-radix FFT for {} elements...", d);
+
+            let mut gpu = coeffs.clone();
+            kern.mixed_radix_fft(&mut gpu, &omega, log2_n, log3_n)
+                .expect("GPU FFT failed!");
+            let mut cpu = coeffs;
+            mixed_radix::fft(&mut cpu, &worker, &omega, log2_n, log3_n);
+
+            assert_eq!(gpu, cpu);
+        }
+    }
+}
+
+#[test]
+pub fn gpu_fft_buffer_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let mut engine = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for log_d in 1..=16 {
+        let domain = EvaluationDomain::<Fr>::new(log_d).unwrap();
+        let d = domain.size();
+        let a = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let b = (0..d).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let c = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| *a * b)
+            .collect::<Vec<_>>();
+
+        println!("Testing GPU-resident FFT pipeline for {} elements...", d);
+
+        let mut buffer = kern.upload(&a).expect("GPU upload failed!");
+        let mut downloaded = vec![Fr::zero(); d];
+        kern.download(&buffer, &mut downloaded)
+            .expect("GPU download failed!");
+        assert_eq!(downloaded, a);
+
+        kern.radix_fft_buffer(&mut buffer, domain.omega(), log_d)
+            .expect("GPU FFT failed!");
+        kern.download(&buffer, &mut downloaded)
+            .expect("GPU download failed!");
+        let mut expected = a.clone();
+        serial_fft(&mut expected, domain.omega(), log_d);
+        assert_eq!(downloaded, expected);
+
+        let gpu =
+            poly::vanishing_quotient(&mut kern, &domain, &a, &b, &c).expect("GPU FFT failed!");
+        let cpu = poly::vanishing_quotient(&mut engine, &domain, &a, &b, &c).unwrap();
+        assert_eq!(gpu, cpu);
+    }
+}
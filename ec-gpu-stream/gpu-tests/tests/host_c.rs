@@ -0,0 +1,944 @@
+#![cfg(feature = "host-c")]
+
+#[path = "../curves.rs"]
+mod curves;
+
+use std::sync::Arc;
+
+use blstrs::{Fp, Fp12, Fp2, Fp6, G1Affine, G1Projective, Gt, Scalar as Fr};
+use ec_gpu::{
+    GpuCubicExtension, GpuCurve, GpuEdwardsCurve, GpuField, GpuName, GpuQuadraticExtension,
+};
+use ec_gpu_gen::{
+    curve_cpu::GroupOps,
+    domain::EvaluationDomain,
+    edwards_cpu::{EdwardsAffine, EdwardsCurve, EdwardsExtended},
+    fft_cpu::{self, serial_fft},
+    host::{Buffer, LocalBuffer, Program},
+    multiexp_cpu::{multiexp_cpu, multiexp_cpu_reproducible, FullDensity},
+    normalize_cpu::{batch_normalize, num_segments, PointJacobian, SEGMENT_SIZE},
+    threadpool::Worker,
+    vector_cpu,
+    xyzz_cpu::PointXyzz,
+    BucketCoordinates, SourceBuilder,
+};
+use ff::{Field, PrimeField};
+use group::Group;
+
+use curves::Bls12G1;
+
+const LOCAL_WORK_SIZE: usize = 64;
+
+fn program() -> Program {
+    ec_gpu_gen::host_program!().expect("Cannot load the host C kernels!")
+}
+
+/// Runs an element-wise kernel on `a` and returns the result.
+fn run_elementwise(program: &Program, name: &str, a: &[Fr], b: &[Fr]) -> Vec<Fr> {
+    let a_buffer = Buffer::from_slice(a);
+    let b_buffer = Buffer::from_slice(b);
+    let num_groups = (a.len() + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+    program
+        .create_kernel(
+            &format!("{}_{}", Fr::name(), name),
+            num_groups,
+            LOCAL_WORK_SIZE,
+        )
+        .expect("Cannot create kernel!")
+        .arg(&a_buffer)
+        .arg(&b_buffer)
+        .arg(&(a.len() as u32))
+        .run()
+        .expect("Host C kernel failed!");
+    let mut result = vec![Fr::zero(); a.len()];
+    a_buffer.read_into(&mut result);
+    result
+}
+
+#[test]
+pub fn host_c_vector_ops_consistency() {
+    let mut rng = rand::thread_rng();
+    let worker = Worker::new();
+    let program = program();
+
+    for len in [1, 63, 64, 1000] {
+        let a = (0..len).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let b = (0..len).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        let mut sum = a.clone();
+        vector_cpu::add_assign(&mut sum, &worker, &b);
+        assert_eq!(run_elementwise(&program, "vector_add", &a, &b), sum);
+
+        let mut difference = a.clone();
+        vector_cpu::sub_assign(&mut difference, &worker, &b);
+        assert_eq!(run_elementwise(&program, "vector_sub", &a, &b), difference);
+
+        let mut product = a.clone();
+        vector_cpu::mul_assign(&mut product, &worker, &b);
+        assert_eq!(run_elementwise(&program, "vector_mul", &a, &b), product);
+
+        let c = Fr::random(&mut rng);
+        let mut scaled = a.clone();
+        fft_cpu::scale(&mut scaled, &worker, &c);
+        let buffer = Buffer::from_slice(&a);
+        program
+            .create_kernel(
+                &format!("{}_vector_scale", Fr::name()),
+                (len + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE,
+                LOCAL_WORK_SIZE,
+            )
+            .expect("Cannot create kernel!")
+            .arg(&buffer)
+            .arg(&(len as u32))
+            .arg(&c)
+            .run()
+            .expect("Host C kernel failed!");
+        let mut result = vec![Fr::zero(); len];
+        buffer.read_into(&mut result);
+        assert_eq!(result, scaled);
+    }
+}
+
+/// The maximum degree of a radix FFT round, the same as the `FftKernel` uses.
+const MAX_LOG2_RADIX: u32 = 8;
+/// The maximum local work size of a radix FFT round, the same as the `FftKernel` uses.
+const MAX_LOG2_LOCAL_WORK_SIZE: u32 = 7;
+
+/// All rounds of the radix FFT kernel, like the `FftKernel` runs them. The kernel uses local
+/// memory and barriers.
+#[test]
+pub fn host_c_radix_fft_consistency() {
+    let mut rng = rand::thread_rng();
+    let program = program();
+
+    // Sizes up to the maximum radix take a single round, bigger ones need several.
+    for log_n in (1..=8).chain([9, 12]) {
+        let n = 1 << log_n;
+        let omega = *EvaluationDomain::<Fr>::new(log_n).unwrap().omega();
+        let coeffs = (0..n).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+
+        // The precalculated twiddle factors of a round of the maximum degree, the kernel derives
+        // the ones of all rounds from them.
+        let max_deg = std::cmp::min(MAX_LOG2_RADIX, log_n);
+        let twiddle = omega.pow_vartime([(n >> max_deg) as u64]);
+        let pq = (0..std::cmp::max(1 << max_deg >> 1, 1))
+            .scan(Fr::one(), |power, _| {
+                let current = *power;
+                *power *= twiddle;
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+        let omegas = (0..32)
+            .scan(omega, |square, _| {
+                let current = *square;
+                *square = square.square();
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+
+        let mut src = Buffer::from_slice(&coeffs);
+        let mut dst = Buffer::from_slice(&vec![Fr::zero(); n]);
+        let pq_buffer = Buffer::from_slice(&pq);
+        let omegas_buffer = Buffer::from_slice(&omegas);
+        let mut log_p = 0;
+        while log_p < log_n {
+            let deg = std::cmp::min(max_deg, log_n - log_p);
+            let local_size = 1 << std::cmp::min(deg - 1, MAX_LOG2_LOCAL_WORK_SIZE);
+            program
+                .create_kernel(&format!("{}_radix_fft", Fr::name()), n >> deg, local_size)
+                .expect("Cannot create kernel!")
+                .arg(&src)
+                .arg(&dst)
+                .arg(&pq_buffer)
+                .arg(&omegas_buffer)
+                .arg(&LocalBuffer::<Fr>::new(1 << deg))
+                .arg(&(n as u32))
+                .arg(&log_p)
+                .arg(&deg)
+                .arg(&max_deg)
+                .run()
+                .expect("Host C kernel failed!");
+            log_p += deg;
+            std::mem::swap(&mut src, &mut dst);
+        }
+        let mut result = vec![Fr::zero(); n];
+        src.read_into(&mut result);
+
+        let mut expected = coeffs;
+        serial_fft(&mut expected, &omega, log_n);
+        assert_eq!(result, expected, "FFT of size {}", n);
+    }
+}
+
+// `blstrs` only describes `Fp` and `Fp2` for the use on the GPU, the rest of the tower is
+// described here.
+struct GpuFp6;
+
+impl GpuName for GpuFp6 {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuCubicExtension for GpuFp6 {
+    type SubField = Fp2;
+
+    // The non-residue is `u + 1`.
+    fn non_residue() -> [Vec<u32>; 2] {
+        [<Fp as GpuField>::one(), <Fp as GpuField>::one()]
+    }
+}
+
+struct GpuFp12;
+
+impl GpuName for GpuFp12 {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuQuadraticExtension for GpuFp12 {
+    type SubField = GpuFp6;
+}
+
+static TOWER_TEST_SRC: &str = r#"
+KERNEL void FIELD6_test_mul(GLOBAL FIELD6* a, GLOBAL FIELD6* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD6_mul(a[i], b[i]);
+}
+KERNEL void FIELD6_test_sqr(GLOBAL FIELD6* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD6_sqr(a[i]);
+}
+KERNEL void FIELD12_test_add(GLOBAL FIELD12* a, GLOBAL FIELD12* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD12_add(a[i], b[i]);
+}
+KERNEL void FIELD12_test_sub(GLOBAL FIELD12* a, GLOBAL FIELD12* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD12_sub(a[i], b[i]);
+}
+KERNEL void FIELD12_test_mul(GLOBAL FIELD12* a, GLOBAL FIELD12* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD12_mul(a[i], b[i]);
+}
+KERNEL void FIELD12_test_sqr(GLOBAL FIELD12* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD12_sqr(a[i]);
+}
+KERNEL void FIELD12_test_conjugate(GLOBAL FIELD12* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD12_conjugate(a[i]);
+}
+"#;
+
+/// Runs a kernel with the signature `(a, n)` and returns the resulting `a`.
+fn run_unary_kernel<T: Copy>(program: &Program, name: &str, a: &[T]) -> Vec<T> {
+    let a_buffer = Buffer::from_slice(a);
+    let num_groups = (a.len() + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+    program
+        .create_kernel(name, num_groups, LOCAL_WORK_SIZE)
+        .expect("Cannot create kernel!")
+        .arg(&a_buffer)
+        .arg(&(a.len() as u32))
+        .run()
+        .expect("Host C kernel failed!");
+    let mut result = a.to_vec();
+    a_buffer.read_into(&mut result);
+    result
+}
+
+/// Runs a kernel with the signature `(a, b, n)` and returns the resulting `a`.
+fn run_binary_kernel<T: Copy, U: Copy>(program: &Program, name: &str, a: &[T], b: &[U]) -> Vec<T> {
+    let a_buffer = Buffer::from_slice(a);
+    let b_buffer = Buffer::from_slice(b);
+    let num_groups = (a.len() + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+    program
+        .create_kernel(name, num_groups, LOCAL_WORK_SIZE)
+        .expect("Cannot create kernel!")
+        .arg(&a_buffer)
+        .arg(&b_buffer)
+        .arg(&(a.len() as u32))
+        .run()
+        .expect("Host C kernel failed!");
+    let mut result = a.to_vec();
+    a_buffer.read_into(&mut result);
+    result
+}
+
+#[test]
+pub fn host_c_tower_field_consistency() {
+    let mut rng = rand::thread_rng();
+    let source = SourceBuilder::new()
+        .add_quadratic_extension::<GpuFp12>()
+        .append_source(
+            TOWER_TEST_SRC
+                .replace("FIELD12", &GpuFp12::name())
+                .replace("FIELD6", &GpuFp6::name()),
+        )
+        .build_64_bit_limbs();
+    let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+    let fp6_kernel = |name| format!("{}_test_{}", GpuFp6::name(), name);
+    let fp12_kernel = |name| format!("{}_test_{}", GpuFp12::name(), name);
+
+    let len = 100;
+    let a = (0..len).map(|_| Fp6::random(&mut rng)).collect::<Vec<_>>();
+    let b = (0..len).map(|_| Fp6::random(&mut rng)).collect::<Vec<_>>();
+    let expected = a.iter().zip(&b).map(|(a, b)| *a * b).collect::<Vec<_>>();
+    assert_eq!(
+        run_binary_kernel(&program, &fp6_kernel("mul"), &a, &b),
+        expected
+    );
+    let expected = a.iter().map(Field::square).collect::<Vec<_>>();
+    assert_eq!(run_unary_kernel(&program, &fp6_kernel("sqr"), &a), expected);
+
+    let a = (0..len).map(|_| Fp12::random(&mut rng)).collect::<Vec<_>>();
+    let b = (0..len).map(|_| Fp12::random(&mut rng)).collect::<Vec<_>>();
+    let expected = a.iter().zip(&b).map(|(a, b)| *a + b).collect::<Vec<_>>();
+    assert_eq!(
+        run_binary_kernel(&program, &fp12_kernel("add"), &a, &b),
+        expected
+    );
+    let expected = a.iter().zip(&b).map(|(a, b)| *a - b).collect::<Vec<_>>();
+    assert_eq!(
+        run_binary_kernel(&program, &fp12_kernel("sub"), &a, &b),
+        expected
+    );
+    let expected = a.iter().zip(&b).map(|(a, b)| *a * b).collect::<Vec<_>>();
+    assert_eq!(
+        run_binary_kernel(&program, &fp12_kernel("mul"), &a, &b),
+        expected
+    );
+    let expected = a.iter().map(Field::square).collect::<Vec<_>>();
+    assert_eq!(
+        run_unary_kernel(&program, &fp12_kernel("sqr"), &a),
+        expected
+    );
+
+    // Elements of the target group are unitary, their inverse is the conjugate.
+    let gt = (0..len).map(|_| Gt::random(&mut rng)).collect::<Vec<_>>();
+    let expected = gt.iter().map(|gt| -*gt).collect::<Vec<_>>();
+    assert_eq!(
+        run_unary_kernel(&program, &fp12_kernel("conjugate"), &gt),
+        expected
+    );
+}
+
+/// The curve `y^2 = x^3 + x + 1` over the scalar field, it's used to test the formulas for curves
+/// with `a ≠ 0`.
+struct TestCurve;
+
+impl GpuName for TestCurve {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuCurve for TestCurve {
+    type BaseField = Fr;
+
+    fn a() -> Vec<Vec<u32>> {
+        vec![<Fr as GpuField>::one()]
+    }
+
+    fn b() -> Vec<Vec<u32>> {
+        vec![<Fr as GpuField>::one()]
+    }
+}
+
+static CURVE_TEST_SRC: &str = r#"
+KERNEL void POINT_test_double(GLOBAL POINT_jacobian* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = POINT_double(a[i]);
+}
+KERNEL void POINT_test_add(GLOBAL POINT_jacobian* a, GLOBAL POINT_jacobian* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = POINT_add(a[i], b[i]);
+}
+KERNEL void POINT_test_add_mixed(GLOBAL POINT_jacobian* a, GLOBAL POINT_affine* b, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = POINT_add_mixed(a[i], b[i]);
+}
+"#;
+
+/// Returns a random point of the [`TestCurve`] in affine coordinates.
+fn random_test_curve_point<R: rand::RngCore>(rng: &mut R) -> [Fr; 2] {
+    loop {
+        let x = Fr::random(&mut *rng);
+        let y_squared = x.square() * x + x + Fr::one();
+        if let Some(y) = Option::<Fr>::from(y_squared.sqrt()) {
+            return [x, y];
+        }
+    }
+}
+
+/// Adds two affine points of the [`TestCurve`], they must not be the inverse of each other.
+fn test_curve_add(p: [Fr; 2], q: [Fr; 2]) -> [Fr; 2] {
+    let lambda = if p == q {
+        (p[0].square().double() + p[0].square() + Fr::one()) * p[1].double().invert().unwrap()
+    } else {
+        (q[1] - p[1]) * (q[0] - p[0]).invert().unwrap()
+    };
+    let x = lambda.square() - p[0] - q[0];
+    let y = lambda * (p[0] - x) - p[1];
+    [x, y]
+}
+
+fn to_jacobian(p: [Fr; 2], z: Fr) -> [Fr; 3] {
+    [p[0] * z.square(), p[1] * z.square() * z, z]
+}
+
+fn to_affine(p: [Fr; 3]) -> [Fr; 2] {
+    let z_inv = p[2].invert().unwrap();
+    [p[0] * z_inv.square(), p[1] * z_inv.square() * z_inv]
+}
+
+#[test]
+pub fn host_c_curve_non_zero_a_consistency() {
+    let mut rng = rand::thread_rng();
+    let source = SourceBuilder::new()
+        .add_curve::<TestCurve>()
+        .append_source(CURVE_TEST_SRC.replace("POINT", &TestCurve::name()))
+        .build_64_bit_limbs();
+    let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+    let kernel = |name| format!("{}_test_{}", TestCurve::name(), name);
+
+    let len = 100;
+    let p = (0..len)
+        .map(|_| random_test_curve_point(&mut rng))
+        .collect::<Vec<_>>();
+    let q = (0..len)
+        .map(|_| random_test_curve_point(&mut rng))
+        .collect::<Vec<_>>();
+    let p_jacobian = p
+        .iter()
+        .map(|p| to_jacobian(*p, Fr::random(&mut rng)))
+        .collect::<Vec<_>>();
+    let q_jacobian = q
+        .iter()
+        .map(|q| to_jacobian(*q, Fr::random(&mut rng)))
+        .collect::<Vec<_>>();
+    let to_affine_all =
+        |points: Vec<[Fr; 3]>| points.into_iter().map(to_affine).collect::<Vec<_>>();
+
+    let doubled = p.iter().map(|p| test_curve_add(*p, *p)).collect::<Vec<_>>();
+    let sum = p
+        .iter()
+        .zip(&q)
+        .map(|(p, q)| test_curve_add(*p, *q))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        to_affine_all(run_unary_kernel(&program, &kernel("double"), &p_jacobian)),
+        doubled
+    );
+    assert_eq!(
+        to_affine_all(run_binary_kernel(
+            &program,
+            &kernel("add"),
+            &p_jacobian,
+            &q_jacobian
+        )),
+        sum
+    );
+    assert_eq!(
+        to_affine_all(run_binary_kernel(
+            &program,
+            &kernel("add_mixed"),
+            &p_jacobian,
+            &q
+        )),
+        sum
+    );
+    // Adding a point to itself falls back to doubling.
+    assert_eq!(
+        to_affine_all(run_binary_kernel(
+            &program,
+            &kernel("add_mixed"),
+            &p_jacobian,
+            &p
+        )),
+        doubled
+    );
+}
+
+/// Returns the Montgomery form of a field element as it's used on the GPU.
+fn montgomery_limbs(x: Fr) -> Vec<u32> {
+    // `R` is the Montgomery form of one, interpreting its limbs as canonical representation
+    // results in the field element `R`.
+    let mut r_repr = <Fr as PrimeField>::Repr::default();
+    for (bytes, limb) in r_repr.chunks_mut(4).zip(<Fr as GpuField>::one()) {
+        bytes.copy_from_slice(&limb.to_le_bytes());
+    }
+    let r = Fr::from_repr(r_repr).unwrap();
+    (x * r)
+        .to_repr()
+        .chunks(4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect()
+}
+
+/// A twisted Edwards curve with the Jubjub parameters, but over the scalar field. The exponents of
+/// the multiexp are elements of the scalar field as well.
+struct TestEdwardsCurve;
+
+impl TestEdwardsCurve {
+    fn cpu() -> EdwardsCurve<Fr> {
+        EdwardsCurve {
+            a: -Fr::one(),
+            d: -(Fr::from(10240) * Fr::from(10241).invert().unwrap()),
+        }
+    }
+}
+
+impl GpuName for TestEdwardsCurve {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuEdwardsCurve for TestEdwardsCurve {
+    type ScalarField = Fr;
+
+    fn a() -> Vec<Vec<u32>> {
+        vec![montgomery_limbs(Self::cpu().a)]
+    }
+
+    fn d() -> Vec<Vec<u32>> {
+        vec![montgomery_limbs(Self::cpu().d)]
+    }
+}
+
+/// The window size of [`run_multiexp_kernel`].
+const MULTIEXP_WINDOW_SIZE: usize = 4;
+/// The number of windows of [`run_multiexp_kernel`], they cover all 256 bits of the exponents.
+const MULTIEXP_NUM_WINDOWS: usize = 64;
+/// The number of groups the bases are split into by [`run_multiexp_kernel`].
+const MULTIEXP_NUM_GROUPS: usize = 2;
+
+/// Runs the multiexp kernel of the given curve and returns the result of every thread.
+///
+/// `zero` is the point at infinity in the representation of the buckets and results.
+fn run_multiexp_kernel<B: Copy, P: Copy>(
+    program: &Program,
+    curve_name: &str,
+    bases: &[B],
+    exps: &[<Fr as PrimeField>::Repr],
+    zero: P,
+) -> Vec<P> {
+    let num_threads = MULTIEXP_NUM_WINDOWS * MULTIEXP_NUM_GROUPS;
+    let bucket_len = (1 << MULTIEXP_WINDOW_SIZE) - 1;
+
+    let bases_buffer = Buffer::from_slice(bases);
+    let buckets_buffer = Buffer::from_slice(&vec![zero; num_threads * bucket_len]);
+    let results_buffer = Buffer::from_slice(&vec![zero; num_threads]);
+    let exps_buffer = Buffer::from_slice(exps);
+    program
+        .create_kernel(
+            &format!("{}_multiexp", curve_name),
+            (num_threads + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE,
+            LOCAL_WORK_SIZE,
+        )
+        .expect("Cannot create kernel!")
+        .arg(&bases_buffer)
+        .arg(&buckets_buffer)
+        .arg(&results_buffer)
+        .arg(&exps_buffer)
+        .arg(&(bases.len() as u32))
+        .arg(&(MULTIEXP_NUM_GROUPS as u32))
+        .arg(&(MULTIEXP_NUM_WINDOWS as u32))
+        .arg(&(MULTIEXP_WINDOW_SIZE as u32))
+        .run()
+        .expect("Host C kernel failed!");
+    let mut results = vec![zero; num_threads];
+    results_buffer.read_into(&mut results);
+    results
+}
+
+/// Combines the results of [`run_multiexp_kernel`] into the result of the multiexp.
+fn combine_multiexp_results<P: Copy>(
+    results: &[P],
+    zero: P,
+    double: impl Fn(&P) -> P,
+    add: impl Fn(&P, &P) -> P,
+) -> P {
+    // The first window contains the least significant bits.
+    let mut acc = zero;
+    for window in (0..MULTIEXP_NUM_WINDOWS).rev() {
+        for _ in 0..MULTIEXP_WINDOW_SIZE {
+            acc = double(&acc);
+        }
+        for group in 0..MULTIEXP_NUM_GROUPS {
+            acc = add(&acc, &results[group * MULTIEXP_NUM_WINDOWS + window]);
+        }
+    }
+    acc
+}
+
+/// A multiexp with the Jacobian buckets of the default kernels, compared with the CPU.
+#[test]
+pub fn host_c_multiexp_consistency() {
+    let mut rng = rand::thread_rng();
+    let pool = Worker::new();
+    let program = program();
+
+    let n = 100;
+    let bases = (0..n)
+        .map(|_| G1Affine::from(G1Projective::random(&mut rng)))
+        .collect::<Vec<_>>();
+    let exps = (0..n)
+        .map(|_| Fr::random(&mut rng).to_repr())
+        .collect::<Vec<_>>();
+
+    let results = run_multiexp_kernel(
+        &program,
+        &G1Affine::name(),
+        &bases,
+        &exps,
+        G1Projective::identity(),
+    );
+    let result = combine_multiexp_results(
+        &results,
+        G1Projective::identity(),
+        |p| p.double(),
+        |p, q| *p + q,
+    );
+
+    let bases = Arc::new(bases);
+    let exps = Arc::new(exps);
+    let expected = multiexp_cpu(&pool, (bases.clone(), 0), FullDensity, exps.clone())
+        .wait()
+        .unwrap();
+    assert_eq!(result, expected);
+
+    // The windows are the same as the ones of the CPU.
+    let (_, trace) = multiexp_cpu_reproducible(
+        &pool,
+        (bases, 0),
+        FullDensity,
+        exps,
+        MULTIEXP_WINDOW_SIZE as u32,
+        n,
+    )
+    .wait()
+    .unwrap();
+    assert_eq!(trace.windows.len(), MULTIEXP_NUM_WINDOWS);
+    for (window, expected) in trace.windows.iter().enumerate() {
+        let sum = (0..MULTIEXP_NUM_GROUPS)
+            .map(|group| results[group * MULTIEXP_NUM_WINDOWS + window])
+            .sum::<G1Projective>();
+        assert_eq!(sum, *expected, "window {}", window);
+    }
+}
+
+#[test]
+pub fn host_c_edwards_multiexp_consistency() {
+    let mut rng = rand::thread_rng();
+    let source = SourceBuilder::new()
+        .add_edwards_multiexp::<TestEdwardsCurve, Fr>()
+        .build_64_bit_limbs();
+    let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+    let curve = TestEdwardsCurve::cpu();
+
+    let n = 100;
+    let bases = (0..n)
+        .map(|_| loop {
+            if let Some(point) = curve.point_with_y(Fr::random(&mut rng)) {
+                break point;
+            }
+        })
+        .collect::<Vec<EdwardsAffine<Fr>>>();
+    let exps = (0..n)
+        .map(|_| Fr::random(&mut rng).to_repr())
+        .collect::<Vec<_>>();
+
+    let results = run_multiexp_kernel(
+        &program,
+        &TestEdwardsCurve::name(),
+        &bases,
+        &exps,
+        EdwardsExtended::identity(),
+    );
+    let result = combine_multiexp_results(
+        &results,
+        EdwardsExtended::identity(),
+        |p| curve.double(p),
+        |p, q| curve.add(p, q),
+    );
+    assert_eq!(result, curve.multiexp(&bases, &exps));
+}
+
+#[test]
+pub fn host_c_xyzz_multiexp_consistency() {
+    let mut rng = rand::thread_rng();
+    let source = SourceBuilder::new()
+        .add_multiexp::<G1Affine, Fp>()
+        .bucket_coordinates(BucketCoordinates::Xyzz)
+        .build_64_bit_limbs();
+    let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+
+    let n = 50;
+    let bases = (0..n)
+        .map(|_| G1Affine::from(G1Projective::random(&mut rng)))
+        .collect::<Vec<_>>();
+    let scalars = (0..n).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+    let exps = scalars.iter().map(|s| s.to_repr()).collect::<Vec<_>>();
+
+    let results = run_multiexp_kernel(
+        &program,
+        &G1Affine::name(),
+        &bases,
+        &exps,
+        PointXyzz::<Fp>::identity(),
+    );
+    // The host converts the XYZZ results into points, without any inversions.
+    let results = results
+        .iter()
+        .map(|result| unsafe { result.to_curve::<G1Projective>() })
+        .collect::<Vec<_>>();
+    let result = combine_multiexp_results(
+        &results,
+        G1Projective::identity(),
+        |p| p.double(),
+        |p, q| *p + q,
+    );
+
+    let expected = bases
+        .iter()
+        .zip(&scalars)
+        .map(|(base, scalar)| G1Projective::from(base) * scalar)
+        .sum::<G1Projective>();
+    assert_eq!(result, expected);
+}
+
+/// The scalar field together with its root of unity, so that square roots are generated with
+/// Tonelli-Shanks. `Fp` has a 2-adicity of one, its square roots need no root of unity.
+struct FrWithSqrt;
+
+impl GpuName for FrWithSqrt {
+    fn name() -> String {
+        ec_gpu::name!()
+    }
+}
+
+impl GpuField for FrWithSqrt {
+    fn one() -> Vec<u32> {
+        <Fr as GpuField>::one()
+    }
+
+    fn r2() -> Vec<u32> {
+        <Fr as GpuField>::r2()
+    }
+
+    fn modulus() -> Vec<u32> {
+        <Fr as GpuField>::modulus()
+    }
+
+    fn root_of_unity() -> Option<Vec<u32>> {
+        Some(montgomery_limbs(<Fr as PrimeField>::root_of_unity()))
+    }
+}
+
+static FIELD_FUNCTIONS_TEST_SRC: &str = r#"
+KERNEL void FIELD_test_inverse(GLOBAL FIELD* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) a[i] = FIELD_inverse(a[i]);
+}
+KERNEL void FIELD_test_legendre(GLOBAL FIELD* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) {
+    const int legendre = FIELD_legendre(a[i]);
+    if (legendre == 0) a[i] = FIELD_ZERO;
+    else if (legendre == 1) a[i] = FIELD_ONE;
+    else a[i] = FIELD_sub(FIELD_ZERO, FIELD_ONE);
+  }
+}
+// Non-squares result in zero, which is never the root of a non-zero element.
+KERNEL void FIELD_test_sqrt(GLOBAL FIELD* a, uint n) {
+  const uint i = GET_GLOBAL_ID();
+  if (i < n) {
+    FIELD root;
+    a[i] = FIELD_sqrt(a[i], &root) ? root : FIELD_ZERO;
+  }
+}
+"#;
+
+/// Checks the inverse, the Legendre symbol and the square root of the field `G` on the GPU against
+/// its CPU counterpart `F`.
+fn check_field_functions<G: GpuField + 'static, F: Field>() {
+    let mut rng = rand::thread_rng();
+    let source = SourceBuilder::new()
+        .add_field::<G>()
+        .append_source(FIELD_FUNCTIONS_TEST_SRC.replace("FIELD", &G::name()));
+    let kernel = |name| format!("{}_test_{}", G::name(), name);
+
+    for source in [source.build_32_bit_limbs(), source.build_64_bit_limbs()] {
+        let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+
+        let mut a = (0..100).map(|_| F::random(&mut rng)).collect::<Vec<_>>();
+        // Make sure that there are squares and zero.
+        let squares = a.iter().map(Field::square).collect::<Vec<_>>();
+        a.extend(squares);
+        a.push(F::zero());
+
+        let expected = a
+            .iter()
+            .map(|a| Option::<F>::from(a.invert()).unwrap_or_else(F::zero))
+            .collect::<Vec<_>>();
+        assert_eq!(run_unary_kernel(&program, &kernel("inverse"), &a), expected);
+
+        let expected = a
+            .iter()
+            .map(
+                |a| match (a.is_zero_vartime(), bool::from(a.sqrt().is_some())) {
+                    (true, _) => F::zero(),
+                    (false, true) => F::one(),
+                    (false, false) => -F::one(),
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            run_unary_kernel(&program, &kernel("legendre"), &a),
+            expected
+        );
+
+        let roots = run_unary_kernel(&program, &kernel("sqrt"), &a);
+        for (a, root) in a.iter().zip(roots) {
+            if bool::from(a.sqrt().is_some()) {
+                assert_eq!(root.square(), *a);
+            } else {
+                assert_eq!(root, F::zero());
+            }
+        }
+    }
+}
+
+#[test]
+pub fn host_c_field_functions_consistency() {
+    check_field_functions::<FrWithSqrt, Fr>();
+    check_field_functions::<Fp, Fp>();
+}
+
+#[test]
+pub fn host_c_point_decompression_consistency() {
+    use rand::RngCore;
+
+    let mut rng = rand::thread_rng();
+    let mut encodings = (0..50)
+        .map(|_| G1Affine::from(G1Projective::random(&mut rng)).to_compressed())
+        .collect::<Vec<_>>();
+    encodings.push(G1Affine::identity().to_compressed());
+    // About half of the random x-coordinates are on the curve, but almost none of them are in the
+    // subgroup.
+    for _ in 0..50 {
+        let mut encoding = [0u8; 48];
+        rng.fill_bytes(&mut encoding);
+        encoding[0] = encoding[0] & 0x3f | 0x80;
+        encodings.push(encoding);
+    }
+    // Without the compression flag, with the infinity flag but a non-zero x-coordinate and with an
+    // x-coordinate that is larger than the modulus.
+    let mut uncompressed = encodings[0];
+    uncompressed[0] &= 0x7f;
+    let mut infinity = encodings[0];
+    infinity[0] |= 0x40;
+    let mut non_canonical = [0xff; 48];
+    non_canonical[0] = 0x9f;
+    encodings.extend([uncompressed, infinity, non_canonical]);
+    let n = encodings.len();
+
+    let source = SourceBuilder::new().add_point_decompression::<Bls12G1, Fr>();
+    for source in [source.build_32_bit_limbs(), source.build_64_bit_limbs()] {
+        let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+        for check_subgroup in [false, true] {
+            let compressed_buffer = Buffer::from_slice(&encodings.concat());
+            let points_buffer = Buffer::from_slice(&vec![G1Affine::identity(); n]);
+            let valid_buffer = Buffer::from_slice(&vec![0u32; n]);
+            let num_groups = (n + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE;
+            program
+                .create_kernel(
+                    &format!("{}_decompress", Bls12G1::name()),
+                    num_groups,
+                    LOCAL_WORK_SIZE,
+                )
+                .expect("Cannot create kernel!")
+                .arg(&compressed_buffer)
+                .arg(&points_buffer)
+                .arg(&valid_buffer)
+                .arg(&(n as u32))
+                .arg(&(check_subgroup as u32))
+                .run()
+                .expect("Host C kernel failed!");
+            let mut points = vec![G1Affine::identity(); n];
+            points_buffer.read_into(&mut points);
+            let mut valid = vec![0u32; n];
+            valid_buffer.read_into(&mut valid);
+
+            for (i, encoding) in encodings.iter().enumerate() {
+                let expected = if check_subgroup {
+                    G1Affine::from_compressed(encoding)
+                } else {
+                    G1Affine::from_compressed_unchecked(encoding)
+                };
+                let expected = Option::<G1Affine>::from(expected);
+                assert_eq!(valid[i] == 1, expected.is_some(), "encoding {}", i);
+                if let Some(expected) = expected {
+                    assert_eq!(points[i], expected, "encoding {}", i);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+pub fn host_c_batch_normalization_consistency() {
+    let mut rng = rand::thread_rng();
+    let worker = Worker::new();
+    let mut points = (0..200)
+        .map(|_| G1Projective::random(&mut rng))
+        .collect::<Vec<_>>();
+    // Points at infinity within a segment and at its borders.
+    for i in [10, SEGMENT_SIZE - 1, SEGMENT_SIZE] {
+        points[i] = G1Projective::identity();
+    }
+    let n = points.len();
+    let expected = points.iter().map(G1Affine::from).collect::<Vec<_>>();
+
+    // `G1Projective` is in Jacobian coordinates.
+    let jacobian = points
+        .iter()
+        .map(|point| unsafe { std::mem::transmute::<_, PointJacobian<Fp>>(*point) })
+        .collect::<Vec<_>>();
+    let cpu = batch_normalize(&jacobian, &worker, SEGMENT_SIZE);
+    for (point, expected) in cpu.iter().zip(&expected) {
+        assert_eq!((point.x, point.y), (expected.x(), expected.y()));
+    }
+
+    let source = SourceBuilder::new().add_batch_normalization::<G1Affine, Fp>();
+    for source in [source.build_32_bit_limbs(), source.build_64_bit_limbs()] {
+        let program = Program::from_source(&source).expect("Cannot compile the host C kernels!");
+        for segment_size in [1, 7, SEGMENT_SIZE] {
+            let points_buffer = Buffer::from_slice(&points);
+            let result_buffer = Buffer::from_slice(&vec![G1Affine::identity(); n]);
+            let scratch_buffer = Buffer::from_slice(&vec![Fp::zero(); n]);
+            let num_threads = num_segments(n, segment_size);
+            program
+                .create_kernel(
+                    &format!("{}_batch_normalize", G1Affine::name()),
+                    (num_threads + LOCAL_WORK_SIZE - 1) / LOCAL_WORK_SIZE,
+                    LOCAL_WORK_SIZE,
+                )
+                .expect("Cannot create kernel!")
+                .arg(&points_buffer)
+                .arg(&result_buffer)
+                .arg(&scratch_buffer)
+                .arg(&(n as u32))
+                .arg(&(segment_size as u32))
+                .run()
+                .expect("Host C kernel failed!");
+            let mut result = vec![G1Affine::identity(); n];
+            result_buffer.read_into(&mut result);
+            assert_eq!(result, expected, "segment size {}", segment_size);
+        }
+    }
+}
@@ -4,30 +4,42 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use blstrs::Bls12;
+#[cfg(feature = "opencl")]
+use blstrs::{Fp, G1Affine};
 use ec_gpu::GpuName;
-use ec_gpu_gen::multiexp_cpu::{multiexp_cpu, FullDensity, QueryDensity, SourceBuilder};
+use ec_gpu_gen::multiexp_cpu::{
+    multiexp_cpu, multiexp_cpu_reproducible, FullDensity, QueryDensity, SourceBuilder,
+};
+use ec_gpu_gen::{
+    multiexp::MultiexpKernel, program, rust_gpu_tools::Device, scalar::WindowScalar,
+    threadpool::Worker, EcError,
+};
+#[cfg(feature = "opencl")]
 use ec_gpu_gen::{
-    multiexp::MultiexpKernel, program, rust_gpu_tools::Device, threadpool::Worker, EcError,
+    rust_gpu_tools::{opencl, Program},
+    BucketCoordinates,
 };
 use ff::{Field, PrimeField};
 use group::Curve;
 use group::{prime::PrimeCurveAffine, Group};
 use pairing::Engine;
+use rand::Rng;
 
-fn multiexp_gpu<Q, D, G, S>(
+fn multiexp_gpu<Q, D, G, E, S>(
     pool: &Worker,
     bases: S,
     density_map: D,
-    exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
+    exponents: Arc<Vec<E>>,
     kern: &mut MultiexpKernel<G>,
 ) -> Result<G::Curve, EcError>
 where
     for<'a> &'a Q: QueryDensity,
     D: Send + Sync + 'static + Clone + AsRef<Q>,
     G: PrimeCurveAffine + GpuName,
+    E: WindowScalar,
     S: SourceBuilder<G>,
 {
-    let exps = density_map.as_ref().generate_exps::<G::Scalar>(exponents);
+    let exps = density_map.as_ref().generate_exps(exponents);
     let (bss, skip) = bases.get();
     kern.multiexp(pool, bss, exps, skip).map_err(Into::into)
 }
@@ -87,3 +99,140 @@ fn gpu_multiexp_consistency() {
         bases = [bases.clone(), bases.clone()].concat();
     }
 }
+
+/// The kernels with XYZZ buckets, their results are converted back on the host.
+#[cfg(feature = "opencl")]
+#[test]
+fn gpu_multiexp_xyzz_consistency() {
+    fil_logger::maybe_init();
+    const SAMPLES: usize = (1 << 14) + 123;
+
+    // The kernels of the build script have Jacobian buckets, hence the source is compiled here.
+    let source = ec_gpu_gen::SourceBuilder::new()
+        .add_multiexp::<G1Affine, Fp>()
+        .bucket_coordinates(BucketCoordinates::Xyzz)
+        .build_64_bit_limbs();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| {
+            let opencl_device = device.opencl_device().expect("Not an OpenCL device!");
+            opencl::Program::from_opencl(opencl_device, &source).map(Program::Opencl)
+        })
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = MultiexpKernel::<G1Affine>::create(programs, &devices)
+        .expect("Cannot initialize kernel!")
+        .with_xyzz_buckets();
+    let pool = Worker::new();
+
+    let mut rng = rand::thread_rng();
+    let g = Arc::new(
+        (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::G1::random(&mut rng).to_affine())
+            .collect::<Vec<_>>(),
+    );
+    let v = Arc::new(
+        (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::Fr::random(&mut rng).to_repr())
+            .collect::<Vec<_>>(),
+    );
+
+    let gpu = multiexp_gpu(&pool, (g.clone(), 0), FullDensity, v.clone(), &mut kern).unwrap();
+    let cpu = multiexp_cpu(&pool, (g, 0), FullDensity, v).wait().unwrap();
+    assert_eq!(cpu, gpu);
+}
+
+#[test]
+fn gpu_multiexp_reproducible() {
+    fil_logger::maybe_init();
+    const SAMPLES: usize = (1 << 16) + 123;
+    const CHUNK_SIZE: usize = 1 << 14;
+    // It doesn't divide the 256 bits of the exponents, so that the last window is a partial one.
+    const WINDOW_SIZE: u32 = 7;
+
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| crate::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = MultiexpKernel::<<Bls12 as Engine>::G1Affine>::create(programs, &devices)
+        .expect("Cannot initialize kernel!");
+    let pool = Worker::new();
+
+    let mut rng = rand::thread_rng();
+    let g = Arc::new(
+        (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::G1::random(&mut rng).to_affine())
+            .collect::<Vec<_>>(),
+    );
+    let v = Arc::new(
+        (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::Fr::random(&mut rng).to_repr())
+            .collect::<Vec<_>>(),
+    );
+
+    let (gpu, gpu_trace) = kern
+        .multiexp_reproducible(&pool, g.clone(), v.clone(), 0, WINDOW_SIZE, CHUNK_SIZE)
+        .unwrap();
+    let (cpu, cpu_trace) = multiexp_cpu_reproducible(
+        &pool,
+        (g.clone(), 0),
+        FullDensity,
+        v.clone(),
+        WINDOW_SIZE,
+        CHUNK_SIZE,
+    )
+    .wait()
+    .unwrap();
+
+    assert_eq!(gpu_trace.chunks.len(), 5);
+    assert_eq!(cpu_trace.first_mismatching_chunk(&gpu_trace), None);
+    assert_eq!(gpu_trace.window_size, WINDOW_SIZE);
+    assert_eq!(gpu_trace.windows.len(), cpu_trace.windows.len());
+    assert_eq!(cpu_trace.first_mismatching_window(&gpu_trace), None);
+    assert_eq!(cpu_trace, gpu_trace);
+    assert_eq!(cpu, gpu);
+
+    // Running it again gives the very same trace.
+    let (_, gpu_trace_again) = kern
+        .multiexp_reproducible(&pool, g, v, 0, WINDOW_SIZE, CHUNK_SIZE)
+        .unwrap();
+    assert_eq!(gpu_trace, gpu_trace_again);
+}
+
+#[test]
+fn gpu_multiexp_integer_scalars() {
+    fil_logger::maybe_init();
+    const SAMPLES: usize = 1 << 14;
+
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| crate::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = MultiexpKernel::<<Bls12 as Engine>::G1Affine>::create(programs, &devices)
+        .expect("Cannot initialize kernel!");
+    let pool = Worker::new();
+
+    let mut rng = rand::thread_rng();
+    let g = Arc::new(
+        (0..SAMPLES)
+            .map(|_| <Bls12 as Engine>::G1::random(&mut rng).to_affine())
+            .collect::<Vec<_>>(),
+    );
+
+    let v = Arc::new((0..SAMPLES).map(|_| rng.gen()).collect::<Vec<u64>>());
+    let gpu = multiexp_gpu(&pool, (g.clone(), 0), FullDensity, v.clone(), &mut kern).unwrap();
+    let cpu = multiexp_cpu(&pool, (g.clone(), 0), FullDensity, v)
+        .wait()
+        .unwrap();
+    assert_eq!(cpu, gpu);
+
+    let v = Arc::new((0..SAMPLES).map(|_| rng.gen()).collect::<Vec<u128>>());
+    let gpu = multiexp_gpu(&pool, (g.clone(), 0), FullDensity, v.clone(), &mut kern).unwrap();
+    let cpu = multiexp_cpu(&pool, (g, 0), FullDensity, v).wait().unwrap();
+    assert_eq!(cpu, gpu);
+}
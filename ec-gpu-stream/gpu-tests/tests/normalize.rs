@@ -0,0 +1,47 @@
+#![cfg(feature = "opencl")]
+
+use blstrs::{Fp, G1Affine, G1Projective};
+use ec_gpu_gen::{
+    normalize::BatchNormalizeKernel,
+    normalize_cpu::SEGMENT_SIZE,
+    rust_gpu_tools::{opencl, Device, Program},
+    SourceBuilder,
+};
+use group::Group;
+
+#[test]
+fn gpu_batch_normalization_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let mut points = (0..1000)
+        .map(|_| G1Projective::random(&mut rng))
+        .collect::<Vec<_>>();
+    // Points at infinity within a segment and at its borders.
+    for i in [10, SEGMENT_SIZE - 1, SEGMENT_SIZE] {
+        points[i] = G1Projective::identity();
+    }
+    let expected = points.iter().map(G1Affine::from).collect::<Vec<_>>();
+
+    // The kernels of the build script don't contain the normalization, hence the source is
+    // compiled here.
+    let source = SourceBuilder::new()
+        .add_batch_normalization::<G1Affine, Fp>()
+        .build_64_bit_limbs();
+    for device in Device::all() {
+        let opencl_device = device.opencl_device().expect("Not an OpenCL device!");
+        let program =
+            opencl::Program::from_opencl(opencl_device, &source).expect("Cannot create program!");
+        let kern = BatchNormalizeKernel::<G1Affine>::create(Program::Opencl(program), None)
+            .expect("Cannot initialize kernel!");
+
+        // There is nothing to run for an empty input.
+        let empty = kern.normalize::<G1Affine>(&[]);
+        assert!(empty.expect("GPU normalization failed!").is_empty());
+
+        let result = kern
+            .normalize::<G1Affine>(&points)
+            .expect("GPU normalization failed!");
+        assert_eq!(result, expected);
+    }
+}
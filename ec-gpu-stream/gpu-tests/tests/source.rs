@@ -0,0 +1,112 @@
+#![cfg(any(feature = "cuda", feature = "opencl", feature = "host-c"))]
+
+#[path = "../source_builder.rs"]
+mod source_builder;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use blstrs::{Fp, Fp2};
+use ec_gpu::GpuName;
+use ec_gpu_gen::SourceBuilder;
+
+use source_builder::source_builder;
+
+/// If this environment variable is set, the golden files are overwritten with the currently
+/// generated source instead of being compared against it.
+const UPDATE_GOLDEN_ENV: &str = "EC_GPU_UPDATE_GOLDEN";
+
+fn golden_path(file_name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(file_name)
+}
+
+/// Compares the source with the golden file of the given name.
+fn assert_golden(file_name: &str, source: &str) {
+    let path = golden_path(file_name);
+    if env::var(UPDATE_GOLDEN_ENV).is_ok() {
+        fs::write(&path, source).expect("Cannot write golden file!");
+        return;
+    }
+
+    let golden = fs::read_to_string(&path).expect("Cannot read golden file!");
+    // The sources are large, hence only report the first line that differs.
+    if let Some((line_number, (expected, actual))) = golden
+        .lines()
+        .zip(source.lines())
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+    {
+        panic!(
+            "Generated source differs from {} at line {}:\nexpected: {}\n  actual: {}\nIf the \
+             change is intended, rerun the test with `{}=1` to update the golden file.",
+            path.display(),
+            line_number + 1,
+            expected,
+            actual,
+            UPDATE_GOLDEN_ENV
+        );
+    }
+    assert!(
+        golden == source,
+        "Generated source differs from {} in length. If the change is intended, rerun the test \
+         with `{}=1` to update the golden file.",
+        path.display(),
+        UPDATE_GOLDEN_ENV
+    );
+}
+
+#[test]
+fn source_matches_golden_32_bit_limbs() {
+    assert_golden(
+        "kernel_32_bit_limbs.cl",
+        &source_builder().build_32_bit_limbs(),
+    );
+}
+
+#[test]
+fn source_matches_golden_64_bit_limbs() {
+    assert_golden(
+        "kernel_64_bit_limbs.cl",
+        &source_builder().build_64_bit_limbs(),
+    );
+}
+
+#[test]
+fn source_is_deterministic() {
+    for _ in 0..10 {
+        assert_eq!(
+            source_builder().build_32_bit_limbs(),
+            source_builder().build_32_bit_limbs()
+        );
+        assert_eq!(
+            source_builder().build_64_bit_limbs(),
+            source_builder().build_64_bit_limbs()
+        );
+    }
+}
+
+/// Returns the position of the struct definition of the given field.
+fn field_definition(source: &str, name: &str) -> usize {
+    source
+        .find(&format!("}} {};", name))
+        .unwrap_or_else(|| panic!("{} is not defined", name))
+}
+
+#[test]
+fn sub_field_is_defined_before_extension_field() {
+    // The extension field is added first, still the sub-field needs to be defined first.
+    let source = SourceBuilder::new()
+        .add_field::<Fp2>()
+        .add_field::<Fp>()
+        .build_64_bit_limbs();
+    assert!(field_definition(&source, &Fp::name()) < field_definition(&source, &Fp2::name()));
+    assert_eq!(
+        source.matches(&format!("}} {};", Fp::name())).count(),
+        1,
+        "The sub-field must only be defined once"
+    );
+}
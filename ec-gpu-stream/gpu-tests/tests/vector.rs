@@ -0,0 +1,75 @@
+#![cfg(any(feature = "cuda", feature = "opencl"))]
+
+use blstrs::Scalar as Fr;
+use ec_gpu_gen::{fft::FftKernel, fft_cpu, rust_gpu_tools::Device, threadpool::Worker, vector_cpu};
+use ff::Field;
+
+#[test]
+pub fn gpu_vector_ops_consistency() {
+    fil_logger::maybe_init();
+    let mut rng = rand::thread_rng();
+
+    let worker = Worker::new();
+    let devices = Device::all();
+    let programs = devices
+        .iter()
+        .map(|device| ec_gpu_gen::program!(device))
+        .collect::<Result<_, _>>()
+        .expect("Cannot create programs!");
+    let mut kern = FftKernel::<Fr>::create(programs).expect("Cannot initialize kernel!");
+
+    for len in [1, 63, 64, 1000, 1 << 16] {
+        let a = (0..len).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let b = (0..len).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let c = Fr::random(&mut rng);
+        // Zeros must stay zero when inverting, they are set right before the inversion by
+        // multiplying with a mask.
+        let zeros = (0..len).step_by(7).collect::<Vec<_>>();
+        let mut mask = vec![Fr::one(); len];
+        for &i in &zeros {
+            mask[i] = Fr::zero();
+        }
+
+        println!("Testing vector operations for {} elements...", len);
+
+        let mut gpu = kern.upload(&a).expect("GPU upload failed!");
+        let gpu_b = kern.upload(&b).expect("GPU upload failed!");
+        kern.add_assign_buffer(&mut gpu, &gpu_b)
+            .expect("GPU vector operation failed!");
+        kern.mul_assign_buffer(&mut gpu, &gpu_b)
+            .expect("GPU vector operation failed!");
+        kern.sub_assign_buffer(&mut gpu, &gpu_b)
+            .expect("GPU vector operation failed!");
+        kern.scale_buffer(&mut gpu, &c)
+            .expect("GPU vector operation failed!");
+        let gpu_mask = kern.upload(&mask).expect("GPU upload failed!");
+        kern.mul_assign_buffer(&mut gpu, &gpu_mask)
+            .expect("GPU vector operation failed!");
+        kern.batch_inverse_buffer(&mut gpu)
+            .expect("GPU vector operation failed!");
+        let mut gpu_result = vec![Fr::zero(); len];
+        kern.download(&gpu, &mut gpu_result)
+            .expect("GPU download failed!");
+
+        let mut cpu = a.clone();
+        vector_cpu::add_assign(&mut cpu, &worker, &b);
+        vector_cpu::mul_assign(&mut cpu, &worker, &b);
+        vector_cpu::sub_assign(&mut cpu, &worker, &b);
+        fft_cpu::scale(&mut cpu, &worker, &c);
+        for &i in &zeros {
+            cpu[i] = Fr::zero();
+        }
+        vector_cpu::batch_inverse(&mut cpu, &worker);
+        assert_eq!(gpu_result, cpu);
+        for &i in &zeros {
+            assert_eq!(gpu_result[i], Fr::zero(), "Zero at {} was inverted.", i);
+        }
+
+        let gpu_powers = kern.powers_buffer(&c, len).expect("GPU powers failed!");
+        kern.download(&gpu_powers, &mut gpu_result)
+            .expect("GPU download failed!");
+        let mut cpu_powers = vec![Fr::zero(); len];
+        vector_cpu::powers(&mut cpu_powers, &worker, &c);
+        assert_eq!(gpu_result, cpu_powers);
+    }
+}